@@ -0,0 +1,99 @@
+//! Throughput of the formatter on a synthetic, chatty stream: partial text
+//! deltas, assistant messages with tool calls and large tool results.
+//!
+//! Run with `cargo bench`; each case prints the input size and MB/s.
+
+use claude_stream_format::recover::Reassembler;
+use claude_stream_format::sessions::Sessions;
+use claude_stream_format::{Options, StreamFormatter};
+use serde_json::json;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Turns in the generated stream; each is ~16 KB of input.
+const TURNS: usize = 2_000;
+
+fn stream() -> Vec<String> {
+    let mut lines = vec![json!({"type": "system", "subtype": "init", "model": "claude-sonnet-4", "cwd": "/repo", "session_id": "abc"}).to_string()];
+    let output = vec!["x".repeat(60); 200].join("\n");
+    for turn in 0..TURNS {
+        for word in 0..20 {
+            lines.push(
+                json!({"type": "stream_event", "session_id": "abc", "event": {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": format!("word{} ", word)}}})
+                    .to_string(),
+            );
+        }
+        let id = format!("t{}", turn);
+        lines.push(
+            json!({"type": "assistant", "session_id": "abc", "message": {"id": format!("m{}", turn), "content": [
+                {"type": "text", "text": "Let me look at the file. ".repeat(10)},
+                {"type": "tool_use", "id": id, "name": "Read", "input": {"file_path": "/repo/src/main.rs"}},
+            ]}})
+            .to_string(),
+        );
+        lines.push(
+            json!({"type": "user", "session_id": "abc", "message": {"content": [{"type": "tool_result", "tool_use_id": id, "content": output}]}})
+                .to_string(),
+        );
+    }
+    lines.push(json!({"type": "result", "session_id": "abc", "result": "Done.", "num_turns": TURNS}).to_string());
+    lines
+}
+
+/// Runs `f` over `lines` a few times and prints the best rate.
+fn bench(name: &str, lines: &[String], mut f: impl FnMut(&[String])) {
+    let bytes: usize = lines.iter().map(|line| line.len() + 1).sum();
+    let best = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            f(lines);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+    let rate = bytes as f64 / 1e6 / best.as_secs_f64();
+    println!("{:<24} {:>6.1} MB in {:>7.1?}  {:>8.1} MB/s", name, bytes as f64 / 1e6, best, rate);
+}
+
+fn format(options: Options) -> impl FnMut(&[String]) {
+    move |lines| {
+        let mut formatter = StreamFormatter::new(options.clone());
+        for line in lines {
+            black_box(formatter.process_line(line));
+        }
+    }
+}
+
+fn main() {
+    let lines = stream();
+    bench("reassemble", &lines, |lines| {
+        let mut reassembler = Reassembler::default();
+        for line in lines {
+            black_box(reassembler.push(line));
+        }
+    });
+    let messages: Vec<_> = lines.iter().map(|line| Options::default().parse(line)).collect();
+    bench("route --by-session", &lines, |_| {
+        let mut sessions = Sessions::new(Options::default(), true);
+        for msg in &messages {
+            black_box(sessions.route_message(msg.as_ref()));
+        }
+    });
+    bench("format", &lines, format(Options::default()));
+    bench(
+        "format --partial",
+        &lines,
+        format(Options {
+            partial: true,
+            ..Default::default()
+        }),
+    );
+    bench(
+        "format --show-tool-results",
+        &lines,
+        format(Options {
+            show_tool_results: true,
+            ..Default::default()
+        }),
+    );
+}
@@ -0,0 +1,267 @@
+//! `--announce`: key events spoken aloud, e.g. `Edit main.rs` and `Task
+//! complete`, for following a run without watching the screen. Speech
+//! goes through the system's own command, or one set in the config, so
+//! any text-to-speech tool can be plugged in.
+
+use crate::bell::Alert;
+use crate::compact::summarize;
+use crate::config::{AnnounceConfig, Labels};
+use crate::message::{ContentBlock, StreamMessage};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// Something to say.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Announcement {
+    pub text: String,
+    /// Results, errors and prompts, which are always spoken. Others are
+    /// skipped while speech is behind.
+    pub urgent: bool,
+}
+
+impl Announcement {
+    fn new(text: String, urgent: bool) -> Self {
+        Announcement { text, urgent }
+    }
+}
+
+/// Works out what to say about each message.
+#[derive(Debug, Default)]
+pub struct Announcer {
+    /// The tool of each call still waiting for its result, to name the
+    /// one that failed.
+    tools: HashMap<String, String>,
+}
+
+impl Announcer {
+    /// What to say about `msg`, which raised `alerts`.
+    pub fn announce(&mut self, msg: &StreamMessage, alerts: &[Alert], config: &AnnounceConfig, labels: &Labels) -> Vec<Announcement> {
+        let mut said = Vec::new();
+        let blocks = msg.message.iter().flat_map(|message| &message.content);
+        // What a sub-agent does is left out; only its failures are said.
+        let top_level = msg.parent_tool_use_id.is_none();
+        for block in blocks {
+            match block {
+                ContentBlock::Text { text } if config.text && top_level && msg.msg_type == "assistant" => {
+                    said.extend(summarize(text).map(|sentence| Announcement::new(sentence, false)));
+                }
+                ContentBlock::ToolUse { id, name, input } => {
+                    self.tools.insert(id.clone(), name.clone());
+                    if top_level {
+                        said.extend(tool_use(name, input, config).map(|text| Announcement::new(text, false)));
+                    }
+                }
+                ContentBlock::ToolResult { tool_use_id, is_error, .. } => {
+                    let tool = self.tools.remove(tool_use_id);
+                    if *is_error && config.error {
+                        let tool = tool.as_deref().unwrap_or("Tool");
+                        said.push(Announcement::new(format!("{} {}", tool, labels.failed), true));
+                    }
+                }
+                _ => {}
+            }
+        }
+        for alert in alerts {
+            let text = match alert {
+                Alert::OnResult if config.result && msg.is_error_result() => labels.task_failed.clone(),
+                Alert::OnResult if config.result => labels.task_complete.clone(),
+                Alert::OnPermission if config.permission => {
+                    let tool = msg.request.as_ref().and_then(|request| request.tool_name.as_deref());
+                    match tool {
+                        Some(tool) => format!("{}: {}", labels.permission_requested, tool),
+                        None => labels.permission_requested.clone(),
+                    }
+                }
+                Alert::OnBudget if config.budget => labels.over_budget.clone(),
+                _ => continue,
+            };
+            said.push(Announcement::new(text, true));
+        }
+        said
+    }
+}
+
+/// What to say for a call to the tool `name`, if it is announced.
+fn tool_use(name: &str, input: &serde_json::Value, config: &AnnounceConfig) -> Option<String> {
+    let announced = match name {
+        "Edit" | "MultiEdit" | "Write" | "NotebookEdit" => config.edit,
+        "Bash" => config.bash,
+        _ => config.tool,
+    };
+    if !announced {
+        return None;
+    }
+    // Only the name of a file is said, as a whole path takes a while.
+    let path = ["file_path", "notebook_path"].iter().find_map(|key| input.get(key)?.as_str());
+    let file = path.and_then(|path| Path::new(path).file_name()).map(|name| name.to_string_lossy());
+    let name = name.strip_prefix("mcp__").map_or(name, |name| name.rsplit("__").next().unwrap_or(name));
+    Some(match (file, input.get("command").and_then(|command| command.as_str())) {
+        (Some(file), _) => format!("{} {}", name, file),
+        (None, Some(command)) if name == "Bash" => {
+            let words: Vec<&str> = command.split_whitespace().take(2).collect();
+            format!("{} {}", name, words.join(" "))
+        }
+        _ => name.replace('_', " "),
+    })
+}
+
+/// Speaks announcements one at a time on a thread of its own, so the
+/// stream isn't held up while they are said.
+pub struct Speaker {
+    tx: Option<Sender<Announcement>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Speaker {
+    /// Speaks with `command`, run with `sh -c`, or the system's speech
+    /// command if there is none.
+    pub fn spawn(command: Option<String>) -> Speaker {
+        let (tx, rx) = mpsc::channel();
+        let thread = thread::spawn(move || run(&rx, command.as_deref()));
+        Speaker { tx: Some(tx), thread: Some(thread) }
+    }
+
+    pub fn say(&self, announcement: Announcement) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(announcement);
+        }
+    }
+}
+
+impl Drop for Speaker {
+    /// Waits for what is left to be said, such as the result.
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(rx: &Receiver<Announcement>, command: Option<&str>) {
+    while let Ok(first) = rx.recv() {
+        let queued: Vec<Announcement> = std::iter::once(first).chain(rx.try_iter()).collect();
+        for announcement in behind(queued) {
+            if let Err(err) = speak(command, &announcement.text) {
+                eprintln!("claude-stream-format: announcing failed: {}", err);
+                // Nothing more is said, but the rest is still taken so the
+                // stream isn't held up.
+                rx.iter().for_each(drop);
+                return;
+            }
+        }
+    }
+}
+
+/// Of what was queued while speech was behind, the urgent announcements
+/// and the last of the others.
+fn behind(queued: Vec<Announcement>) -> Vec<Announcement> {
+    let last = queued.iter().rposition(|announcement| !announcement.urgent);
+    queued
+        .into_iter()
+        .enumerate()
+        .filter(|(i, announcement)| announcement.urgent || Some(*i) == last)
+        .map(|(_, announcement)| announcement)
+        .collect()
+}
+
+/// Says `text` and waits until it has been said.
+fn speak(command: Option<&str>, text: &str) -> io::Result<()> {
+    if let Some(command) = command {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("CSF_TEXT", text)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that takes its text from CSF_TEXT may not read it.
+            let _ = writeln!(stdin, "{}", text);
+        }
+        child.wait()?;
+        return Ok(());
+    }
+    for (program, args) in system_commands() {
+        let status = Command::new(program)
+            .args(*args)
+            // Text starting with `-` isn't taken for an option.
+            .arg("--")
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        match status {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            status => return status.map(drop),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "no speech command found; set one with --announce-command"))
+}
+
+/// The speech commands to try, in order, each given the text to say last.
+fn system_commands() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "macos") {
+        &[("say", &[])]
+    } else {
+        &[("spd-say", &["--wait"]), ("espeak-ng", &[]), ("espeak", &[])]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announce(announcer: &mut Announcer, line: &str, alerts: &[Alert], config: &AnnounceConfig) -> Vec<String> {
+        let msg: StreamMessage = serde_json::from_str(line).unwrap();
+        announcer.announce(&msg, alerts, config, &Labels::default()).into_iter().map(|announcement| announcement.text).collect()
+    }
+
+    #[test]
+    fn test_announce() {
+        let config = AnnounceConfig::default();
+        let mut announcer = Announcer::default();
+        let calls = r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Fixing it."}, {"type": "tool_use", "id": "t1", "name": "Edit", "input": {"file_path": "/work/src/main.rs"}}, {"type": "tool_use", "id": "t2", "name": "Bash", "input": {"command": "cargo test --all"}}]}}"#;
+        assert_eq!(announce(&mut announcer, calls, &[], &config), ["Edit main.rs"]);
+        let failed = r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t2", "content": "Exit code 101", "is_error": true}]}}"#;
+        assert_eq!(announce(&mut announcer, failed, &[Alert::OnError], &config), ["Bash failed"]);
+        // Each call is forgotten once its result is in.
+        assert_eq!(announcer.tools.keys().collect::<Vec<_>>(), ["t1"]);
+        let prompt = r#"{"type": "control_request", "request_id": "r1", "request": {"subtype": "can_use_tool", "tool_name": "WebFetch", "input": {}}}"#;
+        assert_eq!(announce(&mut announcer, prompt, &[Alert::OnPermission], &config), ["Permission requested: WebFetch"]);
+        let result = r#"{"type": "result", "result": "Fixed."}"#;
+        assert_eq!(announce(&mut announcer, result, &[Alert::OnResult], &config), ["Task complete"]);
+        let failed = r#"{"type": "result", "subtype": "error_max_turns"}"#;
+        assert_eq!(announce(&mut announcer, failed, &[Alert::OnResult, Alert::OnError], &config), ["Task failed"]);
+
+        let config = AnnounceConfig { edit: false, bash: true, text: true, result: false, ..AnnounceConfig::default() };
+        assert_eq!(announce(&mut announcer, calls, &[], &config), ["Fixing it.", "Bash cargo test"]);
+        assert!(announce(&mut announcer, result, &[Alert::OnResult], &config).is_empty());
+    }
+
+    #[test]
+    fn test_behind() {
+        let queued = vec![
+            Announcement::new("Edit a.rs".to_string(), false),
+            Announcement::new("Bash failed".to_string(), true),
+            Announcement::new("Edit b.rs".to_string(), false),
+            Announcement::new("Edit c.rs".to_string(), false),
+        ];
+        let said: Vec<String> = behind(queued).into_iter().map(|announcement| announcement.text).collect();
+        assert_eq!(said, ["Bash failed", "Edit c.rs"]);
+    }
+
+    #[test]
+    fn test_speak() {
+        let path = std::env::temp_dir().join(format!("csf-announce-{}", std::process::id()));
+        let command = format!("cat > {0} && echo \"$CSF_TEXT\" >> {0}", path.display());
+        speak(Some(&command), "Task complete").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Task complete\nTask complete\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
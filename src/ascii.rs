@@ -0,0 +1,197 @@
+//! `--ascii`: plain ASCII in place of emoji and box-drawing characters, for
+//! terminals and fonts that can't show them.
+
+use clap::ValueEnum;
+
+/// Each emoji the formatter prints and its ASCII tag. A tag followed by
+/// a space is padded to [`TAG_WIDTH`], so that the text after it lines up
+/// whatever the tag.
+const TAGS: &[(&str, &str)] = &[
+    ("📖", "[READ]"),
+    ("✏️", "[EDIT]"),
+    ("✏", "[EDIT]"),
+    ("📝", "[WRITE]"),
+    ("💻", "[BASH]"),
+    ("🔍", "[FIND]"),
+    ("🔎", "[SEARCH]"),
+    ("🌐", "[WEB]"),
+    ("📋", "[TODO]"),
+    ("🤖", "[TASK]"),
+    ("📓", "[NOTEBOOK]"),
+    ("🔌", "[MCP]"),
+    ("🔧", "[TOOL]"),
+    ("🚀", "[START]"),
+    ("✅", "[OK]"),
+    ("❌", "[ERROR]"),
+    ("🧠", "[THINK]"),
+    ("🔐", "[ASK]"),
+    ("🚫", "[DENIED]"),
+    ("👤", "[USER]"),
+    ("📊", "[SUMMARY]"),
+    ("📁", "[FILES]"),
+    ("📂", "[LS]"),
+    ("📄", "[PAGE]"),
+    ("⚠️", "[WARN]"),
+    ("⏳", "[IDLE]"),
+    ("🔁", "[REPEAT]"),
+    ("🛑", "[KILL]"),
+    ("🗺️", "[PLAN]"),
+    ("🗺", "[PLAN]"),
+    ("❓", "[?]"),
+    ("🖼️", "[IMAGE]"),
+    ("🖼", "[IMAGE]"),
+    ("🔄", "[MODEL]"),
+    ("↩️", "[RESUMED]"),
+    ("↩", "[RESUMED]"),
+];
+
+/// The tag for emoji not in [`TAGS`], such as those set in the config file.
+const UNKNOWN_TAG: &str = "[*]";
+
+/// The width tags are padded to, that of the longest.
+const TAG_WIDTH: usize = 10;
+
+/// Drawing symbols and their ASCII stand-ins. Symbols that set the layout
+/// (sub-agent bars, `↳` markers) are replaced with a single character so
+/// indentation still lines up.
+const SYMBOLS: &[(&str, &str)] = &[
+    ("×", "x"),
+    ("Σ", "Total:"),
+    ("☑", "[x]"),
+    ("☐", "[ ]"),
+    ("◐", "[~]"),
+    ("│", "|"),
+    ("─", "-"),
+    ("↳", ">"),
+    ("→", "->"),
+    ("—", "--"),
+    ("…", "..."),
+];
+
+/// Whether to use ASCII in place of emoji.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum AsciiChoice {
+    /// ASCII when the terminal doesn't look able to show emoji
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl AsciiChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            AsciiChoice::Always => true,
+            AsciiChoice::Never => false,
+            AsciiChoice::Auto => !supports_emoji(cfg!(windows), |name| std::env::var(name).ok()),
+        }
+    }
+}
+
+/// Whether the terminal described by the environment `var` can likely show
+/// emoji. On Windows only newer terminals can, and they announce
+/// themselves; elsewhere, the Linux console can't, nor can a locale that
+/// names a character set other than UTF-8. The `C` and `POSIX` locales name
+/// none, and are often all a container or CI runner sets up for a terminal
+/// that shows emoji fine.
+fn supports_emoji(windows: bool, var: impl Fn(&str) -> Option<String>) -> bool {
+    let set = |name: &str| var(name).is_some_and(|value| !value.is_empty());
+    if windows {
+        return set("WT_SESSION") || set("TERM_PROGRAM") || var("ConEmuANSI").as_deref() == Some("ON");
+    }
+    if var("TERM").as_deref() == Some("linux") {
+        return false;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter().find_map(|name| var(name).filter(|value| !value.is_empty()));
+    // language_TERRITORY.CHARSET@modifier
+    let charset = locale.and_then(|locale| {
+        let locale = locale.split('@').next().unwrap_or_default().to_ascii_lowercase();
+        locale.split_once('.').map(|(_, charset)| charset.to_string())
+    });
+    charset.is_none_or(|charset| charset == "utf-8" || charset == "utf8")
+}
+
+/// `text` with each emoji and drawing symbol replaced by ASCII. Emoji not
+/// in the table become `[*]`.
+pub fn to_ascii(text: &str) -> String {
+    let tag = |out: &mut String, tag: &str, after: &str| {
+        if after.starts_with(' ') {
+            out.push_str(&format!("{:<width$}", tag, width = TAG_WIDTH));
+        } else {
+            out.push_str(tag);
+        }
+    };
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    'outer: while let Some(c) = rest.chars().next() {
+        if c.is_ascii() {
+            out.push(c);
+            rest = &rest[1..];
+            continue;
+        }
+        for (symbol, replacement) in TAGS {
+            if let Some(after) = rest.strip_prefix(symbol) {
+                tag(&mut out, replacement, after);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        for (symbol, replacement) in SYMBOLS {
+            if let Some(after) = rest.strip_prefix(symbol) {
+                out.push_str(replacement);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        match c {
+            '\u{FE0F}' => {}
+            '\u{1F000}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' => tag(&mut out, UNKNOWN_TAG, &rest[c.len_utf8()..]),
+            c => out.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_to_ascii() {
+        assert_eq!(to_ascii("📖 Read: src/main.rs"), "[READ]     Read: src/main.rs");
+        assert_eq!(to_ascii("✏️ Edit: a.rs"), "[EDIT]     Edit: a.rs");
+        assert_eq!(
+            to_ascii("│ 💻 Bash: ls → exit 1\n│   ↳ no such file"),
+            "| [BASH]     Bash: ls -> exit 1\n|   > no such file"
+        );
+        assert_eq!(to_ascii("👀 Read: café.rs"), "[*]        Read: café.rs");
+        assert_eq!(to_ascii("\x1b[92m✅ Done\x1b[0m"), "\x1b[92m[OK]       Done\x1b[0m");
+        // Only tags followed by text are padded.
+        assert_eq!(to_ascii("Checking the parser ✅"), "Checking the parser [OK]");
+    }
+
+    #[test]
+    fn test_tag_width() {
+        assert!(TAGS.iter().chain([&("", UNKNOWN_TAG)]).all(|(_, tag)| tag.len() <= TAG_WIDTH));
+        assert_eq!(TAGS.iter().map(|(_, tag)| tag.len()).max(), Some(TAG_WIDTH));
+    }
+
+    #[test]
+    fn test_supports_emoji() {
+        let env = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            move |name: &str| vars.get(name).cloned()
+        };
+        assert!(!supports_emoji(true, env(&[])));
+        assert!(supports_emoji(true, env(&[("WT_SESSION", "abc")])));
+        assert!(supports_emoji(false, env(&[("LANG", "en_US.UTF-8")])));
+        assert!(!supports_emoji(false, env(&[("LANG", "en_US.UTF-8"), ("TERM", "linux")])));
+        assert!(!supports_emoji(false, env(&[("LC_ALL", "de_DE.ISO-8859-1"), ("LANG", "en_US.UTF-8")])));
+        assert!(supports_emoji(false, env(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")])));
+        assert!(supports_emoji(false, env(&[("LANG", "C")])));
+        assert!(supports_emoji(false, env(&[("LANG", "C.utf8")])));
+        assert!(supports_emoji(false, env(&[])));
+    }
+}
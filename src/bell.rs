@@ -0,0 +1,99 @@
+//! `--bell`: a terminal bell, or a command of the user's choosing, when
+//! something happens that an unattended session should be alerted about.
+
+use crate::message::{ContentBlock, StreamMessage};
+use clap::ValueEnum;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// An event worth ringing the bell for.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Alert {
+    /// The run finished
+    OnResult,
+    /// The run or a tool call failed
+    OnError,
+    /// Claude asked for permission to use a tool
+    OnPermission,
+    /// The session's cost passed `--max-cost`
+    OnBudget,
+}
+
+impl Alert {
+    /// The alerts raised by one message.
+    pub fn of(msg: &StreamMessage) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        if msg.msg_type == "result" {
+            alerts.push(Alert::OnResult);
+        }
+        let tool_failed = msg.message.iter().flat_map(|m| &m.content).any(|block| {
+            matches!(block, ContentBlock::ToolResult { is_error: true, .. })
+        });
+        if msg.is_error_result() || tool_failed {
+            alerts.push(Alert::OnError);
+        }
+        if msg.request.as_ref().is_some_and(|r| r.subtype.as_deref() == Some("can_use_tool")) {
+            alerts.push(Alert::OnPermission);
+        }
+        alerts
+    }
+
+    /// The name passed to a bell command in `CSF_EVENT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Alert::OnResult => "result",
+            Alert::OnError => "error",
+            Alert::OnPermission => "permission",
+            Alert::OnBudget => "budget",
+        }
+    }
+}
+
+/// Rings the terminal bell on stderr, so it is heard even when stdout is
+/// redirected, or starts `command` with `sh -c` without waiting for it.
+pub fn ring(command: Option<&str>, alert: Alert) -> io::Result<()> {
+    let Some(command) = command else {
+        let mut stderr = io::stderr();
+        stderr.write_all(b"\x07")?;
+        return stderr.flush();
+    };
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("CSF_EVENT", alert.name())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()?;
+    // Reap the command in the background so a slow sound doesn't hold up
+    // the stream.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alerts(line: &str) -> Vec<Alert> {
+        Alert::of(&serde_json::from_str(line).unwrap())
+    }
+
+    #[test]
+    fn test_alerts() {
+        assert_eq!(alerts(r#"{"type": "result", "result": "ok"}"#), vec![Alert::OnResult]);
+        assert_eq!(
+            alerts(r#"{"type": "result", "subtype": "error_max_turns"}"#),
+            vec![Alert::OnResult, Alert::OnError]
+        );
+        assert_eq!(
+            alerts(r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "no such file", "is_error": true}]}}"#),
+            vec![Alert::OnError]
+        );
+        assert_eq!(
+            alerts(r#"{"type": "control_request", "request_id": "r1", "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {}}}"#),
+            vec![Alert::OnPermission]
+        );
+        assert_eq!(alerts(r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "hi"}]}}"#), vec![]);
+    }
+}
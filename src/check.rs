@@ -0,0 +1,216 @@
+//! The `check` subcommand: reads a stream without formatting it and
+//! reports what is in it, counting each type of message and listing the
+//! lines that aren't valid stream-json, for tracking down whatever wrote
+//! them.
+
+use crate::config::Labels;
+use crate::format::{plural, truncate};
+use crate::lines::lines;
+use crate::message::StreamMessage;
+use crate::style::Theme;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+
+/// The message types the formatter understands.
+pub const KNOWN_TYPES: [&str; 7] =
+    ["system", "assistant", "user", "result", "stream_event", "control_request", "control_response"];
+
+/// Lines that failed to parse listed in full; the rest are only counted.
+const MAX_FAILURES: usize = 20;
+
+/// How much of a bad line is quoted.
+const QUOTE_WIDTH: usize = 60;
+
+/// A line that isn't a stream-json message.
+#[derive(Debug, PartialEq)]
+pub struct Failure {
+    /// Counted from 1.
+    pub line: usize,
+    pub reason: String,
+    pub text: String,
+}
+
+/// What a stream held.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub lines: usize,
+    pub blank: usize,
+    /// Messages of each type, as `type` or `type/subtype`.
+    pub types: BTreeMap<String, usize>,
+    /// Messages of types the formatter doesn't know, with the line of the
+    /// first.
+    pub unknown: BTreeMap<String, (usize, usize)>,
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    pub fn read(input: impl BufRead) -> io::Result<Report> {
+        let mut report = Report::default();
+        for line in lines(input) {
+            let line = line?;
+            match line.text() {
+                Ok(text) => report.record(text),
+                Err(err) => {
+                    report.lines += 1;
+                    let reason = format!("invalid UTF-8 at column {}", err.valid_up_to() + 1);
+                    report.failures.push(Failure { line: report.lines, reason, text: line.lossy().trim().to_string() });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    fn record(&mut self, line: &str) {
+        self.lines += 1;
+        let text = line.trim();
+        if text.is_empty() {
+            self.blank += 1;
+            return;
+        }
+        let msg = match serde_json::from_str::<StreamMessage>(text) {
+            Ok(msg) => msg,
+            Err(err) => {
+                let reason = err.to_string();
+                // The stream is one message to a line, so only the column
+                // says anything.
+                let reason = reason.strip_suffix(&format!(" at line 1 column {}", err.column())).map_or(reason.clone(), |reason| {
+                    format!("{} at column {}", reason, err.column())
+                });
+                self.failures.push(Failure { line: self.lines, reason, text: text.to_string() });
+                return;
+            }
+        };
+        let name = match &msg.subtype {
+            Some(subtype) => format!("{}/{}", msg.msg_type, subtype),
+            None => msg.msg_type.clone(),
+        };
+        *self.types.entry(name).or_insert(0) += 1;
+        if !KNOWN_TYPES.contains(&msg.msg_type.as_str()) {
+            self.unknown.entry(msg.msg_type).or_insert((0, self.lines)).0 += 1;
+        }
+    }
+
+    /// Whether every line was a message or blank.
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn render(&self, theme: &Theme, labels: &Labels) -> String {
+        let messages = self.lines - self.blank - self.failures.len();
+        let mut heading = format!("📋 {}, {}", plural(self.lines, &labels.line), plural(messages, &labels.message));
+        if self.blank > 0 {
+            heading.push_str(&format!(", {} {}", self.blank, labels.blank));
+        }
+        let mut lines = vec![theme.heading.paint(&heading)];
+
+        let mut types: Vec<(&String, &usize)> = self.types.iter().collect();
+        types.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        lines.extend(aligned(types.into_iter().map(|(name, count)| (name.clone(), count.to_string()))));
+
+        if !self.unknown.is_empty() {
+            lines.push(theme.heading.paint(&format!("⚠️ {}", labels.unknown_types)));
+            lines.extend(aligned(self.unknown.iter().map(|(name, (count, first))| {
+                (name.clone(), format!("{}  ({} {})", count, labels.first_on_line, first))
+            })));
+        }
+
+        if self.failures.is_empty() {
+            lines.push(theme.result.paint(&format!("✅ {}", labels.every_line_parsed)));
+        } else {
+            let count = self.failures.len();
+            lines.push(theme.error.paint(&format!("❌ {} {}", plural(count, &labels.line), labels.failed_to_parse)));
+            for failure in self.failures.iter().take(MAX_FAILURES) {
+                lines.push(format!("   {} {}: {}", labels.line.one, failure.line, failure.reason));
+                lines.push(theme.comment.paint(&format!("      {}", truncate(&failure.text, QUOTE_WIDTH))));
+            }
+            if count > MAX_FAILURES {
+                lines.push(theme.comment.paint(&format!("   … {} {}", count - MAX_FAILURES, labels.more)));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+
+/// Names and counts in two columns, the counts right-aligned by their
+/// leading number.
+fn aligned(rows: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let rows: Vec<(String, String)> = rows.collect();
+    let number = |value: &str| value.find(' ').unwrap_or(value.len());
+    let name_width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    let number_width = rows.iter().map(|(_, value)| number(value)).max().unwrap_or(0);
+    rows.iter()
+        .map(|(name, value)| {
+            format!(
+                "   {}{}  {}{}",
+                name,
+                " ".repeat(name_width - name.chars().count()),
+                " ".repeat(number_width - number(value)),
+                value
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let input = [
+            r#"{"type": "system", "subtype": "init", "model": "claude-sonnet-4"}"#,
+            r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Hi"}]}}"#,
+            "",
+            "hook: formatting src/main.rs",
+            r#"{"type": "hook_output", "text": "ok"}"#,
+            r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Bye"}]}}"#,
+            r#"{"type": "assistant", "message": {"content": [{"type": "te"#,
+            r#"{"message": {"content": []}}"#,
+            r#"{"type": "result", "subtype": "success", "result": "Bye"}"#,
+        ]
+        .join("\n");
+        let report = Report::read(input.as_bytes()).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.render(&Theme::default(), &Labels::default()),
+            "📋 9 lines, 5 messages, 1 blank
+   assistant       2
+   hook_output     1
+   result/success  1
+   system/init     1
+⚠️ Unknown types
+   hook_output  1  (first on line 5)
+❌ 3 lines failed to parse
+   line 4: expected value at column 1
+      hook: formatting src/main.rs
+   line 7: EOF while parsing a string at column 58
+      {\"type\": \"assistant\", \"message\": {\"content\": [{\"type\": \"te
+   line 8: missing field `type` at column 28
+      {\"message\": {\"content\": []}}"
+        );
+    }
+
+    #[test]
+    fn test_valid() {
+        let report = Report::read(r#"{"type": "user", "message": {"content": "Hi"}}"#.as_bytes()).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.render(&Theme::default(), &Labels::default()), "📋 1 line, 1 message\n   user  1\n✅ Every line parsed");
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let input: &[u8] = b"{\"type\": \"user\", \"message\": {\"content\": \"\xff\"}}\n{\"type\": \"result\"}";
+        let report = Report::read(input).unwrap();
+        assert_eq!(report.lines, 2);
+        assert_eq!(report.types.get("result"), Some(&1));
+        assert_eq!(
+            report.failures,
+            [Failure {
+                line: 1,
+                reason: "invalid UTF-8 at column 42".to_string(),
+                text: "{\"type\": \"user\", \"message\": {\"content\": \"\u{FFFD}\"}}".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! `--copy-result`: puts text on the system clipboard, through the
+//! platform's own copy command, or over SSH (or with no command found)
+//! with an OSC 52 escape that asks the terminal itself to copy it.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Copies `text` to the clipboard.
+pub fn copy(text: &str) -> io::Result<()> {
+    let remote = std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some();
+    if !remote {
+        for (program, args) in commands() {
+            match pipe(program, args, text) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                result => return result,
+            }
+        }
+    }
+    osc52(text)
+}
+
+/// Copy commands to try in turn, for the platform and display server.
+fn commands() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        return vec![("pbcopy", &[])];
+    }
+    if cfg!(windows) {
+        return vec![("clip", &[])];
+    }
+    let mut commands: Vec<(&str, &[&str])> = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        commands.push(("wl-copy", &[]));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        commands.push(("xclip", &["-selection", "clipboard"]));
+        commands.push(("xsel", &["--clipboard", "--input"]));
+    }
+    commands
+}
+
+fn pipe(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    child.stdin.take().expect("piped stdin").write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} failed: {}", program, status)));
+    }
+    Ok(())
+}
+
+/// Sends the OSC 52 escape to the terminal, which copies the text if it
+/// allows it. It is written to the terminal directly, so it stays out of
+/// piped or logged output.
+fn osc52(text: &str) -> io::Result<()> {
+    let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some());
+    #[cfg(unix)]
+    if let Ok(mut tty) = std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        return tty.write_all(sequence.as_bytes());
+    }
+    let mut stderr = io::stderr();
+    if !stderr.is_terminal() {
+        return Err(io::Error::other("no clipboard command or terminal to copy with"));
+    }
+    stderr.write_all(sequence.as_bytes())
+}
+
+/// The OSC 52 escape setting the clipboard to `text`, wrapped for tmux to
+/// pass on to the terminal outside it.
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52_sequence("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+}
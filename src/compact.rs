@@ -0,0 +1,266 @@
+//! `--compact`: each assistant turn condensed to one line, the first
+//! sentence of what it said and a count of the tools it called, e.g.
+//! `Fixing the parser… [2 edits, 1 bash]`.
+
+use crate::config::Labels;
+use crate::format::{plural, room_for, truncate};
+use crate::style::Theme;
+
+/// One turn: what the agent said, then every tool call until it next
+/// says something.
+#[derive(Debug, Default)]
+pub struct Turn {
+    /// The id of the message that started the turn; the other lines of a
+    /// split message carry on the same turn.
+    pub message_id: Option<String>,
+    pub sentence: Option<String>,
+    /// Calls by kind, in the order each kind was first called.
+    tools: Vec<(&'static str, usize)>,
+}
+
+impl Turn {
+    pub fn new(message_id: Option<String>) -> Self {
+        Turn {
+            message_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sentence.is_none() && self.tools.is_empty()
+    }
+
+    /// Counts a call to the tool `name`.
+    pub fn add_tool(&mut self, name: &str) {
+        let noun = tool_noun(name);
+        match self.tools.iter_mut().find(|(kind, _)| *kind == noun) {
+            Some((_, count)) => *count += 1,
+            None => self.tools.push((noun, 1)),
+        }
+    }
+
+    /// The turn's line, cut to `width` columns (0 for unlimited) keeping
+    /// the counts whole.
+    pub fn render(&self, theme: &Theme, labels: &Labels, width: usize) -> String {
+        let counts: Vec<String> = self.tools.iter().map(|(noun, count)| plural(*count, labels.compact.get(noun))).collect();
+        let counts = Some(format!("[{}]", counts.join(", "))).filter(|_| !counts.is_empty());
+        match (&self.sentence, counts) {
+            (Some(sentence), Some(counts)) => {
+                let sentence = truncate(sentence, room_for(width, &format!(" {}", counts)));
+                format!("{} {}", theme.text.paint(&sentence), theme.comment.paint(&counts))
+            }
+            (Some(sentence), None) => theme.text.paint(&truncate(sentence, width)),
+            (None, Some(counts)) => theme.comment.paint(&counts),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// A line summing up `text`: its first sentence of prose, passing over
+/// code blocks, headings, lists and tables, or failing those its longest
+/// line. Ends with `…` if there is more.
+pub fn summarize(text: &str) -> Option<String> {
+    let lines = classify(text);
+    let shown = lines.iter().filter(|line| !matches!(line, Line::Blank)).count();
+    if let Some(start) = lines.iter().position(|line| matches!(line, Line::Prose(_))) {
+        // A paragraph's lines are one run of text, wrapped.
+        let paragraph: Vec<&str> = lines[start..]
+            .iter()
+            .map_while(|line| match line {
+                Line::Prose(text) => Some(*text),
+                _ => None,
+            })
+            .collect();
+        let joined = paragraph.join(" ").replace("**", "");
+        let (sentence, rest) = split_sentence(&joined);
+        return Some(with_more(sentence, !rest.is_empty() || shown > paragraph.len()));
+    }
+    // Code only if there is nothing else.
+    let longest = |code: bool| {
+        lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Markup(text) if !code => Some(*text),
+                Line::Code(text) if code => Some(*text),
+                _ => None,
+            })
+            .filter(|text| text.chars().any(char::is_alphanumeric))
+            .rev()
+            .max_by_key(|text| text.chars().count())
+    };
+    let line = longest(false).or_else(|| longest(true))?;
+    Some(with_more(line, shown > 1))
+}
+
+/// A line of a message, by what it holds.
+enum Line<'a> {
+    Blank,
+    /// The ``` or ~~~ opening or closing a code block.
+    Fence,
+    Code(&'a str),
+    /// The text of a heading, list item, table row or rule.
+    Markup(&'a str),
+    Prose(&'a str),
+}
+
+fn classify(text: &str) -> Vec<Line<'_>> {
+    let mut fence: Option<&str> = None;
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim();
+            if let Some(marker) = fence {
+                // Closed by a run of the same character at least as long,
+                // with nothing after it.
+                let run = trimmed.trim_start_matches(marker.chars().next().unwrap_or('`'));
+                if run.is_empty() && trimmed.len() >= marker.len() {
+                    fence = None;
+                    return Line::Fence;
+                }
+                return Line::Code(trimmed);
+            }
+            if let Some(marker) = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker)) {
+                let len = trimmed.len() - trimmed.trim_start_matches(marker.chars().next().unwrap_or('`')).len();
+                fence = Some(&trimmed[..len]);
+                return Line::Fence;
+            }
+            if trimmed.is_empty() {
+                Line::Blank
+            } else if line.starts_with("    ") || line.starts_with('\t') {
+                Line::Code(trimmed)
+            } else if let Some(text) = markup(trimmed) {
+                Line::Markup(text)
+            } else if !trimmed.chars().any(char::is_alphabetic) {
+                Line::Markup(trimmed)
+            } else {
+                Line::Prose(trimmed.trim_start_matches(['>', ' ']))
+            }
+        })
+        .collect()
+}
+
+/// The text of a heading, list item or table row, without its markup.
+fn markup(line: &str) -> Option<&str> {
+    if line.starts_with('#') {
+        return Some(line.trim_start_matches('#').trim());
+    }
+    if line.starts_with('|') {
+        return Some(line.trim_matches('|').trim());
+    }
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|bullet| line.strip_prefix(bullet)) {
+        return Some(item.trim());
+    }
+    let number = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    if number.len() < line.len() {
+        return number.strip_prefix(". ").or_else(|| number.strip_prefix(") ")).map(str::trim);
+    }
+    None
+}
+
+/// Words ending in a full stop that don't end a sentence.
+const ABBREVIATIONS: [&str; 5] = ["e.g.", "i.e.", "etc.", "vs.", "cf."];
+
+/// The first sentence of `text`, without the stop ending it, and the rest.
+/// A colon only ends one at the end, as in `Root cause: the cache key…`
+/// it doesn't.
+fn split_sentence(text: &str) -> (&str, &str) {
+    let end = text.char_indices().find(|(i, c)| {
+        let after = i + c.len_utf8();
+        let word = text[..after].rsplit(char::is_whitespace).next().unwrap_or("");
+        matches!(c, '.' | '!' | '?')
+            && text[after..].starts_with(char::is_whitespace)
+            && !ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+    });
+    match end {
+        Some((i, c)) => (text[..i].trim_end(), text[i + c.len_utf8()..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+/// `text`, followed by `…` if there is more.
+fn with_more(text: &str, more: bool) -> String {
+    if more {
+        format!("{}…", text.trim_end_matches(['.', ':', '!', '?', ' ']))
+    } else {
+        text.to_string()
+    }
+}
+
+/// What a call to the tool `name` is counted as, a key of
+/// [`CompactLabels`](crate::config::CompactLabels).
+fn tool_noun(name: &str) -> &'static str {
+    match name {
+        "Read" => "read",
+        "Edit" | "MultiEdit" | "NotebookEdit" => "edit",
+        "Write" => "write",
+        "Bash" | "BashOutput" | "KillShell" => "bash",
+        "Grep" | "Glob" | "LS" => "search",
+        "WebFetch" | "WebSearch" => "web",
+        "Task" | "Agent" | "dispatch_agent" => "task",
+        "TodoWrite" => "todo",
+        name if name.starts_with("mcp__") => "mcp",
+        _ => "tool",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize("Fixing the parser. Then the tests.").unwrap(), "Fixing the parser…");
+        assert_eq!(summarize("Let me look at the file:\n\n```rs").unwrap(), "Let me look at the file…");
+        assert_eq!(summarize("Version 1.2 is out").unwrap(), "Version 1.2 is out");
+        assert_eq!(summarize("Run the slow ones too, e.g. the fuzz tests. Then commit.").unwrap(), "Run the slow ones too, e.g. the fuzz tests…");
+        assert_eq!(summarize("  \n"), None);
+        assert_eq!(summarize("```\n\n```"), None);
+        assert_eq!(summarize("## Plan\n1. Parse").unwrap(), "Parse…");
+        // A longer fence closes a shorter one; a shorter one doesn't.
+        assert_eq!(summarize("```\nlet a = 1;\n`````\nSet a.").unwrap(), "Set a…");
+        assert_eq!(summarize("````\n```\nSet a.\n````\nDone.").unwrap(), "Done…");
+    }
+
+    /// Openings of assistant messages in the shapes sessions have: prose
+    /// after code, headings, lists, tables and quotes.
+    #[test]
+    fn test_summarize_transcripts() {
+        let cases = [
+            (
+                "```rust\nfn parse(input: &str) -> Result<Ast> {\n```\n\nThe parser returned early on an empty group, so nested groups were\ndropped. I'll fix that next.",
+                "The parser returned early on an empty group, so nested groups were dropped…",
+            ),
+            (
+                "## Summary\n\n- Fixed the off-by-one in `Lexer::peek`\n- Added a regression test\n\nAll 214 tests pass now.",
+                "All 214 tests pass now…",
+            ),
+            ("**Root cause:** the cache key ignored the locale.", "Root cause: the cache key ignored the locale."),
+            (
+                "1. Read the config loader\n2. Trace where `timeout` is set\n3. Fix it",
+                "Trace where `timeout` is set…",
+            ),
+            (
+                "| File | Change |\n|------|--------|\n| src/lib.rs | export `Parser` |",
+                "src/lib.rs | export `Parser`…",
+            ),
+            ("```diff\n-    let n = items.len() - 1;\n+    let n = items.len();\n```", "-    let n = items.len() - 1;…"),
+            ("> Note: this needs a migration.\n\nRunning it now.", "Note: this needs a migration…"),
+            ("Done.", "Done."),
+        ];
+        for (text, summary) in cases {
+            assert_eq!(summarize(text).unwrap(), summary, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let theme = Theme::default();
+        let mut turn = Turn::new(None);
+        for name in ["Edit", "Bash", "MultiEdit", "Grep", "Glob"] {
+            turn.add_tool(name);
+        }
+        assert_eq!(turn.render(&theme, &Labels::default(), 0), "[2 edits, 1 bash, 2 searches]");
+        turn.sentence = summarize("Fixing the parser so that it handles nested groups.");
+        assert_eq!(turn.render(&theme, &Labels::default(), 0), "Fixing the parser so that it handles nested groups. [2 edits, 1 bash, 2 searches]");
+        assert_eq!(turn.render(&theme, &Labels::default(), 50), "Fixing the parser... [2 edits, 1 bash, 2 searches]");
+    }
+}
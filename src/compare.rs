@@ -0,0 +1,290 @@
+//! The `diff` subcommand: compares two recorded runs, such as `--tee`
+//! copies of one prompt given to two models, by turns, tool use, files
+//! touched, cost and result, lining up their tool calls.
+
+use crate::config::{Config, Labels};
+use crate::diff::{diff_lines, DiffLine};
+use crate::format::{display_width, format_duration, format_tool, format_usage, plural, short_model, Limits};
+use crate::lines::lines;
+use crate::message::{ContentBlock, StreamMessage, Usage};
+use crate::style::Theme;
+use crate::summary::Summary;
+use std::collections::BTreeSet;
+use std::io::{self, BufRead};
+
+/// Identical calls shown either side of a difference; longer runs of them
+/// are collapsed.
+const CONTEXT: usize = 2;
+
+/// What one run did, read from its stream-json.
+#[derive(Default)]
+pub struct Run {
+    pub model: Option<String>,
+    pub summary: Summary,
+    /// The line for each tool call, in order.
+    pub calls: Vec<String>,
+    /// The text of the last result.
+    pub result: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl Run {
+    /// Reads a run, skipping lines that aren't stream-json.
+    pub fn read(input: impl BufRead, config: &Config) -> io::Result<Run> {
+        let mut run = Run::default();
+        for line in lines(input) {
+            // A line that isn't UTF-8 is skipped like any other bad line.
+            if let Ok(msg) = serde_json::from_str::<StreamMessage>(line?.text().unwrap_or_default()) {
+                run.record(&msg, config);
+            }
+        }
+        Ok(run)
+    }
+
+    fn record(&mut self, msg: &StreamMessage, config: &Config) {
+        match msg.msg_type.as_str() {
+            "system" if msg.subtype.as_deref() == Some("init") && self.model.is_none() => {
+                self.model = msg.model.clone();
+            }
+            "assistant" => {
+                let limits = Limits::default();
+                for block in msg.message.iter().flat_map(|message| &message.content) {
+                    if let ContentBlock::ToolUse { name, input, .. } = block {
+                        self.calls.push(format_tool(name, input, config, &limits));
+                    }
+                }
+            }
+            "result" => {
+                self.result = msg.result_text(&config.labels);
+                self.duration_ms += msg.duration_ms.unwrap_or(0);
+            }
+            _ => {}
+        }
+        self.summary.record(msg);
+    }
+
+    /// Token usage as results report it, or summed from the messages if
+    /// the run never finished.
+    fn usage(&self) -> &Usage {
+        if self.summary.usage != Usage::default() {
+            &self.summary.usage
+        } else {
+            &self.summary.message_usage
+        }
+    }
+}
+
+/// Whether the runs made different tool calls or came to different results.
+pub fn differ(old: &Run, new: &Run) -> bool {
+    old.calls != new.calls || old.result != new.result
+}
+
+/// Sets `old` and `new`, named `names`, side by side: a table of the
+/// totals and how they changed, the files only one touched, a diff of
+/// their tool calls and one of their results.
+pub fn render(old: &Run, new: &Run, names: (&str, &str), theme: &Theme, labels: &Labels) -> String {
+    let mut lines = vec![theme.heading.paint(&format!("📊 {} → {}", names.0, names.1))];
+    lines.extend(table(old, new, labels));
+
+    let old_files = &old.summary.files;
+    let new_files = &new.summary.files;
+    for (name, only) in [(names.0, old_files.difference(new_files)), (names.1, new_files.difference(old_files))] {
+        let only: Vec<&String> = only.collect();
+        if !only.is_empty() {
+            lines.push(theme.heading.paint(&format!("📁 {} {}", labels.only_in, name)));
+            lines.extend(only.iter().map(|path| format!("   {}", path)));
+        }
+    }
+
+    lines.push(theme.heading.paint(&format!("🔧 {}", labels.tool_calls)));
+    lines.extend(calls_diff(&old.calls, &new.calls, theme, labels));
+
+    let result = |run: &Run| run.result.clone().unwrap_or_default();
+    let heading = theme.heading.paint(&format!("✅ {}", labels.result));
+    if old.result == new.result {
+        lines.push(format!("{} {}", heading, theme.comment.paint(&format!("({})", labels.the_same))));
+    } else {
+        lines.push(heading);
+        lines.extend(diff_lines(&result(old), &result(new)).into_iter().map(|line| paint(line, theme)));
+    }
+    lines.join("\n")
+}
+
+/// The totals of each run with the change between them, aligned.
+fn table(old: &Run, new: &Run, labels: &Labels) -> Vec<String> {
+    let model = |run: &Run| run.model.as_deref().map_or("-".to_string(), |model| short_model(model).to_string());
+    let status = |run: &Run| if run.summary.is_error { &labels.failed } else { &labels.succeeded }.clone();
+    let cost = |run: &Run| format!("${:.2}", run.summary.running_cost());
+
+    let mut rows = vec![
+        (labels.model.clone(), model(old), model(new), String::new()),
+        (labels.result.clone(), status(old), status(new), String::new()),
+        count_row(&labels.turns, old.summary.num_turns as usize, new.summary.num_turns as usize),
+        count_row(&labels.assistant_messages, old.summary.assistant_messages, new.summary.assistant_messages),
+        count_row(&labels.tool_calls, old.summary.tool_calls(), new.summary.tool_calls()),
+    ];
+    let tools: BTreeSet<&String> = old.summary.tool_counts.keys().chain(new.summary.tool_counts.keys()).collect();
+    let count = |run: &Run, tool: &String| run.summary.tool_counts.get(tool).copied().unwrap_or(0);
+    let mut tools: Vec<&String> = tools.into_iter().collect();
+    tools.sort_by_key(|tool| std::cmp::Reverse(count(old, tool).max(count(new, tool))));
+    rows.extend(tools.into_iter().map(|tool| count_row(&format!("  {}", tool), count(old, tool), count(new, tool))));
+    rows.push(count_row(&labels.files_touched, old.summary.files.len(), new.summary.files.len()));
+    rows.push((labels.tokens.clone(), format_usage(old.usage(), labels), format_usage(new.usage(), labels), String::new()));
+    let cost_change = new.summary.running_cost() - old.summary.running_cost();
+    let cost_change = match cost_change {
+        change if change.abs() < 0.005 => String::new(),
+        change if change > 0.0 => format!("+${:.2}", change),
+        change => format!("-${:.2}", -change),
+    };
+    rows.push((labels.cost.clone(), cost(old), cost(new), cost_change));
+    rows.push((
+        labels.duration.clone(),
+        format_duration(old.duration_ms),
+        format_duration(new.duration_ms),
+        String::new(),
+    ));
+
+    let width = |column: fn(&(String, String, String, String)) -> &String| {
+        rows.iter().map(|row| display_width(column(row))).max().unwrap_or(0)
+    };
+    let (label_width, old_width, new_width) = (width(|row| &row.0), width(|row| &row.1), width(|row| &row.2));
+    rows.iter()
+        .map(|(label, old, new, change)| {
+            let line = format!(
+                "   {}{}  {}{}  →  {}{}  {}",
+                label,
+                " ".repeat(label_width - display_width(label)),
+                " ".repeat(old_width - display_width(old)),
+                old,
+                " ".repeat(new_width - display_width(new)),
+                new,
+                change
+            );
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// A row for a count, with its change such as `+3`.
+fn count_row(label: &str, old: usize, new: usize) -> (String, String, String, String) {
+    let change = match new as i64 - old as i64 {
+        0 => String::new(),
+        change if change > 0 => format!("+{}", change),
+        change => change.to_string(),
+    };
+    (label.to_string(), old.to_string(), new.to_string(), change)
+}
+
+/// The tool calls of both runs lined up, with runs of identical calls
+/// away from any difference collapsed.
+fn calls_diff(old: &[String], new: &[String], theme: &Theme, labels: &Labels) -> Vec<String> {
+    let (old, new) = (old.join("\n"), new.join("\n"));
+    let diff = diff_lines(&old, &new);
+    let changed: Vec<bool> = diff.iter().map(|line| !matches!(line, DiffLine::Same(_))).collect();
+    let near_change = |i: usize| changed[i.saturating_sub(CONTEXT)..(i + CONTEXT + 1).min(changed.len())].contains(&true);
+
+    let mut lines = Vec::new();
+    let mut skipped = 0;
+    for (i, line) in diff.into_iter().enumerate() {
+        if matches!(line, DiffLine::Same(_)) && !near_change(i) {
+            skipped += 1;
+            continue;
+        }
+        if skipped > 0 {
+            lines.push(theme.comment.paint(&format!("      … {}", plural(skipped, &labels.identical_call))));
+            skipped = 0;
+        }
+        lines.push(paint(line, theme));
+    }
+    if skipped > 0 {
+        lines.push(theme.comment.paint(&format!("      … {}", plural(skipped, &labels.identical_call))));
+    }
+    lines
+}
+
+fn paint(line: DiffLine, theme: &Theme) -> String {
+    match line {
+        DiffLine::Same(l) => theme.comment.paint(&format!("      {}", l)),
+        DiffLine::Removed(l) => theme.removed.paint(&format!("    - {}", l)),
+        DiffLine::Added(l) => theme.added.paint(&format!("    + {}", l)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(lines: &[&str]) -> Run {
+        Run::read(lines.join("\n").as_bytes(), &Config::default()).unwrap()
+    }
+
+    fn read(path: &str) -> String {
+        format!(r#"{{"type": "assistant", "message": {{"content": [{{"type": "tool_use", "name": "Read", "input": {{"file_path": "{}"}}}}]}}}}"#, path)
+    }
+
+    #[test]
+    fn test_render() {
+        let init = |model: &str| format!(r#"{{"type": "system", "subtype": "init", "model": "{}"}}"#, model);
+        let bash = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Bash", "input": {"command": "cargo test"}}]}}"#;
+        let old = run(&[
+            &init("claude-sonnet-4-20250514"),
+            &read("/a.rs"),
+            &read("/b.rs"),
+            &read("/c.rs"),
+            &read("/d.rs"),
+            &read("/e.rs"),
+            bash,
+            r#"{"type": "result", "result": "Fixed it.\nAll tests pass.", "num_turns": 3, "total_cost_usd": 0.1, "duration_ms": 12000}"#,
+        ]);
+        let new = run(&[
+            &init("claude-opus-4-20250514"),
+            &read("/a.rs"),
+            &read("/b.rs"),
+            &read("/c.rs"),
+            &read("/d.rs"),
+            &read("/e.rs"),
+            r#"{"type": "result", "result": "Fixed it.", "num_turns": 2, "total_cost_usd": 0.25, "duration_ms": 9000}"#,
+        ]);
+        assert!(differ(&old, &new));
+        assert_eq!(
+            render(&old, &new, ("old.jsonl", "new.jsonl"), &Theme::default(), &Labels::default()),
+            "📊 old.jsonl → new.jsonl
+   Model                sonnet-4  →     opus-4
+   Result              succeeded  →  succeeded
+   Turns                       3  →          2  -1
+   Assistant messages          6  →          5  -1
+   Tool calls                  6  →          5  -1
+     Read                      5  →          5
+     Bash                      1  →          0  -1
+   Files touched               5  →          5
+   Tokens                0→0 tok  →    0→0 tok
+   Cost                    $0.10  →      $0.25  +$0.15
+   Duration                12.0s  →       9.0s
+🔧 Tool calls
+      … 3 identical calls
+      📖 Read: /d.rs
+      📖 Read: /e.rs
+    - 💻 Bash: cargo test
+✅ Result
+      Fixed it.
+    - All tests pass."
+        );
+    }
+
+    #[test]
+    fn test_only_in() {
+        let old = run(&[&read("/a.rs")]);
+        let new = run(&[&read("/b.rs")]);
+        let rendered = render(&old, &new, ("1", "2"), &Theme::default(), &Labels::default());
+        assert!(rendered.contains("📁 Only in 1\n   /a.rs\n📁 Only in 2\n   /b.rs\n"));
+        assert!(rendered.ends_with("✅ Result (the same)"));
+        assert!(!differ(&run(&[&read("/a.rs")]), &old));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let input = [read("/a.rs").as_bytes(), b"\xff\xfe", read("/b.rs").as_bytes()].join(&b'\n');
+        let run = Run::read(input.as_slice(), &Config::default()).unwrap();
+        assert_eq!(run.summary.files.len(), 2);
+    }
+}
@@ -0,0 +1,535 @@
+//! The optional TOML config file.
+//!
+//! ```toml
+//! [tools.Read]
+//! emoji = "👀"
+//!
+//! [tools.mcp__github__create_issue]
+//! emoji = "🐙"
+//! label = "Issue"
+//! fields = ["repo", "title"]
+//! max_len = 60
+//!
+//! [servers.linear]
+//! emoji = "📐"
+//! label = "Linear"
+//!
+//! [templates]
+//! Bash = "$ {input.command|truncate:60}"
+//!
+//! [truncate]
+//! command = 120
+//! result = 0
+//!
+//! [labels]
+//! done = "Fertig"
+//! error = "Fehler"
+//!
+//! [announce]
+//! command = "espeak -s 200"
+//! bash = true
+//! ```
+
+use crate::template::Template;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Per-tool display overrides, keyed by tool name.
+    pub tools: HashMap<String, ToolConfig>,
+    /// Display overrides for every tool of an MCP server, keyed by server
+    /// name. A `label` here replaces the server name; `tools` entries take
+    /// precedence.
+    pub servers: HashMap<String, ToolConfig>,
+    /// A template for the whole line of each tool, keyed by tool name.
+    /// Takes precedence over `tools` and `servers`.
+    pub templates: HashMap<String, Template>,
+    /// How much of long values to show.
+    pub truncate: TruncateConfig,
+    /// The words printed around events.
+    pub labels: Labels,
+    /// What `--announce` speaks.
+    pub announce: AnnounceConfig,
+}
+
+/// The words printed around events, from the `[labels]` table, e.g. to
+/// translate them. Unset keys keep the English defaults; tool names are
+/// set with `label` in `[tools]`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Labels {
+    /// The result of a successful run: `✅ Done: ...`.
+    pub done: String,
+    /// The result of a failed run: `❌ Error: ...`.
+    pub error: String,
+    /// Notification and webhook titles: `✅ Claude finished in 12.4s`.
+    pub claude_finished: String,
+    pub claude_failed: String,
+    pub permission_requested: String,
+    pub permission_granted: String,
+    pub denied: String,
+    /// A finished Bash command: `💻 Bash: cargo test → exit 1`.
+    pub exit: String,
+    /// A tool call that failed without an exit code.
+    pub failed: String,
+    /// `--idle-warning`: `⏳ no activity for 2m (last: Bash: cargo build)`.
+    pub no_activity: String,
+    pub last: String,
+    /// `--max-cost`: `⚠️ Over budget: $0.52 / $0.50`.
+    pub over_budget: String,
+    /// Messages cut short: `⚠️ Response truncated (max_tokens)`.
+    pub response_truncated: String,
+    pub model_refused: String,
+    /// `--warn-repeats`: `🔁 repeated 5× in a row`.
+    pub repeated: String,
+    pub in_a_row: String,
+    /// `↩️ Resumed session abc12345 (previously 14 turns)`.
+    pub resumed_session: String,
+    pub previously: String,
+    /// `🔄 model changed: opus-4 → sonnet-4`.
+    pub model_changed: String,
+    /// `--summary` and `--files-report` headings and rows.
+    pub summary: String,
+    pub files: String,
+    pub assistant_messages: String,
+    pub tool_calls: String,
+    pub sub_agents: String,
+    pub files_touched: String,
+    pub time_span: String,
+    /// Extended thinking in `--output html`.
+    pub thinking: String,
+    /// Spoken by `--announce` when the run ends.
+    pub task_complete: String,
+    pub task_failed: String,
+    /// `… (+12 more lines, use --verbose)` and `(+2 more)`.
+    pub more_lines: String,
+    pub more: String,
+    /// `3 turns, 17 in session, 12.4s, $0.0342, 15k in / 2k out tokens`.
+    pub turn: Noun,
+    pub in_session: String,
+    pub tokens_in: String,
+    pub tokens_out: String,
+    /// `🚀 claude (sonnet-4) in /path/to/project [session abc12345]`.
+    pub working_in: String,
+    pub session: String,
+    /// `🖼️ image (png, 234.0 KB), not saved: ...`.
+    pub image: String,
+    pub not_saved: String,
+    /// Hotkey notes: `Tool results on`, `Paused: press p to resume`.
+    pub tool_results: String,
+    pub on: String,
+    pub off: String,
+    pub paused: String,
+    pub resumed: String,
+    /// The `check` report: `📋 120 lines, 118 messages, 2 blank`.
+    pub line: Noun,
+    pub message: Noun,
+    pub blank: String,
+    pub unknown_types: String,
+    pub first_on_line: String,
+    pub every_line_parsed: String,
+    pub failed_to_parse: String,
+    /// `compare` tables and headings.
+    pub model: String,
+    pub result: String,
+    pub succeeded: String,
+    pub turns: String,
+    pub tokens: String,
+    pub cost: String,
+    pub duration: String,
+    pub only_in: String,
+    pub the_same: String,
+    pub identical_call: Noun,
+    /// The `stats` report: `📊 12 sessions`, `Session length  14m 20s`.
+    pub sessions: Noun,
+    pub per_session: String,
+    pub session_length: String,
+    pub longest: String,
+    pub models: String,
+    pub tools: String,
+    pub most_edited: String,
+    /// Token counts: `1.2k→340 tok`.
+    pub tok: String,
+    /// Tool output previews: `↳ (no output)`, `… +12 lines`.
+    pub no_output: String,
+    /// `📋 TodoWrite: 2/5 done` and `🔎 WebSearch: rust (only docs.rs)`.
+    pub todos_done: String,
+    pub only: String,
+    /// `✅ Claude finished in 12.4s`.
+    pub finished_in: String,
+    /// The tools listed under the header with `--verbose`.
+    pub tool_list: String,
+    /// Background shells: `💻 [bg a1b2] output requested (filter: error)`
+    /// and `🛑 killed shell a1b2`.
+    pub output_requested: String,
+    pub filter: String,
+    pub killed_shell: String,
+    /// Results that failed without text: `reached the maximum number of
+    /// turns`.
+    pub max_turns_reached: String,
+    pub error_during_execution: String,
+    /// `--compact` counts, `[2 edits, 1 bash]`, and the reads, edits and
+    /// writes of each file in `--files-report` and `stats`.
+    pub compact: CompactLabels,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Labels {
+            done: "Done".to_string(),
+            error: "Error".to_string(),
+            claude_finished: "Claude finished".to_string(),
+            claude_failed: "Claude failed".to_string(),
+            permission_requested: "Permission requested".to_string(),
+            permission_granted: "Permission granted".to_string(),
+            denied: "Denied".to_string(),
+            exit: "exit".to_string(),
+            failed: "failed".to_string(),
+            no_activity: "no activity for".to_string(),
+            last: "last".to_string(),
+            over_budget: "Over budget".to_string(),
+            response_truncated: "Response truncated".to_string(),
+            model_refused: "Model refused".to_string(),
+            repeated: "repeated".to_string(),
+            in_a_row: "in a row".to_string(),
+            resumed_session: "Resumed session".to_string(),
+            previously: "previously".to_string(),
+            model_changed: "model changed".to_string(),
+            summary: "Summary".to_string(),
+            files: "Files".to_string(),
+            assistant_messages: "Assistant messages".to_string(),
+            tool_calls: "Tool calls".to_string(),
+            sub_agents: "Sub-agents".to_string(),
+            files_touched: "Files touched".to_string(),
+            time_span: "Time span".to_string(),
+            thinking: "Thinking".to_string(),
+            task_complete: "Task complete".to_string(),
+            task_failed: "Task failed".to_string(),
+            more_lines: "more lines, use --verbose".to_string(),
+            more: "more".to_string(),
+            turn: Noun::new("turn", "turns"),
+            in_session: "in session".to_string(),
+            tokens_in: "in".to_string(),
+            tokens_out: "out tokens".to_string(),
+            working_in: "in".to_string(),
+            session: "session".to_string(),
+            image: "image".to_string(),
+            not_saved: "not saved".to_string(),
+            tool_results: "Tool results".to_string(),
+            on: "on".to_string(),
+            off: "off".to_string(),
+            paused: "Paused: press p to resume".to_string(),
+            resumed: "Resumed".to_string(),
+            line: Noun::new("line", "lines"),
+            message: Noun::new("message", "messages"),
+            blank: "blank".to_string(),
+            unknown_types: "Unknown types".to_string(),
+            first_on_line: "first on line".to_string(),
+            every_line_parsed: "Every line parsed".to_string(),
+            failed_to_parse: "failed to parse".to_string(),
+            model: "Model".to_string(),
+            result: "Result".to_string(),
+            succeeded: "succeeded".to_string(),
+            turns: "Turns".to_string(),
+            tokens: "Tokens".to_string(),
+            cost: "Cost".to_string(),
+            duration: "Duration".to_string(),
+            only_in: "Only in".to_string(),
+            the_same: "the same".to_string(),
+            identical_call: Noun::new("identical call", "identical calls"),
+            sessions: Noun::new("session", "sessions"),
+            per_session: "per session".to_string(),
+            session_length: "Session length".to_string(),
+            longest: "longest".to_string(),
+            models: "Models".to_string(),
+            tools: "Tools".to_string(),
+            most_edited: "Most edited".to_string(),
+            tok: "tok".to_string(),
+            no_output: "no output".to_string(),
+            todos_done: "done".to_string(),
+            only: "only".to_string(),
+            finished_in: "in".to_string(),
+            tool_list: "tools".to_string(),
+            output_requested: "output requested".to_string(),
+            filter: "filter".to_string(),
+            killed_shell: "killed shell".to_string(),
+            max_turns_reached: "reached the maximum number of turns".to_string(),
+            error_during_execution: "error during execution".to_string(),
+            compact: CompactLabels::default(),
+        }
+    }
+}
+
+/// A word with its singular and plural, set as `["turn", "turns"]`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(from = "[String; 2]")]
+pub struct Noun {
+    pub one: String,
+    pub many: String,
+}
+
+impl Noun {
+    pub fn new(one: &str, many: &str) -> Self {
+        Noun { one: one.to_string(), many: many.to_string() }
+    }
+}
+
+impl From<[String; 2]> for Noun {
+    fn from([one, many]: [String; 2]) -> Self {
+        Noun { one, many }
+    }
+}
+
+/// What `--compact` counts each kind of tool call as, from the
+/// `[labels.compact]` table.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompactLabels {
+    pub read: Noun,
+    pub edit: Noun,
+    pub write: Noun,
+    pub bash: Noun,
+    pub search: Noun,
+    pub web: Noun,
+    pub task: Noun,
+    pub todo: Noun,
+    pub mcp: Noun,
+    pub tool: Noun,
+}
+
+impl CompactLabels {
+    /// The word for `key`, a field name; `tool` for any other.
+    pub fn get(&self, key: &str) -> &Noun {
+        match key {
+            "read" => &self.read,
+            "edit" => &self.edit,
+            "write" => &self.write,
+            "bash" => &self.bash,
+            "search" => &self.search,
+            "web" => &self.web,
+            "task" => &self.task,
+            "todo" => &self.todo,
+            "mcp" => &self.mcp,
+            _ => &self.tool,
+        }
+    }
+}
+
+impl Default for CompactLabels {
+    fn default() -> Self {
+        CompactLabels {
+            read: Noun::new("read", "reads"),
+            edit: Noun::new("edit", "edits"),
+            write: Noun::new("write", "writes"),
+            bash: Noun::new("bash", "bash"),
+            search: Noun::new("search", "searches"),
+            web: Noun::new("web", "web"),
+            task: Noun::new("task", "tasks"),
+            todo: Noun::new("todo", "todos"),
+            mcp: Noun::new("mcp", "mcp"),
+            tool: Noun::new("tool", "tools"),
+        }
+    }
+}
+
+/// The events `--announce` speaks, from the `[announce]` table, and what
+/// it speaks with. Unset keys keep the defaults: the result, errors,
+/// permission prompts, `--max-cost` and file changes are spoken.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnnounceConfig {
+    /// A command to speak with, run with `sh -c` and the text on stdin and
+    /// in `CSF_TEXT`, in place of the system's own speech command.
+    pub command: Option<String>,
+    pub result: bool,
+    /// A failed tool call.
+    pub error: bool,
+    pub permission: bool,
+    pub budget: bool,
+    /// Edit, MultiEdit, Write and NotebookEdit calls: `Edit main.rs`.
+    pub edit: bool,
+    pub bash: bool,
+    /// Calls to every other tool.
+    pub tool: bool,
+    /// The first sentence of what Claude says.
+    pub text: bool,
+}
+
+impl Default for AnnounceConfig {
+    fn default() -> Self {
+        AnnounceConfig {
+            command: None,
+            result: true,
+            error: true,
+            permission: true,
+            budget: true,
+            edit: true,
+            bash: false,
+            tool: false,
+            text: false,
+        }
+    }
+}
+
+/// Lengths from the `[truncate]` table, in columns; 0 means unlimited.
+/// Unset keys keep the built-in lengths.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TruncateConfig {
+    /// Bash commands.
+    pub command: Option<usize>,
+    /// The final result and each line of tool output.
+    pub result: Option<usize>,
+    /// Each block of assistant text.
+    pub text: Option<usize>,
+}
+
+/// How to display one tool. Unset keys keep the built-in behavior.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolConfig {
+    pub emoji: Option<String>,
+    pub label: Option<String>,
+    /// Input fields to show, in order.
+    pub fields: Option<Vec<String>>,
+    /// Maximum length of the displayed fields; 0 means unlimited.
+    pub max_len: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "invalid config {}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        Config::parse(&text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// `$XDG_CONFIG_HOME/claude-stream-format/config.toml`, falling back to
+    /// `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(base.join("claude-stream-format").join("config.toml"))
+    }
+
+    /// Loads `path` if given, otherwise the default config file if it exists.
+    pub fn discover(path: Option<&Path>) -> Result<Config, ConfigError> {
+        match path {
+            Some(path) => Config::load(path),
+            None => match Config::default_path() {
+                Some(path) if path.exists() => Config::load(&path),
+                _ => Ok(Config::default()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tools() {
+        let config = Config::parse(
+            r#"
+            [tools.Read]
+            emoji = "👀"
+
+            [tools.mcp__github__create_issue]
+            label = "Issue"
+            fields = ["repo", "title"]
+            max_len = 60
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.tools["Read"].emoji.as_deref(), Some("👀"));
+        let issue = &config.tools["mcp__github__create_issue"];
+        assert_eq!(issue.fields, Some(vec!["repo".to_string(), "title".to_string()]));
+        assert_eq!(issue.max_len, Some(60));
+    }
+
+    #[test]
+    fn test_parse_templates() {
+        let config = Config::parse("[templates]\nRead = \"📖 {input.file_path}\"").unwrap();
+        let input = serde_json::json!({"file_path": "a.rs"});
+        assert_eq!(config.templates["Read"].render("Read", &input), "📖 a.rs");
+
+        let err = Config::parse("[templates]\nRead = \"{input.file_path|shout}\"").unwrap_err();
+        assert!(err.to_string().contains("unknown filter `shout`"));
+    }
+
+    #[test]
+    fn test_parse_truncate() {
+        let config = Config::parse("[truncate]\ncommand = 120\ntext = 0").unwrap();
+        assert_eq!(
+            config.truncate,
+            TruncateConfig {
+                command: Some(120),
+                result: None,
+                text: Some(0),
+            }
+        );
+        assert!(Config::parse("[truncate]\npath = 10").is_err());
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        let config = Config::parse("[labels]\ndone = \"Fertig\"\nerror = \"Fehler\"").unwrap();
+        assert_eq!(config.labels.done, "Fertig");
+        assert_eq!(config.labels.error, "Fehler");
+        assert_eq!(config.labels.denied, "Denied");
+        assert!(Config::parse("[labels]\nfinished = \"Fertig\"").is_err());
+        let config = Config::parse("[labels]\nturn = [\"Runde\", \"Runden\"]\n[labels.compact]\nedit = [\"Änderung\", \"Änderungen\"]").unwrap();
+        assert_eq!(config.labels.turn, Noun::new("Runde", "Runden"));
+        assert_eq!(config.labels.compact.edit, Noun::new("Änderung", "Änderungen"));
+        assert_eq!(config.labels.compact.read, Noun::new("read", "reads"));
+        let config = Config::parse("[labels]\nno_output = \"keine Ausgabe\"").unwrap();
+        assert_eq!(config.labels.no_output, "keine Ausgabe");
+        assert_eq!(config.labels.tok, "tok");
+    }
+
+    #[test]
+    fn test_parse_announce() {
+        let config = Config::parse("[announce]\ncommand = \"espeak\"\nedit = false\nbash = true").unwrap();
+        assert_eq!(config.announce.command.as_deref(), Some("espeak"));
+        assert!(!config.announce.edit && config.announce.bash && config.announce.result);
+        assert!(Config::parse("[announce]\nthinking = true").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        assert!(Config::parse("[tools.Read]\ncolour = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = Config::load(Path::new("/nonexistent/config.toml")).unwrap_err();
+        assert!(err.to_string().starts_with("cannot read /nonexistent/config.toml"));
+    }
+}
@@ -0,0 +1,48 @@
+//! Estimated API cost of assistant messages, for `--max-cost` to track
+//! spending before a result reports the real total.
+
+use crate::message::Usage;
+
+/// Dollars per million input and output tokens, by a part of the model
+/// name. The first match is used, so more specific names come first.
+const PRICES: [(&str, f64, f64); 7] = [
+    ("opus-4-5", 5.0, 25.0),
+    ("opus-4-6", 5.0, 25.0),
+    ("opus", 15.0, 75.0),
+    ("sonnet", 3.0, 15.0),
+    ("haiku-4", 1.0, 5.0),
+    ("3-5-haiku", 0.8, 4.0),
+    ("haiku", 0.25, 1.25),
+];
+
+/// Cache writes and reads, as a multiple of the input price.
+const CACHE_WRITE: f64 = 1.25;
+const CACHE_READ: f64 = 0.1;
+
+/// The estimated cost in dollars of `usage` on `model`. Unknown models are
+/// priced as Sonnet, so a budget errs towards warning early.
+pub fn estimate(model: Option<&str>, usage: &Usage) -> f64 {
+    let model = model.unwrap_or("");
+    let (_, input, output) = PRICES.iter().find(|(name, ..)| model.contains(name)).unwrap_or(&PRICES[3]);
+    let input_tokens = usage.input_tokens as f64
+        + usage.cache_creation_input_tokens as f64 * CACHE_WRITE
+        + usage.cache_read_input_tokens as f64 * CACHE_READ;
+    (input_tokens * input + usage.output_tokens as f64 * output) / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let usage = Usage { input_tokens: 1_000_000, output_tokens: 100_000, ..Usage::default() };
+        assert_eq!(estimate(Some("claude-sonnet-4-5-20250929"), &usage), 4.5);
+        assert_eq!(estimate(Some("claude-opus-4-1-20250805"), &usage), 22.5);
+        assert_eq!(estimate(Some("claude-opus-4-5-20251101"), &usage), 7.5);
+        assert_eq!(estimate(Some("claude-3-5-haiku-20241022"), &usage), 1.2);
+        assert_eq!(estimate(None, &usage), 4.5);
+        let cached = Usage { cache_creation_input_tokens: 1_000_000, cache_read_input_tokens: 10_000_000, ..Usage::default() };
+        assert_eq!(estimate(Some("claude-sonnet-4"), &cached), 6.75);
+    }
+}
@@ -0,0 +1,357 @@
+//! `--dialect`: the shapes of stream the formatter understands.
+//!
+//! Besides Claude Code's stream-json, the Anthropic Messages API's own
+//! streaming events (`message_start`, `content_block_delta` and so on, as
+//! JSON lines or as raw server-sent events) are translated into the
+//! stream-json lines Claude Code would have printed for the same message,
+//! so the rest of the formatter doesn't need to know which it was given.
+//! Stream-json messages holding several content blocks are split into a
+//! line per block, as Claude Code itself prints them.
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Dialect {
+    /// Decide from the first line of input
+    #[default]
+    Auto,
+    /// Claude Code's `--output-format stream-json`
+    ClaudeCode,
+    /// The Messages API's streaming events, as JSON lines or server-sent
+    /// events
+    AnthropicSse,
+}
+
+/// Event types only sent by the Messages API.
+const SSE_TYPES: &[&str] = &[
+    "message_start",
+    "message_delta",
+    "message_stop",
+    "content_block_start",
+    "content_block_delta",
+    "content_block_stop",
+    "ping",
+    "error",
+];
+
+impl Dialect {
+    /// The dialect a line of input is in, if it says.
+    pub fn detect(line: &str) -> Option<Dialect> {
+        let line = line.trim_start();
+        if line.starts_with("event:") || line.starts_with("data:") {
+            return Some(Dialect::AnthropicSse);
+        }
+        let value: Value = serde_json::from_str(line).ok()?;
+        let kind = value.get("type")?.as_str()?;
+        Some(if SSE_TYPES.contains(&kind) { Dialect::AnthropicSse } else { Dialect::ClaudeCode })
+    }
+}
+
+/// Translates input in any [`Dialect`] into stream-json.
+#[derive(Default)]
+pub struct Translator {
+    dialect: Dialect,
+    /// The message being streamed, from its `message_start`.
+    message: Option<Value>,
+    /// Its content blocks still being streamed, by index, with the JSON of
+    /// a tool call's input so far.
+    blocks: BTreeMap<u64, (Value, String)>,
+    /// Its text, for the result.
+    text: Vec<String>,
+    /// Messages finished, for the result's turn count.
+    turns: u32,
+    started: bool,
+}
+
+impl Translator {
+    pub fn new(dialect: Dialect) -> Self {
+        Translator {
+            dialect,
+            ..Default::default()
+        }
+    }
+
+    /// The stream-json lines for one line of input. Lines that are already
+    /// stream-json, or not JSON at all, are returned unchanged.
+    pub fn translate(&mut self, line: &str) -> Vec<String> {
+        if self.dialect == Dialect::Auto {
+            match Dialect::detect(line) {
+                Some(dialect) => self.dialect = dialect,
+                None => return vec![line.to_string()],
+            }
+        }
+        if self.dialect == Dialect::ClaudeCode {
+            return split_blocks(line);
+        }
+
+        let trimmed = line.trim();
+        // Of a server-sent event only the data matters; the event name is
+        // repeated in it.
+        if trimmed.is_empty() || trimmed.starts_with("event:") || trimmed.starts_with(':') {
+            return Vec::new();
+        }
+        let data = trimmed.strip_prefix("data:").map_or(trimmed, str::trim_start);
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return vec![line.to_string()];
+        };
+        self.event(event).into_iter().map(|value| value.to_string()).collect()
+    }
+
+    fn event(&mut self, event: Value) -> Vec<Value> {
+        let index = event.get("index").and_then(Value::as_u64).unwrap_or(0);
+        match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => {
+                let message = event.get("message").cloned().unwrap_or_else(|| json!({}));
+                let model = message.get("model").cloned();
+                self.message = Some(message);
+                self.text.clear();
+                // Passed on for the message's id, which the formatter uses to
+                // tell what its deltas have already shown.
+                let start = json!({"type": "stream_event", "event": event});
+                if std::mem::replace(&mut self.started, true) {
+                    return vec![start];
+                }
+                vec![json!({"type": "system", "subtype": "init", "model": model}), start]
+            }
+            Some("content_block_start") => {
+                let block = event.get("content_block").cloned().unwrap_or_else(|| json!({}));
+                self.blocks.insert(index, (block, String::new()));
+                Vec::new()
+            }
+            Some("content_block_delta") => {
+                let delta = event.get("delta").cloned().unwrap_or_default();
+                if let Some((block, input)) = self.blocks.get_mut(&index) {
+                    let text = |key| delta.get(key).and_then(Value::as_str).unwrap_or_default();
+                    match delta.get("type").and_then(Value::as_str) {
+                        Some("text_delta") => append(block, "text", text("text")),
+                        Some("thinking_delta") => append(block, "thinking", text("thinking")),
+                        Some("input_json_delta") => input.push_str(text("partial_json")),
+                        _ => {}
+                    }
+                }
+                vec![json!({"type": "stream_event", "event": event})]
+            }
+            Some("content_block_stop") => {
+                let stop = json!({"type": "stream_event", "event": event});
+                let Some((mut block, input)) = self.blocks.remove(&index) else {
+                    return vec![stop];
+                };
+                if !input.is_empty() {
+                    block["input"] = serde_json::from_str(&input).unwrap_or(Value::String(input));
+                }
+                if let Some(text) = block.get("text").and_then(Value::as_str) {
+                    self.text.push(text.to_string());
+                }
+                let mut message = self.message.clone().unwrap_or_else(|| json!({}));
+                message["role"] = json!("assistant");
+                message["content"] = json!([block]);
+                vec![stop, json!({"type": "assistant", "message": message})]
+            }
+            Some("message_delta") => {
+                let stop_reason = event.pointer("/delta/stop_reason").filter(|reason| !reason.is_null());
+                if let (Some(message), Some(reason)) = (&mut self.message, stop_reason) {
+                    message["stop_reason"] = reason.clone();
+                }
+                // The final output token count arrives here, for the result.
+                if let (Some(message), Some(usage)) = (&mut self.message, event.get("usage")) {
+                    let totals = message["usage"].as_object().cloned().unwrap_or_default();
+                    let mut totals = Value::Object(totals);
+                    for (key, count) in usage.as_object().into_iter().flatten() {
+                        totals[key] = count.clone();
+                    }
+                    message["usage"] = totals;
+                }
+                Vec::new()
+            }
+            Some("message_stop") => {
+                self.turns += 1;
+                let mut lines = Vec::new();
+                let message = self.message.take().unwrap_or_else(|| json!({}));
+                // The stop reason only arrives after the content, so a message
+                // that ended unusually is repeated, empty, to carry it.
+                let stop_reason = message.get("stop_reason").and_then(Value::as_str);
+                if stop_reason.is_some_and(|reason| !matches!(reason, "end_turn" | "tool_use" | "stop_sequence")) {
+                    let mut message = message.clone();
+                    message["role"] = json!("assistant");
+                    message["content"] = json!([]);
+                    lines.push(json!({"type": "assistant", "message": message}));
+                }
+                lines.push(json!({
+                    "type": "result",
+                    "subtype": "success",
+                    "is_error": false,
+                    "result": self.text.join("\n\n"),
+                    "num_turns": self.turns,
+                    "usage": message.get("usage"),
+                }));
+                lines
+            }
+            Some("error") => {
+                let message = event.pointer("/error/message").and_then(Value::as_str).unwrap_or("error");
+                vec![json!({
+                    "type": "result",
+                    "subtype": "error_during_execution",
+                    "is_error": true,
+                    "result": message,
+                    "num_turns": self.turns,
+                })]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// An assistant message with several content blocks as a line for each,
+/// so each is formatted and written as it is reached rather than all at
+/// once. The pieces keep the message's id, with its usage on the first,
+/// to be counted once, and its stop reason on the last. Messages without an
+/// id, which couldn't be told apart from several messages, are left whole.
+fn split_blocks(line: &str) -> Vec<String> {
+    // Claude Code's messages say "type" three times with one block: for
+    // the line, the message and the block.
+    if !line.contains("\"assistant\"") || line.matches("\"type\"").count() < 4 {
+        return vec![line.to_string()];
+    }
+    let Ok(value) = serde_json::from_str::<Value>(line) else {
+        return vec![line.to_string()];
+    };
+    let blocks = match value.pointer("/message/content").and_then(Value::as_array) {
+        Some(blocks) if blocks.len() > 1 && value["type"] == "assistant" && value["message"]["id"].is_string() => blocks,
+        _ => return vec![line.to_string()],
+    };
+    let last = blocks.len() - 1;
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| {
+            let mut piece = value.clone();
+            let message = piece["message"].as_object_mut().expect("message is an object");
+            message.insert("content".to_string(), json!([block]));
+            if i > 0 {
+                message.remove("usage");
+            }
+            if i < last {
+                message.remove("stop_reason");
+            }
+            piece.to_string()
+        })
+        .collect()
+}
+
+/// Adds a delta to a text or thinking block's `key`.
+fn append(block: &mut Value, key: &str, delta: &str) {
+    let text = block.get(key).and_then(Value::as_str).unwrap_or_default();
+    block[key] = Value::String(format!("{}{}", text, delta));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::{Options, StreamFormatter};
+
+    const STREAM: &str = r#"event: message_start
+data: {"type": "message_start", "message": {"id": "msg_1", "type": "message", "role": "assistant", "model": "claude-sonnet-4-20250514", "content": [], "usage": {"input_tokens": 25, "output_tokens": 1}}}
+
+event: content_block_start
+data: {"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}
+
+event: ping
+data: {"type": "ping"}
+
+event: content_block_delta
+data: {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Let me "}}
+
+event: content_block_delta
+data: {"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "check."}}
+
+event: content_block_stop
+data: {"type": "content_block_stop", "index": 0}
+
+event: content_block_start
+data: {"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "Read", "input": {}}}
+
+event: content_block_delta
+data: {"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"file_path\": "}}
+
+event: content_block_delta
+data: {"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"src/main.rs\"}"}}
+
+event: content_block_stop
+data: {"type": "content_block_stop", "index": 1}
+
+event: message_delta
+data: {"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 40}}
+
+event: message_stop
+data: {"type": "message_stop"}"#;
+
+    fn format(lines: Vec<String>) -> String {
+        let mut formatter = StreamFormatter::new(Options::default());
+        lines.iter().filter_map(|line| formatter.format_line(line)).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_anthropic_sse() {
+        let mut translator = Translator::new(Dialect::Auto);
+        let lines: Vec<String> = STREAM.lines().flat_map(|line| translator.translate(line)).collect();
+        assert_eq!(
+            format(lines),
+            "🚀 claude (sonnet-4)\nLet me check.\n\n📖 Read: src/main.rs\n✅ Done (1 turn, 25 in / 40 out tokens): Let me check."
+        );
+    }
+
+    #[test]
+    fn test_stop_reason() {
+        let mut translator = Translator::new(Dialect::AnthropicSse);
+        let events = [
+            r#"{"type": "message_start", "message": {"id": "msg_1", "model": "claude-sonnet-4-20250514", "usage": {"input_tokens": 5}}}"#,
+            r#"{"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}"#,
+            r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "It began"}}"#,
+            r#"{"type": "content_block_stop", "index": 0}"#,
+            r#"{"type": "message_delta", "delta": {"stop_reason": "max_tokens"}, "usage": {"output_tokens": 2}}"#,
+            r#"{"type": "message_stop"}"#,
+        ];
+        let lines: Vec<String> = events.iter().flat_map(|event| translator.translate(event)).collect();
+        assert_eq!(
+            format(lines),
+            "🚀 claude (sonnet-4)\nIt began\n⚠️ Response truncated (max_tokens)\n✅ Done (1 turn, 5 in / 2 out tokens)"
+        );
+    }
+
+    #[test]
+    fn test_split_blocks() {
+        let line = r#"{"type": "assistant", "message": {"id": "msg_1", "type": "message", "content": [{"type": "text", "text": "Let me look."}, {"type": "tool_use", "id": "toolu_1", "name": "Read", "input": {"file_path": "src/main.rs"}}], "stop_reason": "tool_use", "usage": {"output_tokens": 12}}}"#;
+        let mut translator = Translator::new(Dialect::Auto);
+        let lines = translator.translate(line);
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"type":"assistant","message":{"id":"msg_1","type":"message","content":[{"type":"text","text":"Let me look."}],"usage":{"output_tokens":12}}}"#,
+                r#"{"type":"assistant","message":{"id":"msg_1","type":"message","content":[{"type":"tool_use","id":"toolu_1","name":"Read","input":{"file_path":"src/main.rs"}}],"stop_reason":"tool_use"}}"#,
+            ]
+        );
+        assert_eq!(format(lines), "Let me look.\n\n📖 Read: src/main.rs");
+
+        let single = r#"{"type": "assistant", "message": {"id": "msg_2", "type": "message", "content": [{"type": "text", "text": "Done."}]}}"#;
+        assert_eq!(translator.translate(single), vec![single]);
+        let no_id = r#"{"type": "assistant", "message": {"type": "message", "content": [{"type": "text", "text": "a"}, {"type": "text", "text": "b"}]}}"#;
+        assert_eq!(translator.translate(no_id), vec![no_id]);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Dialect::detect(r#"{"type": "system", "subtype": "init"}"#), Some(Dialect::ClaudeCode));
+        assert_eq!(Dialect::detect(r#"{"type": "message_start", "message": {}}"#), Some(Dialect::AnthropicSse));
+        assert_eq!(Dialect::detect("event: message_start"), Some(Dialect::AnthropicSse));
+        assert_eq!(Dialect::detect("not json"), None);
+
+        let mut translator = Translator::new(Dialect::Auto);
+        let line = r#"{"type": "result", "result": "ok"}"#;
+        assert_eq!(translator.translate(line), vec![line.to_string()]);
+        // Once decided, the dialect sticks.
+        let line = r#"{"type": "ping"}"#;
+        assert_eq!(translator.translate(line), vec![line.to_string()]);
+    }
+}
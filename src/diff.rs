@@ -0,0 +1,178 @@
+//! Line diffs for showing what an Edit changed.
+
+use crate::config::Labels;
+use crate::format::{cap_lines, more_lines};
+use crate::highlight::highlight_line;
+use crate::style::Theme;
+
+#[derive(Debug, PartialEq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Above this many line pairs the diff degrades to "all removed, all added"
+/// rather than spend quadratic time and memory.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// Diffs `old` against `new` line by line using a longest common subsequence.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    if old.len() * new.len() > MAX_LCS_CELLS {
+        return old
+            .into_iter()
+            .map(DiffLine::Removed)
+            .chain(new.into_iter().map(DiffLine::Added))
+            .collect();
+    }
+
+    // lcs[i][j] is the LCS length of old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().copied().map(DiffLine::Removed));
+    lines.extend(new[j..].iter().copied().map(DiffLine::Added));
+    lines
+}
+
+/// Renders the diff of `old` and `new` as indented `-`/`+` lines, with the
+/// added lines highlighted as `lang` code.
+pub fn format_diff(old: &str, new: &str, lang: &str, theme: &Theme) -> String {
+    diff_lines(old, new)
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Same(l) => theme.comment.paint(&format!("      {}", l)),
+            DiffLine::Removed(l) => theme.removed.paint(&format!("    - {}", l)),
+            DiffLine::Added(l) => added(l, lang, theme),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders the contents of a new file as added lines highlighted as `lang`
+/// code, cut to `max_lines` (0 for all of them).
+pub fn format_write(content: &str, lang: &str, max_lines: usize, theme: &Theme, labels: &Labels) -> String {
+    let (shown, hidden) = cap_lines(content.trim_end(), max_lines);
+    let mut lines: Vec<String> = shown.lines().map(|line| added(line, lang, theme)).collect();
+    if hidden > 0 {
+        lines.push(theme.comment.paint(&format!("      {}", more_lines(hidden, labels))));
+    }
+    lines.join("\n")
+}
+
+fn added(line: &str, lang: &str, theme: &Theme) -> String {
+    format!("{}{}", theme.added.paint("    + "), highlight_line(line, lang, theme.added, theme))
+}
+
+/// Renders a diff block for each `{old_string, new_string}` edit of a
+/// MultiEdit, under an `@@ n/total @@` header.
+pub fn format_multi_diff(edits: &[serde_json::Value], lang: &str, theme: &Theme) -> String {
+    edits
+        .iter()
+        .enumerate()
+        .map(|(i, edit)| {
+            let old = edit.get("old_string").and_then(|v| v.as_str()).unwrap_or("");
+            let new = edit.get("new_string").and_then(|v| v.as_str()).unwrap_or("");
+            let header = theme.comment.paint(&format!("    @@ {}/{} @@", i + 1, edits.len()));
+            format!("{}\n{}", header, format_diff(old, new, lang, theme))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::ThemeName;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc", "a\nB\nc\nd");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Added("B"),
+                DiffLine::Same("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_diff() {
+        let diff = format_diff("let x = 1;", "let x = 2;\nlet y = 3;", "rs", &Theme::default());
+        assert_eq!(diff, "    - let x = 1;\n    + let x = 2;\n    + let y = 3;");
+    }
+
+    #[test]
+    fn test_highlighted_additions() {
+        let theme = Theme::new(ThemeName::Dark);
+        let diff = format_diff("let x = 1;", "let x = 2;", "rs", &theme);
+        assert_eq!(
+            diff.lines().last().unwrap(),
+            format!(
+                "{}{}{}{}{}",
+                theme.added.paint("    + "),
+                theme.keyword.paint("let"),
+                theme.added.paint(" x = "),
+                theme.number.paint("2"),
+                theme.added.paint(";")
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_write() {
+        let content = "fn main() {\n    run();\n}\n";
+        assert_eq!(format_write(content, "rs", 0, &Theme::default(), &Labels::default()), "    + fn main() {\n    +     run();\n    + }");
+        assert_eq!(
+            format_write(content, "rs", 1, &Theme::default(), &Labels::default()),
+            "    + fn main() {\n      … (+2 more lines, use --verbose)"
+        );
+    }
+
+    #[test]
+    fn test_empty_side() {
+        assert_eq!(diff_lines("", "new"), vec![DiffLine::Added("new")]);
+    }
+
+    #[test]
+    fn test_format_multi_diff() {
+        let edits = serde_json::json!([
+            {"old_string": "a", "new_string": "b"},
+            {"old_string": "c", "new_string": "d"},
+        ]);
+        assert_eq!(
+            format_multi_diff(edits.as_array().unwrap(), "", &Theme::default()),
+            "    @@ 1/2 @@\n    - a\n    + b\n    @@ 2/2 @@\n    - c\n    + d"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! A normalized, serializable view of the stream for machine consumers.
+
+use crate::ascii::to_ascii;
+use crate::config::Config;
+use crate::format::{
+    format_init, format_permission_request, format_permission_response, format_tool, truncate, Limits,
+};
+use crate::message::{ContentBlock, StreamMessage};
+use crate::time;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// The width of the tool column of `--output columns`.
+const TOOL_COLUMN: usize = 16;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Init,
+    Text,
+    TextDelta,
+    Thinking,
+    ToolUse,
+    ToolResult,
+    Permission,
+    Result,
+}
+
+/// One event as emitted by `--output json`.
+#[derive(Clone, Debug, Serialize)]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_tool_use_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_turns: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, summary: String) -> Self {
+        Event {
+            kind,
+            timestamp: time::rfc3339(SystemTime::now()),
+            session_id: None,
+            parent_tool_use_id: None,
+            model: None,
+            cwd: None,
+            tool: None,
+            tool_use_id: None,
+            summary,
+            is_error: false,
+            input: None,
+            num_turns: None,
+            duration_ms: None,
+            cost_usd: None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// The event as a flat log record, for `--output ndjson`.
+    pub fn to_ndjson(&self) -> String {
+        serde_json::to_string(&LogRecord::new(self)).unwrap_or_default()
+    }
+
+    /// The event as a logfmt line, for `--output logfmt`.
+    pub fn to_logfmt(&self) -> String {
+        let record = serde_json::to_value(LogRecord::new(self)).unwrap_or_default();
+        let Some(fields) = record.as_object() else {
+            return String::new();
+        };
+        fields
+            .iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(s) => format!("{}={}", key, logfmt_value(s)),
+                other => format!("{}={}", key, other),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The event as one line of aligned columns, for `--output columns`:
+    /// the time, the event type, the tool (or `-`) and the summary, with
+    /// emoji spelled out and its lines joined.
+    pub fn to_columns(&self) -> String {
+        let clock = self.timestamp.get(11..19).unwrap_or(&self.timestamp);
+        let kind = serde_json::to_value(self.kind).ok();
+        let kind = kind.as_ref().and_then(|kind| kind.as_str()).unwrap_or_default();
+        // The type column says what the icon would, and a tool call's
+        // summary repeats the tool's name after it.
+        let summary = match &self.tool {
+            Some(tool) if self.kind == EventKind::ToolUse => {
+                self.summary.split_once(&format!("{}: ", tool)).map_or(self.summary.as_str(), |(_, rest)| rest)
+            }
+            _ if matches!(self.kind, EventKind::Text | EventKind::Thinking) => &self.summary,
+            _ => match self.summary.split_once(' ') {
+                Some((icon, rest)) if !icon.is_ascii() => rest,
+                _ => &self.summary,
+            },
+        };
+        let summary = to_ascii(&summary.split_whitespace().collect::<Vec<_>>().join(" "));
+        let summary = if self.is_error { format!("[error] {}", summary) } else { summary };
+        let tool = truncate(self.tool.as_deref().unwrap_or("-"), TOOL_COLUMN);
+        format!("{}  {:<11}  {:<width$}  {}", clock, kind, tool, summary, width = TOOL_COLUMN)
+            .trim_end()
+            .to_string()
+    }
+
+    /// The path of the file a tool event works on, if any.
+    fn file(&self) -> Option<&str> {
+        let input = self.input.as_ref()?;
+        ["file_path", "notebook_path", "path"].iter().find_map(|f| input.get(f)?.as_str())
+    }
+}
+
+/// The fields of a log record, in the order they are written.
+#[derive(Serialize)]
+struct LogRecord<'a> {
+    level: &'static str,
+    ts: &'a str,
+    event: EventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_tool_use_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_use_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_turns: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost_usd: Option<f64>,
+    summary: &'a str,
+}
+
+impl<'a> LogRecord<'a> {
+    fn new(event: &'a Event) -> Self {
+        LogRecord {
+            level: if event.is_error { "error" } else { "info" },
+            ts: &event.timestamp,
+            event: event.kind,
+            session_id: event.session_id.as_deref(),
+            parent_tool_use_id: event.parent_tool_use_id.as_deref(),
+            tool: event.tool.as_deref(),
+            tool_use_id: event.tool_use_id.as_deref().filter(|id| !id.is_empty()),
+            file: event.file(),
+            model: event.model.as_deref(),
+            num_turns: event.num_turns,
+            duration_ms: event.duration_ms,
+            cost_usd: event.cost_usd,
+            summary: &event.summary,
+        }
+    }
+}
+
+/// Quotes a logfmt value if it is empty or contains spaces, quotes, `=`
+/// or control characters.
+fn logfmt_value(s: &str) -> String {
+    let plain = !s.is_empty() && !s.chars().any(|c| c == ' ' || c == '"' || c == '=' || c == '\\' || c.is_control());
+    if plain {
+        s.to_string()
+    } else {
+        // A JSON string literal escapes exactly what logfmt needs escaped.
+        serde_json::to_string(s).unwrap_or_default()
+    }
+}
+
+/// The events described by one stream message.
+pub fn events(msg: &StreamMessage, config: &Config, limits: &Limits) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    match msg.msg_type.as_str() {
+        "system" if msg.subtype.as_deref() == Some("init") => {
+            let mut event = Event::new(EventKind::Init, format_init(msg, &config.labels));
+            event.model = msg.model.clone();
+            event.cwd = msg.cwd.clone();
+            events.push(event);
+        }
+        "assistant" | "user" => {
+            let Some(message) = &msg.message else {
+                return events;
+            };
+            for block in &message.content {
+                match block {
+                    ContentBlock::Text { text } if !text.trim().is_empty() => {
+                        events.push(Event::new(EventKind::Text, text.clone()));
+                    }
+                    ContentBlock::Thinking { thinking } if !thinking.trim().is_empty() => {
+                        events.push(Event::new(EventKind::Thinking, thinking.clone()));
+                    }
+                    ContentBlock::ToolUse { id, name, input } => {
+                        let summary = format_tool(name, input, config, limits);
+                        let mut event = Event::new(EventKind::ToolUse, summary);
+                        event.tool = Some(name.clone());
+                        event.tool_use_id = Some(id.clone());
+                        event.input = Some(input.clone());
+                        events.push(event);
+                    }
+                    ContentBlock::ToolResult {
+                        tool_use_id,
+                        content,
+                        is_error,
+                    } => {
+                        let text = content.as_ref().map(|c| c.text()).unwrap_or_default();
+                        let first_line = text.lines().next().unwrap_or_default();
+                        let mut event =
+                            Event::new(EventKind::ToolResult, truncate(first_line, limits.result));
+                        event.tool_use_id = Some(tool_use_id.clone());
+                        event.is_error = *is_error;
+                        events.push(event);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        "control_request" => {
+            let Some(request) = msg.request.as_ref().filter(|r| r.subtype.as_deref() == Some("can_use_tool")) else {
+                return events;
+            };
+            let name = request.tool_name.as_deref().unwrap_or("unknown");
+            let summary = format_permission_request(name, &request.input, config, limits);
+            let mut event = Event::new(EventKind::Permission, summary);
+            event.tool = Some(name.to_string());
+            event.input = Some(request.input.clone());
+            events.push(event);
+        }
+        "control_response" => {
+            if let Some((allowed, message)) = msg.response.as_ref().and_then(|r| r.permission()) {
+                let summary = format_permission_response(allowed, message, None, &config.labels);
+                events.push(Event::new(EventKind::Permission, summary));
+            }
+        }
+        "result" => {
+            let mut event = Event::new(EventKind::Result, msg.result_text(&config.labels).unwrap_or_default());
+            event.is_error = msg.is_error_result();
+            event.num_turns = msg.num_turns;
+            event.duration_ms = msg.duration_ms;
+            event.cost_usd = msg.total_cost_usd;
+            events.push(event);
+        }
+        _ => {}
+    }
+
+    for event in &mut events {
+        event.session_id = msg.session_id.clone();
+        event.parent_tool_use_id = msg.parent_tool_use_id.clone();
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> StreamMessage {
+        serde_json::from_str(line).unwrap()
+    }
+
+    #[test]
+    fn test_columns() {
+        let msg = parse(r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Checking the\nparser ✅"}, {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "cargo test"}}]}}"#);
+        let lines: Vec<String> = events(&msg, &Config::default(), &Limits::default())
+            .iter()
+            .map(|event| event.to_columns()[8..].to_string())
+            .collect();
+        assert_eq!(lines, ["  text         -                 Checking the parser [OK]", "  tool_use     Bash              cargo test"]);
+
+        let mut event = Event::new(EventKind::ToolResult, "No such file".to_string());
+        event.is_error = true;
+        assert!(event.to_columns().ends_with("  tool_result  -                 [error] No such file"));
+
+        // Long tool names, as MCP tools have, are cut to keep the columns.
+        let mut event = Event::new(EventKind::ToolUse, "🔧 mcp__github__create_issue: fix it".to_string());
+        event.tool = Some("mcp__github__create_issue".to_string());
+        assert!(event.to_columns().ends_with("  tool_use     mcp__github__...  fix it"), "{}", event.to_columns());
+    }
+
+    #[test]
+    fn test_tool_use_event() {
+        let msg = parse(r#"{"type": "assistant", "session_id": "abc", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "/a.rs"}}]}}"#);
+        let events = events(&msg, &Config::default(), &Limits::default());
+        assert_eq!(events.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&events[0].to_json()).unwrap();
+        assert_eq!(json["type"], "tool_use");
+        assert_eq!(json["tool"], "Read");
+        assert_eq!(json["tool_use_id"], "t1");
+        assert_eq!(json["summary"], "📖 Read: /a.rs");
+        assert_eq!(json["session_id"], "abc");
+        assert_eq!(json["input"]["file_path"], "/a.rs");
+        assert!(json["timestamp"].as_str().unwrap().ends_with('Z'));
+        assert!(json.get("is_error").is_none());
+    }
+
+    #[test]
+    fn test_result_event() {
+        let msg = parse(r#"{"type": "result", "result": "done", "is_error": true, "num_turns": 2, "total_cost_usd": 0.5}"#);
+        let json: serde_json::Value = serde_json::from_str(&events(&msg, &Config::default(), &Limits::default())[0].to_json()).unwrap();
+        assert_eq!(json["type"], "result");
+        assert_eq!(json["summary"], "done");
+        assert_eq!(json["is_error"], true);
+        assert_eq!(json["num_turns"], 2);
+        assert_eq!(json["cost_usd"], 0.5);
+    }
+
+    #[test]
+    fn test_tool_result_event() {
+        let msg = parse(r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "first\nsecond"}]}}"#);
+        let events = events(&msg, &Config::default(), &Limits::default());
+        assert_eq!(events[0].kind, EventKind::ToolResult);
+        assert_eq!(events[0].summary, "first");
+    }
+
+    #[test]
+    fn test_permission_events() {
+        let msg = parse(r#"{"type": "control_request", "request_id": "r1", "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {"command": "rm -rf build"}}}"#);
+        let request = &events(&msg, &Config::default(), &Limits::default())[0];
+        assert_eq!(request.kind, EventKind::Permission);
+        assert_eq!(request.tool.as_deref(), Some("Bash"));
+        assert_eq!(request.summary, "🔐 Permission requested: Bash (rm -rf build)");
+
+        let msg = parse(r#"{"type": "control_response", "response": {"subtype": "success", "request_id": "r1", "response": {"behavior": "deny"}}}"#);
+        assert_eq!(events(&msg, &Config::default(), &Limits::default())[0].summary, "🚫 Denied");
+    }
+
+    #[test]
+    fn test_log_records() {
+        let msg = parse(r#"{"type": "assistant", "session_id": "abc", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "/a.rs"}}]}}"#);
+        let mut event = events(&msg, &Config::default(), &Limits::default()).remove(0);
+        event.timestamp = "2025-01-31T09:05:03.042Z".to_string();
+        assert_eq!(
+            event.to_logfmt(),
+            r#"level=info ts=2025-01-31T09:05:03.042Z event=tool_use session_id=abc tool=Read tool_use_id=t1 file=/a.rs summary="📖 Read: /a.rs""#
+        );
+        let json: serde_json::Value = serde_json::from_str(&event.to_ndjson()).unwrap();
+        assert_eq!(json["level"], "info");
+        assert_eq!(json["file"], "/a.rs");
+        assert!(json.get("input").is_none());
+
+        let msg = parse(r#"{"type": "result", "result": "it \"broke\"\nbadly", "is_error": true, "num_turns": 2}"#);
+        let event = &events(&msg, &Config::default(), &Limits::default())[0];
+        assert!(event.to_logfmt().starts_with("level=error "));
+        assert!(event.to_logfmt().ends_with(r#" num_turns=2 summary="it \"broke\"\nbadly""#));
+    }
+
+    #[test]
+    fn test_init_event() {
+        let msg = parse(r#"{"type": "system", "subtype": "init", "model": "claude-opus-4-1", "cwd": "/work", "session_id": "s1"}"#);
+        let events = events(&msg, &Config::default(), &Limits::default());
+        assert_eq!(events[0].kind, EventKind::Init);
+        assert_eq!(events[0].model.as_deref(), Some("claude-opus-4-1"));
+        assert_eq!(events[0].cwd.as_deref(), Some("/work"));
+        assert_eq!(events[0].session_id.as_deref(), Some("s1"));
+    }
+}
@@ -0,0 +1,146 @@
+//! `exec`: running `claude` with the flags stream-json output needs, so its
+//! output can be formatted without remembering the pipe incantation.
+
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+/// Starts `args` (a program and its arguments) with its stdout piped,
+/// adding `--output-format stream-json` and `--verbose` (which stream-json
+/// needs) unless they are given, and `--include-partial-messages` for
+/// `partial`. Stdin and stderr are shared with this process.
+pub fn spawn(args: &[String], partial: bool) -> io::Result<Child> {
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command given"))?;
+    Command::new(program).args(with_stream_flags(args, partial)).stdout(Stdio::piped()).spawn()
+}
+
+/// `args` with the flags for stream-json output added.
+fn with_stream_flags(args: &[String], partial: bool) -> Vec<String> {
+    let has = |flag: &str| {
+        args.iter()
+            .take_while(|arg| *arg != "--")
+            .any(|arg| arg == flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+    };
+    let mut flags = Vec::new();
+    if !has("--output-format") {
+        flags.extend(["--output-format".to_string(), "stream-json".to_string()]);
+    }
+    if !has("--verbose") {
+        flags.push("--verbose".to_string());
+    }
+    if partial && !has("--include-partial-messages") {
+        flags.push("--include-partial-messages".to_string());
+    }
+    // Before any `--`, after which arguments aren't read as flags.
+    let end = args.iter().position(|arg| arg == "--").unwrap_or(args.len());
+    let mut args = args.to_vec();
+    args.splice(end..end, flags);
+    args
+}
+
+/// The status to exit with for a command that finished with `status`: its
+/// exit code, or 128 plus the signal that killed it, as a shell reports.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Asks `child` to stop with SIGTERM, as an interrupted shell would.
+#[cfg(unix)]
+pub fn terminate(child: &mut Child) {
+    // SAFETY: kill has no memory-safety preconditions.
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn terminate(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// Passes SIGTERM and SIGHUP on to the process `pid`, and keeps SIGINT from
+/// ending this process: Ctrl-C reaches `claude` through the terminal, and
+/// the output it prints as it stops is still worth formatting.
+#[cfg(unix)]
+pub fn forward_signals(pid: u32) -> io::Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal != SIGINT {
+                // SAFETY: kill has no memory-safety preconditions.
+                unsafe {
+                    libc::kill(pid as libc::pid_t, signal);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn forward_signals(_pid: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Records SIGINT, SIGTERM and SIGHUP in the returned number instead of
+/// ending the process, so the formatter can stop cleanly: it is 0 until
+/// one of them arrives.
+#[cfg(unix)]
+pub fn catch_signals() -> io::Result<Arc<AtomicUsize>> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+    let caught = Arc::new(AtomicUsize::new(0));
+    for signal in [SIGINT, SIGTERM, SIGHUP] {
+        signal_hook::flag::register_usize(signal, Arc::clone(&caught), signal as usize)?;
+    }
+    Ok(caught)
+}
+
+#[cfg(not(unix))]
+pub fn catch_signals() -> io::Result<Arc<AtomicUsize>> {
+    Ok(Arc::new(AtomicUsize::new(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(args: &[&str], partial: bool) -> Vec<String> {
+        with_stream_flags(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>(), partial)
+    }
+
+    #[test]
+    fn test_stream_flags() {
+        assert_eq!(flags(&["-p", "fix the tests"], false), ["-p", "fix the tests", "--output-format", "stream-json", "--verbose"]);
+        assert_eq!(
+            flags(&["-p", "hi", "--output-format=stream-json", "--verbose"], true),
+            ["-p", "hi", "--output-format=stream-json", "--verbose", "--include-partial-messages"]
+        );
+        assert_eq!(
+            flags(&["-p", "--", "--verbose"], false),
+            ["-p", "--output-format", "stream-json", "--verbose", "--", "--verbose"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_code() {
+        let status = Command::new("sh").args(["-c", "exit 3"]).status().unwrap();
+        assert_eq!(exit_code(status), 3);
+        let status = Command::new("sh").args(["-c", "kill -TERM $$"]).status().unwrap();
+        assert_eq!(exit_code(status), 143);
+    }
+}
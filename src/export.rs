@@ -0,0 +1,180 @@
+//! A Markdown transcript for `--export-md`, written alongside the normal
+//! output for pasting into PR descriptions and docs.
+
+use crate::config::Config;
+use crate::format::{format_init, format_stats, format_tool, Limits};
+use crate::message::{ContentBlock, StreamMessage};
+use std::collections::HashMap;
+
+enum Entry {
+    Header(String),
+    Text(String),
+    Tool { depth: usize, line: String, failed: bool },
+    Outcome { text: String, stats: Option<String>, is_error: bool },
+}
+
+/// The messages of a stream, collected for writing as one Markdown document.
+#[derive(Default)]
+pub struct MarkdownExport {
+    entries: Vec<Entry>,
+    /// Index in `entries` of the call for each tool_use id.
+    tools: HashMap<String, usize>,
+}
+
+impl MarkdownExport {
+    /// Adds `msg`, read at sub-agent `depth`. Sub-agents' tool calls are
+    /// nested under the Task that started them; their prose is left out.
+    pub fn record(&mut self, msg: &StreamMessage, depth: usize, config: &Config) {
+        match msg.msg_type.as_str() {
+            "system" if msg.subtype.as_deref() == Some("init") && depth == 0 => {
+                self.entries.push(Entry::Header(format_init(msg, &config.labels)));
+            }
+            "assistant" => {
+                let Some(message) = &msg.message else {
+                    return;
+                };
+                for block in &message.content {
+                    match block {
+                        ContentBlock::Text { text } if depth == 0 && !text.trim().is_empty() => {
+                            self.entries.push(Entry::Text(text.trim().to_string()));
+                        }
+                        ContentBlock::ToolUse { id, name, input } => {
+                            if !id.is_empty() {
+                                self.tools.insert(id.clone(), self.entries.len());
+                            }
+                            self.entries.push(Entry::Tool {
+                                depth,
+                                line: format_tool(name, input, config, &Limits::default().with_config(&config.truncate)),
+                                failed: false,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "user" => {
+                let Some(message) = &msg.message else {
+                    return;
+                };
+                for block in &message.content {
+                    if let ContentBlock::ToolResult {
+                        tool_use_id,
+                        is_error: true,
+                        ..
+                    } = block
+                    {
+                        if let Some(Entry::Tool { failed, .. }) =
+                            self.tools.get(tool_use_id).and_then(|&index| self.entries.get_mut(index))
+                        {
+                            *failed = true;
+                        }
+                    }
+                }
+            }
+            "result" => {
+                self.entries.push(Entry::Outcome {
+                    text: msg.result_text(&config.labels).unwrap_or_default(),
+                    stats: format_stats(msg, 0, &config.labels),
+                    is_error: msg.is_error_result(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders the complete Markdown document.
+    pub fn render(&self) -> String {
+        let mut blocks: Vec<String> = vec!["# Claude session transcript".to_string()];
+        for entry in &self.entries {
+            match entry {
+                Entry::Header(header) => blocks.push(format!("> {}", header)),
+                Entry::Text(text) => blocks.push(text.clone()),
+                Entry::Tool { depth, line, failed } => {
+                    let item = format!("{}- {}{}", "  ".repeat(*depth), tool_item(line), if *failed { " ❌" } else { "" });
+                    // Consecutive calls make up one list.
+                    match blocks.last_mut() {
+                        Some(list) if list.trim_start().starts_with("- ") => {
+                            list.push('\n');
+                            list.push_str(&item);
+                        }
+                        _ => blocks.push(item),
+                    }
+                }
+                Entry::Outcome { text, stats, is_error } => {
+                    let mut section = if *is_error { "## ❌ Failed" } else { "## Result" }.to_string();
+                    if let Some(stats) = stats {
+                        section.push_str(&format!("\n\n_{}_", stats));
+                    }
+                    if !text.trim().is_empty() {
+                        section.push_str(&format!("\n\n{}", text.trim()));
+                    }
+                    blocks.push(section);
+                }
+            }
+        }
+        blocks.join("\n\n") + "\n"
+    }
+}
+
+/// A tool line as a list item, with its detail as inline code:
+/// `` 💻 Bash: `cargo test` ``.
+fn tool_item(line: &str) -> String {
+    match line.split_once(": ") {
+        Some((label, detail)) if !detail.is_empty() => format!("{}: {}", label, code_span(detail)),
+        _ => line.to_string(),
+    }
+}
+
+/// `s` as an inline code span, fenced with enough backticks to contain any
+/// it holds.
+fn code_span(s: &str) -> String {
+    let longest = s.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    if longest > 0 {
+        format!("{} {} {}", fence, s, fence)
+    } else {
+        format!("{}{}{}", fence, s, fence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(lines: &[&str]) -> String {
+        let mut export = MarkdownExport::default();
+        for line in lines {
+            let msg: StreamMessage = serde_json::from_str(line).unwrap();
+            let depth = if msg.parent_tool_use_id.is_some() { 1 } else { 0 };
+            export.record(&msg, depth, &Config::default());
+        }
+        export.render()
+    }
+
+    #[test]
+    fn test_render() {
+        let markdown = export(&[
+            r#"{"type": "system", "subtype": "init", "model": "claude-sonnet-4-20250514", "cwd": "/repo"}"#,
+            r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Let me check."}, {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "cargo test"}}, {"type": "tool_use", "id": "t2", "name": "Task", "input": {"description": "Find callers", "prompt": "..."}}]}}"#,
+            r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "1 failed", "is_error": true}]}}"#,
+            r#"{"type": "assistant", "parent_tool_use_id": "t2", "message": {"content": [{"type": "text", "text": "Searching."}, {"type": "tool_use", "id": "t3", "name": "Grep", "input": {"pattern": "fn main"}}]}}"#,
+            r#"{"type": "result", "result": "Fixed the **test**.", "num_turns": 3}"#,
+        ]);
+        assert_eq!(
+            markdown,
+            "# Claude session transcript\n\n\
+             > 🚀 claude (sonnet-4) in /repo\n\n\
+             Let me check.\n\n\
+             - 💻 Bash: `cargo test` ❌\n\
+             - 🤖 Task: `Find callers`\n  \
+               - 🔍 Grep: `fn main`\n\n\
+             ## Result\n\n_3 turns_\n\nFixed the **test**.\n"
+        );
+    }
+
+    #[test]
+    fn test_code_span() {
+        assert_eq!(code_span("ls"), "`ls`");
+        assert_eq!(code_span("echo `date`"), "`` echo `date` ``");
+    }
+}
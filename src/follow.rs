@@ -0,0 +1,161 @@
+//! Reading lines from a log file that is still being written, like `tail -f`.
+
+use std::fs::{File, Metadata};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How often to check a followed file for new data.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// An endless iterator over the lines of a growing file.
+///
+/// Lines are only yielded once their newline has been written, so a writer
+/// flushing half a JSON object never produces a parse error. If the file is
+/// truncated or replaced, it is read again from the start.
+pub struct Follow {
+    path: PathBuf,
+    reader: BufReader<File>,
+    identity: Option<Identity>,
+    pos: u64,
+    partial: String,
+}
+
+impl Follow {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Follow {
+            path: path.to_path_buf(),
+            identity: identity(&file.metadata()?),
+            reader: BufReader::new(file),
+            pos: 0,
+            partial: String::new(),
+        })
+    }
+
+    /// Reopens the file from the start if it shrank since the last read, or
+    /// the path now names another file, as after a log rotation that is
+    /// already as long as what was read.
+    fn check_truncated(&mut self) -> io::Result<()> {
+        let meta = match std::fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            // The file may be briefly missing while it is being replaced.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let replaced = identity(&meta).is_some_and(|identity| self.identity != Some(identity));
+        if meta.len() < self.pos || replaced {
+            let file = File::open(&self.path)?;
+            self.identity = identity(&file.metadata()?);
+            self.reader = BufReader::new(file);
+            self.reader.seek(SeekFrom::Start(0))?;
+            self.pos = 0;
+            self.partial.clear();
+        }
+        Ok(())
+    }
+}
+
+/// What tells one file from another at the same path: the device and inode
+/// on Unix, elsewhere when it was created.
+#[cfg(unix)]
+type Identity = (u64, u64);
+#[cfg(not(unix))]
+type Identity = std::time::SystemTime;
+
+#[cfg(unix)]
+fn identity(meta: &Metadata) -> Option<Identity> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(meta: &Metadata) -> Option<Identity> {
+    meta.created().ok()
+}
+
+impl Iterator for Follow {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let read = match self.reader.read_line(&mut self.partial) {
+                Ok(read) => read,
+                Err(err) => return Some(Err(err)),
+            };
+            self.pos += read as u64;
+
+            if self.partial.ends_with('\n') {
+                let line = self.partial.trim_end_matches(['\n', '\r']).to_string();
+                self.partial.clear();
+                return Some(Ok(line));
+            }
+            if read == 0 {
+                if let Err(err) = self.check_truncated() {
+                    return Some(Err(err));
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("csf-follow-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_waits_for_complete_lines() {
+        let path = temp_path("partial");
+        let mut file = File::create(&path).unwrap();
+        write!(file, "one\r\n{{\"type\":").unwrap();
+        file.flush().unwrap();
+
+        let mut follow = Follow::open(&path).unwrap();
+        assert_eq!(follow.next().unwrap().unwrap(), "one");
+
+        let writer = thread::spawn(move || {
+            thread::sleep(POLL_INTERVAL);
+            writeln!(file, "\"result\"}}").unwrap();
+        });
+        assert_eq!(follow.next().unwrap().unwrap(), "{\"type\":\"result\"}");
+        writer.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rereads_truncated_file() {
+        let path = temp_path("truncated");
+        std::fs::write(&path, "first line\n").unwrap();
+
+        let mut follow = Follow::open(&path).unwrap();
+        assert_eq!(follow.next().unwrap().unwrap(), "first line");
+
+        std::fs::write(&path, "new\n").unwrap();
+        assert_eq!(follow.next().unwrap().unwrap(), "new");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rereads_replaced_file() {
+        let path = temp_path("replaced");
+        let rotated = temp_path("replaced-new");
+        std::fs::write(&path, "first line\n").unwrap();
+
+        let mut follow = Follow::open(&path).unwrap();
+        assert_eq!(follow.next().unwrap().unwrap(), "first line");
+
+        // The new file is already longer than what was read of the old one.
+        std::fs::write(&rotated, "a longer first line\n").unwrap();
+        std::fs::rename(&rotated, &path).unwrap();
+        assert_eq!(follow.next().unwrap().unwrap(), "a longer first line");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
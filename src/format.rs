@@ -0,0 +1,1275 @@
+//! Rendering of individual tool calls and results.
+
+use crate::config::{Config, Labels, Noun, ToolConfig, TruncateConfig};
+use crate::message::{StreamMessage, ToolResultContent, Usage};
+use crate::style::strip_ansi;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How much of long values to show. A limit of 0 means unlimited.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Maximum length of a single displayed value, such as a file path.
+    pub width: usize,
+    /// Maximum length of a Bash command.
+    pub command: usize,
+    /// Maximum length of the final result text and of each line of tool
+    /// output.
+    pub result: usize,
+    /// Maximum length of each block of assistant text.
+    pub text: usize,
+    /// Maximum number of lines in each block of assistant text.
+    pub text_lines: usize,
+    /// Maximum number of lines in a tool result preview.
+    pub preview_lines: usize,
+    /// Maximum width of a whole one-line event, usually the terminal width.
+    pub line: usize,
+    /// Wrap the lines of assistant text and tool output at `line` columns
+    /// rather than cutting them.
+    pub wrap: bool,
+}
+
+impl Limits {
+    pub const UNLIMITED: Limits = Limits {
+        width: 0,
+        command: 0,
+        result: 0,
+        text: 0,
+        text_lines: 0,
+        preview_lines: 0,
+        line: 0,
+        wrap: false,
+    };
+
+    /// These limits with any lengths set in the config file's `[truncate]`
+    /// table.
+    pub fn with_config(self, truncate: &TruncateConfig) -> Limits {
+        Limits {
+            command: truncate.command.unwrap_or(self.command),
+            result: truncate.result.unwrap_or(self.result),
+            text: truncate.text.unwrap_or(self.text),
+            ..self
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            width: 80,
+            command: 80,
+            result: 80,
+            text: 0,
+            text_lines: 0,
+            preview_lines: 5,
+            line: 0,
+            wrap: false,
+        }
+    }
+}
+
+/// The number of terminal columns `s` takes up.
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// Truncates `s` to at most `max_width` terminal columns, ending with `...`
+/// when shortened, or only dots when there isn't room for more. Never
+/// splits a grapheme cluster. A `max_width` of 0 leaves `s` untouched.
+pub fn truncate(s: &str, max_width: usize) -> String {
+    if max_width == 0 || s.width() <= max_width {
+        return s.to_string();
+    }
+    if max_width < 3 {
+        return ".".repeat(max_width);
+    }
+
+    let budget = max_width.saturating_sub(3);
+    let mut out = String::new();
+    let mut width = 0;
+    for grapheme in s.graphemes(true) {
+        width += grapheme.width();
+        if width > budget {
+            break;
+        }
+        out.push_str(grapheme);
+    }
+    out.push_str("...");
+    out
+}
+
+/// The first `max_lines` lines of `text` (all of them for 0), and how many
+/// more were left off.
+pub fn cap_lines(text: &str, max_lines: usize) -> (&str, usize) {
+    if max_lines == 0 {
+        return (text, 0);
+    }
+    match text.match_indices('\n').nth(max_lines - 1) {
+        Some((end, _)) => (&text[..end], text[end + 1..].lines().count()),
+        None => (text, 0),
+    }
+}
+
+/// The note left in place of the lines [`cap_lines`] cut.
+pub fn more_lines(hidden: usize, labels: &Labels) -> String {
+    format!("… (+{} {})", hidden, labels.more_lines)
+}
+
+/// Word-wraps `line` to lines of at most `width` columns; a `width` of 0
+/// leaves it whole. Continuation lines are indented to line up after the
+/// line's own indentation and any list, quote or `↳` marker. ANSI styles
+/// are carried over onto continuation lines, and words longer than a line
+/// are broken.
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    if width == 0 || strip_ansi(line).width() <= width {
+        return vec![line.to_string()];
+    }
+    let indent = hanging_indent(&strip_ansi(line)).min(width / 2);
+
+    let mut lines: Vec<Vec<&str>> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut current_width = 0;
+    for piece in ansi_graphemes(line) {
+        let piece_width = if piece.starts_with('\x1b') { 0 } else { piece.width() };
+        if piece != " " && piece_width > 0 && current_width + piece_width > width {
+            // Break at the last space past the indentation, if there is one.
+            let start = if lines.is_empty() { indent } else { 1 };
+            let space = current.iter().rposition(|&p| p == " ").filter(|&i| i >= start);
+            let rest = match space {
+                Some(i) => current.split_off(i + 1),
+                None => Vec::new(),
+            };
+            while current.last() == Some(&" ") {
+                current.pop();
+            }
+            lines.push(std::mem::take(&mut current));
+            current.push("");
+            current.extend(rest);
+            current_width = indent + current.iter().filter(|p| !p.starts_with('\x1b')).map(|p| p.width()).sum::<usize>();
+        }
+        current.push(piece);
+        current_width += piece_width;
+    }
+    lines.push(current);
+
+    // Close any style left open at the end of a line and reopen it after
+    // the indentation of the next.
+    let mut active: Option<&str> = None;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, pieces)| {
+            let mut out = String::new();
+            if i > 0 {
+                out.push_str(&" ".repeat(indent));
+                out.push_str(active.unwrap_or_default());
+            }
+            for piece in pieces {
+                if piece.starts_with('\x1b') {
+                    active = (piece != "\x1b[0m").then_some(piece);
+                }
+                out.push_str(piece);
+            }
+            if active.is_some() {
+                out.push_str("\x1b[0m");
+            }
+            out
+        })
+        .collect()
+}
+
+/// The width of `line`'s indentation plus any list, quote or `↳` marker
+/// after it.
+fn hanging_indent(line: &str) -> usize {
+    let rest = line.trim_start();
+    let leading = line.len() - rest.len();
+    let marker = ["- ", "* ", "• ", "> ", "↳ "]
+        .iter()
+        .find(|m| rest.starts_with(*m))
+        .map(|m| m.width())
+        .or_else(|| {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let after = &rest[digits..];
+            (digits > 0 && (after.starts_with(". ") || after.starts_with(") "))).then_some(digits + 2)
+        });
+    leading + marker.unwrap_or(0)
+}
+
+/// The width left in a line of `width` columns (0 for unlimited) once
+/// `suffix` is added, keeping at least a few columns for the start.
+pub fn room_for(width: usize, suffix: &str) -> usize {
+    match width {
+        0 => 0,
+        width => width.saturating_sub(display_width(suffix)).max(4),
+    }
+}
+
+/// Splits `s` into grapheme clusters and whole ANSI escape sequences.
+fn ansi_graphemes(s: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("\x1b[") {
+        pieces.extend(rest[..start].graphemes(true));
+        let end = rest[start..].find('m').map_or(rest.len(), |end| start + end + 1);
+        pieces.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    pieces.extend(rest.graphemes(true));
+    pieces
+}
+
+/// The complete tool input as indented, pretty-printed JSON.
+pub fn format_tool_input(input: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(input)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats a token count compactly: `950`, `1.2k`, `15k`, `2.3M`.
+pub fn format_tokens(n: u64) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=9_999 => format!("{:.1}k", n as f64 / 1e3).replace(".0k", "k"),
+        10_000..=999_999 => format!("{}k", (n as f64 / 1e3).round()),
+        _ => format!("{:.1}M", n as f64 / 1e6).replace(".0M", "M"),
+    }
+}
+
+/// Input and output tokens, e.g. `1.2k→340 tok`. Input counts cached
+/// tokens too, as they fill the context all the same.
+pub fn format_usage(usage: &Usage, labels: &Labels) -> String {
+    format!("{}→{} {}", format_tokens(usage.total_input()), format_tokens(usage.output_tokens), labels.tok)
+}
+
+/// Formats a duration as `12.4s`, or `3m 05s` from a minute up.
+pub fn format_duration(ms: u64) -> String {
+    if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1e3)
+    } else {
+        let secs = ms / 1000;
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Shortens a model id for display: `claude-sonnet-4-20250514` → `sonnet-4`.
+pub fn short_model(model: &str) -> &str {
+    let model = model.strip_prefix("claude-").unwrap_or(model);
+    match model.rsplit_once('-') {
+        Some((name, date)) if date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => model,
+    }
+}
+
+/// The first 8 characters of a session id.
+pub fn short_session(session_id: &str) -> &str {
+    session_id.get(..8).unwrap_or(session_id)
+}
+
+/// The session header for a `system`/`init` message, e.g.
+/// `🚀 claude (sonnet-4) in /path/to/project [session abc12345]`.
+pub fn format_init(msg: &StreamMessage, labels: &Labels) -> String {
+    let mut header = "🚀 claude".to_string();
+    if let Some(model) = &msg.model {
+        header.push_str(&format!(" ({})", short_model(model)));
+    }
+    if let Some(cwd) = &msg.cwd {
+        header.push_str(&format!(" {} {}", labels.working_in, cwd));
+    }
+    if let Some(session_id) = &msg.session_id {
+        header.push_str(&format!(" [{} {}]", labels.session, short_session(session_id)));
+    }
+    header
+}
+
+/// The marker for a resumed session, e.g.
+/// `↩️ Resumed session abc12345 (previously 14 turns)`.
+pub fn format_resumed(session_id: &str, prior_turns: u32, labels: &Labels) -> String {
+    let mut line = format!("↩️ {} {}", labels.resumed_session, short_session(session_id));
+    if prior_turns > 0 {
+        line.push_str(&format!(" ({} {})", labels.previously, plural(prior_turns as usize, &labels.turn)));
+    }
+    line
+}
+
+/// The marker for the model changing mid-session, as on a fallback or
+/// `/model`, e.g. `🔄 model changed: opus-4 → sonnet-4`.
+pub fn format_model_change(old: &str, new: &str, labels: &Labels) -> String {
+    format!("🔄 {}: {} → {}", labels.model_changed, short_model(old), short_model(new))
+}
+
+/// The run statistics from a result message, e.g.
+/// `3 turns, 12.4s, $0.0342, 15k in / 2k out tokens`. For a resumed
+/// session, the turns count on from its `prior_turns`: `3 turns, 17 in
+/// session`.
+pub fn format_stats(msg: &StreamMessage, prior_turns: u32, labels: &Labels) -> Option<String> {
+    let mut parts = Vec::new();
+    let turns = |turns: u32| plural(turns as usize, &labels.turn);
+    match msg.num_turns {
+        Some(n) if prior_turns > 0 => parts.push(format!("{}, {} {}", turns(n), prior_turns + n, labels.in_session)),
+        Some(n) => parts.push(turns(n)),
+        None => {}
+    }
+    if let Some(ms) = msg.duration_ms {
+        parts.push(format_duration(ms));
+    }
+    if let Some(cost) = msg.total_cost_usd {
+        parts.push(format!("${:.4}", cost));
+    }
+    if let Some(usage) = &msg.usage {
+        parts.push(format!(
+            "{} {} / {} {}",
+            format_tokens(usage.total_input()),
+            labels.tokens_in,
+            format_tokens(usage.output_tokens),
+            labels.tokens_out
+        ));
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+/// A count of `noun`: `1 turn`, `3 turns`.
+pub fn plural(n: usize, noun: &Noun) -> String {
+    format!("{} {}", n, if n == 1 { &noun.one } else { &noun.many })
+}
+
+/// An indented preview of a tool's output, shown under its tool_use line.
+pub fn format_tool_result(content: Option<&ToolResultContent>, labels: &Labels, limits: &Limits) -> String {
+    format_output(&content.map(|c| c.text()).unwrap_or_default(), labels, limits)
+}
+
+/// An indented preview of a tool's output text.
+pub fn format_output(text: &str, labels: &Labels, limits: &Limits) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    if lines.is_empty() {
+        return format!("  ↳ ({})", labels.no_output);
+    }
+
+    let shown = match limits.preview_lines {
+        0 => lines.len(),
+        n => n.min(lines.len()),
+    };
+    let mut output: Vec<String> = lines[..shown]
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { "  ↳ " } else { "    " };
+            let line = format!("{}{}", prefix, truncate(line, limits.result));
+            if limits.wrap {
+                wrap(&line, limits.line).join("\n")
+            } else {
+                truncate(&line, limits.line)
+            }
+        })
+        .collect();
+    if lines.len() > shown {
+        output.push(format!("    … +{}", plural(lines.len() - shown, &labels.line)));
+    }
+    output.join("\n")
+}
+
+/// A summary of a WebFetch or WebSearch result, which can be a whole page:
+/// its title, size and first lines, or for a search, the titles of the
+/// results found.
+pub fn format_web_result(text: &str, labels: &Labels, limits: &Limits) -> String {
+    let size = format_bytes(text.len());
+    let title = web_title(text);
+    let header = match &title {
+        Some(title) => format!("  ↳ 📄 {} ({})", truncate(title, limits.width), size),
+        None => format!("  ↳ 📄 {}", size),
+    };
+    let header = truncate(&header, limits.line);
+
+    let lines: Vec<String> = match search_links(text) {
+        Some(titles) => titles.into_iter().map(|title| format!("• {}", title)).collect(),
+        None => {
+            let mut lines: Vec<String> = text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('<'))
+                .map(str::to_string)
+                .collect();
+            // Don't repeat a title taken from the first line.
+            if lines.first().map(|line| line.trim_start_matches('#').trim()) == title.as_deref() {
+                lines.remove(0);
+            }
+            lines
+        }
+    };
+    let shown = match limits.preview_lines {
+        0 => lines.len(),
+        n => n.min(lines.len()),
+    };
+    let mut output = vec![header];
+    output.extend(lines[..shown].iter().map(|line| truncate(&format!("    {}", truncate(line, limits.result)), limits.line)));
+    if lines.len() > shown {
+        output.push(format!("    … +{}", plural(lines.len() - shown, &labels.line)));
+    }
+    output.join("\n")
+}
+
+/// The `<title>` of an HTML page, or else its first line without any
+/// Markdown heading marks.
+fn web_title(text: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(start) = lower.find("<title") {
+        let open = start + lower[start..].find('>')? + 1;
+        let close = open + lower[open..].find("</title")?;
+        let title = text[open..close].trim();
+        if !title.is_empty() {
+            return Some(title.to_string());
+        }
+    }
+    let first = text.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('<'))?;
+    Some(first.trim_start_matches('#').trim().to_string())
+}
+
+/// The titles in the `Links: [...]` line of a WebSearch result.
+fn search_links(text: &str) -> Option<Vec<String>> {
+    let links = text.lines().find_map(|line| line.trim().strip_prefix("Links: "))?;
+    let links: Vec<serde_json::Value> = serde_json::from_str(links).ok()?;
+    Some(links.iter().filter_map(|link| Some(link.get("title")?.as_str()?.to_string())).collect())
+}
+
+/// A byte count for people, e.g. `512 B` or `12.3 KB`.
+pub fn format_bytes(n: usize) -> String {
+    match n {
+        0..=999 => format!("{} B", n),
+        1_000..=999_999 => format!("{:.1} KB", n as f64 / 1_000.0),
+        _ => format!("{:.1} MB", n as f64 / 1_000_000.0),
+    }
+}
+
+/// The exit code Claude Code reports on the first line of a Bash result
+/// (`Exit code 1`, sometimes after `Error: `), and the output after it.
+pub fn bash_exit_code(text: &str) -> (Option<i32>, &str) {
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let code = first.trim().trim_start_matches("Error: ").strip_prefix("Exit code ");
+    match code.and_then(|code| code.trim().parse().ok()) {
+        Some(code) => (Some(code), rest),
+        None => (None, text),
+    }
+}
+
+/// The id of the background shell a Bash call started, from its result
+/// (`Command running in background with ID: a1b2`).
+pub fn background_shell(text: &str) -> Option<&str> {
+    let (_, id) = text.split_once("running in background with ID:")?;
+    id.split_whitespace().next()
+}
+
+/// The background shell a BashOutput or KillShell call names.
+pub fn shell_id(input: &serde_json::Value) -> &str {
+    ["bash_id", "shell_id"].iter().find_map(|key| input.get(key).and_then(|v| v.as_str())).unwrap_or("?")
+}
+
+/// A BashOutput result: the shell's status under its tag, such as
+/// `[bg a1b2] running`, and what it printed since it was last asked.
+pub fn format_shell_output(shell: &str, text: &str, labels: &Labels, limits: &Limits) -> String {
+    let tag = |name: &str| {
+        let (_, rest) = text.split_once(&format!("<{}>", name))?;
+        let (value, _) = rest.split_once(&format!("</{}>", name))?;
+        Some(value.trim_matches('\n'))
+    };
+    let Some(status) = tag("status") else {
+        return format_output(&format!("[bg {}]\n{}", shell, text), labels, limits);
+    };
+    let mut lines = vec![match tag("exit_code") {
+        Some(code) => format!("[bg {}] {} ({} {})", shell, status, labels.exit, code),
+        None => format!("[bg {}] {}", shell, status),
+    }];
+    lines.extend(["stdout", "stderr"].into_iter().filter_map(tag).filter(|output| !output.trim().is_empty()).map(str::to_string));
+    format_output(&lines.join("\n"), labels, limits)
+}
+
+/// The questions an AskUserQuestion call asks, each with its `question`
+/// and `options`. A call asking a single question may give them at the
+/// top level.
+fn questions(input: &serde_json::Value) -> Vec<&serde_json::Value> {
+    match input.get("questions").and_then(|v| v.as_array()) {
+        Some(questions) => questions.iter().collect(),
+        None if input.get("question").is_some() => vec![input],
+        None => Vec::new(),
+    }
+}
+
+/// The choices of an AskUserQuestion call as numbered lists, each after
+/// its question but the first, which the tool line shows.
+pub fn format_questions(input: &serde_json::Value) -> Option<String> {
+    let mut lines = Vec::new();
+    for (i, question) in questions(input).into_iter().enumerate() {
+        if i > 0 {
+            let text = question.get("question").and_then(|v| v.as_str()).unwrap_or("?");
+            lines.push(format!("❓ {}", text));
+        }
+        let options = question.get("options").and_then(|v| v.as_array()).map(Vec::as_slice).unwrap_or_default();
+        for (n, option) in options.iter().enumerate() {
+            let label = option.as_str().or_else(|| option.get("label").and_then(|v| v.as_str())).unwrap_or("?");
+            match option.get("description").and_then(|v| v.as_str()).filter(|d| !d.is_empty()) {
+                Some(description) => lines.push(format!("    {}. {} — {}", n + 1, label, description)),
+                None => lines.push(format!("    {}. {}", n + 1, label)),
+            }
+        }
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// The plan an ExitPlanMode call puts forward, if it isn't empty.
+pub fn plan(input: &serde_json::Value) -> Option<&str> {
+    input.get("plan").and_then(|v| v.as_str()).map(str::trim).filter(|plan| !plan.is_empty())
+}
+
+/// A plan's title, its first line without any heading marks, and the rest
+/// of it.
+pub fn split_plan(plan: &str) -> (&str, &str) {
+    let (first, rest) = plan.split_once('\n').unwrap_or((plan, ""));
+    (first.trim_start_matches('#').trim(), rest.trim_matches('\n'))
+}
+
+/// The one-line summary for a tool_use block, applying any overrides from
+/// the config file.
+pub fn format_tool(name: &str, input: &serde_json::Value, config: &Config, limits: &Limits) -> String {
+    if let Some(template) = config.templates.get(name) {
+        return template.render(name, input);
+    }
+    if let Some(tool) = config.tools.get(name) {
+        return format_configured_tool(name, input, tool, limits);
+    }
+    match parse_mcp(name) {
+        Some((server, tool)) if config.servers.contains_key(server) => {
+            format_mcp_tool(server, tool, input, &config.servers[server], limits)
+        }
+        _ => format_tool_use(name, input, &config.labels, limits),
+    }
+}
+
+/// Splits an MCP tool name, `mcp__<server>__<tool>`, into its server and
+/// tool names.
+pub fn parse_mcp(name: &str) -> Option<(&str, &str)> {
+    let (server, tool) = name.strip_prefix("mcp__")?.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some((server, tool))
+}
+
+/// The value of the first non-empty string field of `input`, the best
+/// guess at what an unknown tool is working on.
+fn first_string(input: &serde_json::Value) -> Option<&str> {
+    input.as_object()?.values().find_map(|v| v.as_str().filter(|s| !s.is_empty()))
+}
+
+/// An MCP tool call: `🔌 server:tool: <value>`, with `server_config`
+/// overriding the emoji, the server's label and the fields shown.
+fn format_mcp_tool(
+    server: &str,
+    tool: &str,
+    input: &serde_json::Value,
+    server_config: &ToolConfig,
+    limits: &Limits,
+) -> String {
+    let emoji = server_config.emoji.as_deref().unwrap_or("🔌");
+    let label = format!("{}:{}", server_config.label.as_deref().unwrap_or(server), tool);
+    let value = match &server_config.fields {
+        Some(fields) => field_values(input, fields.iter().map(String::as_str)).join(" "),
+        None => first_string(input).unwrap_or_default().to_string(),
+    };
+    if value.is_empty() {
+        format!("{} {}", emoji, label)
+    } else {
+        let max_len = server_config.max_len.unwrap_or(limits.width);
+        format!("{} {}: {}", emoji, label, truncate(&value, max_len))
+    }
+}
+
+/// The values of `fields` in `input` that are set, as strings.
+fn field_values<'a>(input: &serde_json::Value, fields: impl Iterator<Item = &'a str>) -> Vec<String> {
+    fields
+        .filter_map(|field| match input.get(field)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        })
+        .collect()
+}
+
+/// Extended thinking, with a `🧠` on the first line and later lines
+/// indented to match. The text is cut to `max_width` columns in total,
+/// or not at all if it is 0.
+pub fn format_thinking(thinking: &str, max_width: usize) -> String {
+    let thinking = truncate(thinking.trim(), max_width);
+    thinking
+        .lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 { format!("🧠 {}", line) } else { format!("   {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The line for a prompt asking whether a tool may run, e.g.
+/// `🔐 Permission requested: Bash (rm -rf build)`.
+pub fn format_permission_request(name: &str, input: &serde_json::Value, config: &Config, limits: &Limits) -> String {
+    let line = format_tool(name, input, config, limits);
+    let label = &config.labels.permission_requested;
+    match line.split_once(": ") {
+        Some((_, detail)) => format!("🔐 {}: {} ({})", label, name, detail),
+        None => format!("🔐 {}: {}", label, name),
+    }
+}
+
+/// A note on why an assistant message ended, for the reasons that leave
+/// it unfinished: `⚠️ Response truncated (max_tokens)` or `🚫 Model refused`.
+pub fn format_stop_reason(reason: &str, labels: &Labels) -> Option<String> {
+    match reason {
+        "max_tokens" | "model_context_window_exceeded" => Some(format!("⚠️ {} ({})", labels.response_truncated, reason)),
+        "refusal" => Some(format!("🚫 {}", labels.model_refused)),
+        _ => None,
+    }
+}
+
+/// The line for the answer to a permission prompt for `tool`, if known.
+pub fn format_permission_response(allowed: bool, message: Option<&str>, tool: Option<&str>, labels: &Labels) -> String {
+    let mut line = if allowed {
+        format!("✅ {}", labels.permission_granted)
+    } else {
+        format!("🚫 {}", labels.denied)
+    };
+    if let Some(tool) = tool {
+        line.push_str(&format!(": {}", tool));
+    }
+    if let Some(message) = message.filter(|m| !m.is_empty()) {
+        line.push_str(&format!(" — {}", message));
+    }
+    line
+}
+
+/// Whether a call to the tool `name` starts a sub-agent, whose messages
+/// name the call as their `parent_tool_use_id`.
+pub fn is_agent_tool(name: &str) -> bool {
+    matches!(name, "Task" | "Agent" | "dispatch_agent")
+}
+
+/// The emoji and displayed input fields of a built-in tool.
+fn tool_defaults(name: &str) -> (&'static str, &'static [&'static str]) {
+    match name {
+        "Read" => ("📖", &["file_path"]),
+        "Edit" | "MultiEdit" => ("✏️ ", &["file_path"]),
+        "Write" => ("📝", &["file_path"]),
+        "Bash" => ("💻", &["command"]),
+        "BashOutput" => ("💻", &["bash_id"]),
+        "KillShell" | "KillBash" => ("🛑", &["shell_id"]),
+        "ExitPlanMode" => ("🗺️ ", &["plan"]),
+        "AskUserQuestion" => ("❓", &["question"]),
+        "Glob" | "Grep" => ("🔍", &["pattern"]),
+        "TodoWrite" => ("📋", &[]),
+        "Task" | "Agent" | "dispatch_agent" => ("🤖", &["description"]),
+        "LS" => ("📂", &["path"]),
+        "WebFetch" => ("🌐", &["url"]),
+        "WebSearch" => ("🔎", &["query"]),
+        "NotebookRead" | "NotebookEdit" => ("📓", &["notebook_path"]),
+        _ if parse_mcp(name).is_some() => ("🔌", &[]),
+        _ => ("🔧", &[]),
+    }
+}
+
+/// Formats a tool as `<emoji> <label>: <fields>`, taking anything the
+/// config leaves unset from the built-in defaults.
+fn format_configured_tool(
+    name: &str,
+    input: &serde_json::Value,
+    tool: &ToolConfig,
+    limits: &Limits,
+) -> String {
+    let (default_emoji, default_fields) = tool_defaults(name);
+    let emoji = tool.emoji.as_deref().unwrap_or(default_emoji);
+    let label = tool.label.as_deref().unwrap_or(name);
+    let values = match &tool.fields {
+        Some(fields) => field_values(input, fields.iter().map(String::as_str)),
+        None if parse_mcp(name).is_some() => first_string(input).map(str::to_string).into_iter().collect(),
+        None => field_values(input, default_fields.iter().copied()),
+    };
+
+    if values.is_empty() {
+        format!("{} {}", emoji, label)
+    } else {
+        let max_len = tool.max_len.unwrap_or(limits.width);
+        format!("{} {}: {}", emoji, label, truncate(&values.join(" "), max_len))
+    }
+}
+
+fn todos(input: &serde_json::Value) -> &[serde_json::Value] {
+    input
+        .get("todos")
+        .and_then(|v| v.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn todo_status(todo: &serde_json::Value) -> &str {
+    todo.get("status").and_then(|v| v.as_str()).unwrap_or("pending")
+}
+
+fn todo_content(todo: &serde_json::Value) -> &str {
+    todo.get("content").and_then(|v| v.as_str()).unwrap_or("?")
+}
+
+/// A one-line count of a TodoWrite list, e.g. `2/5 done, ◐ Run the tests`.
+fn todo_summary(input: &serde_json::Value, labels: &Labels) -> Option<String> {
+    let todos = todos(input);
+    if todos.is_empty() {
+        return None;
+    }
+
+    let done = todos.iter().filter(|t| todo_status(t) == "completed").count();
+    let mut summary = format!("{}/{} {}", done, todos.len(), labels.todos_done);
+    if let Some(current) = todos.iter().find(|t| todo_status(t) == "in_progress") {
+        summary.push_str(&format!(", ◐ {}", todo_content(current)));
+    }
+    Some(summary)
+}
+
+/// The ` [cell N]` suffix for a notebook tool, from its `cell_id` or
+/// `cell_number`; empty if neither is given.
+fn notebook_cell(input: &serde_json::Value) -> String {
+    let cell = match (input.get("cell_id"), input.get("cell_number")) {
+        (Some(serde_json::Value::String(id)), _) => id.clone(),
+        (_, Some(number)) if number.is_u64() => number.to_string(),
+        _ => return String::new(),
+    };
+    format!(" [cell {}]", cell)
+}
+
+/// Every item of a TodoWrite list with its status checkbox, one per line.
+pub fn format_todos(input: &serde_json::Value) -> Option<String> {
+    let todos = todos(input);
+    if todos.is_empty() {
+        return None;
+    }
+
+    let lines: Vec<String> = todos
+        .iter()
+        .map(|todo| {
+            let checkbox = match todo_status(todo) {
+                "completed" => "☑",
+                "in_progress" => "◐",
+                _ => "☐",
+            };
+            format!("    {} {}", checkbox, todo_content(todo))
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// The one-line summary shown for a tool_use block.
+pub fn format_tool_use(name: &str, input: &serde_json::Value, labels: &Labels, limits: &Limits) -> String {
+    match name {
+        "Read" => {
+            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("📖 Read: {}", file_path)
+        }
+        "Edit" => {
+            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("✏️  Edit: {}", file_path)
+        }
+        "MultiEdit" => {
+            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
+            let count = input.get("edits").and_then(|v| v.as_array()).map_or(0, Vec::len);
+            format!("✏️  MultiEdit: {} ({})", file_path, plural(count, &labels.compact.edit))
+        }
+        "Write" => {
+            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("📝 Write: {}", file_path)
+        }
+        "Bash" => {
+            let command = input.get("command").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("💻 Bash: {}", truncate(command, limits.command))
+        }
+        "Glob" => {
+            let pattern = input.get("pattern").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("🔍 Glob: {}", pattern)
+        }
+        "Grep" => {
+            let pattern = input.get("pattern").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("🔍 Grep: {}", pattern)
+        }
+        "WebFetch" => {
+            let url = input.get("url").and_then(|v| v.as_str()).unwrap_or("?");
+            match input.get("prompt").and_then(|v| v.as_str()) {
+                Some(prompt) => format!("🌐 WebFetch: {} — {}", url, truncate(prompt, limits.width / 2)),
+                None => format!("🌐 WebFetch: {}", url),
+            }
+        }
+        "WebSearch" => {
+            let query = input.get("query").and_then(|v| v.as_str()).unwrap_or("?");
+            let domains: Vec<&str> = input
+                .get("allowed_domains")
+                .and_then(|v| v.as_array())
+                .map(|domains| domains.iter().filter_map(|d| d.as_str()).collect())
+                .unwrap_or_default();
+            if domains.is_empty() {
+                format!("🔎 WebSearch: {}", query)
+            } else {
+                format!("🔎 WebSearch: {} ({} {})", query, labels.only, domains.join(", "))
+            }
+        }
+        "NotebookRead" => {
+            let path = input.get("notebook_path").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("📓 NotebookRead: {}{}", path, notebook_cell(input))
+        }
+        "NotebookEdit" => {
+            let path = input.get("notebook_path").and_then(|v| v.as_str()).unwrap_or("?");
+            let mode = input.get("edit_mode").and_then(|v| v.as_str()).unwrap_or("replace");
+            format!("📓 NotebookEdit: {}{} ({})", path, notebook_cell(input), mode)
+        }
+        "TodoWrite" => match todo_summary(input, labels) {
+            Some(summary) => format!("📋 TodoWrite: {}", summary),
+            None => "📋 TodoWrite".to_string(),
+        },
+        "Task" => {
+            let description = input.get("description").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("🤖 Task: {}", description)
+        }
+        "Agent" | "dispatch_agent" => {
+            let agent = match input.get("subagent_type").and_then(|v| v.as_str()) {
+                Some(kind) => format!("{} ({})", name, kind),
+                None => name.to_string(),
+            };
+            let description = input.get("description").and_then(|v| v.as_str());
+            let prompt = input.get("prompt").and_then(|v| v.as_str()).and_then(|p| p.lines().find(|l| !l.trim().is_empty()));
+            match (description, prompt) {
+                (Some(description), Some(prompt)) => {
+                    format!("🤖 {}: {} — {}", agent, description, truncate(prompt.trim(), limits.width / 2))
+                }
+                (Some(text), None) | (None, Some(text)) => format!("🤖 {}: {}", agent, truncate(text.trim(), limits.width)),
+                (None, None) => format!("🤖 {}", agent),
+            }
+        }
+        "LS" => {
+            let path = input.get("path").and_then(|v| v.as_str()).unwrap_or("?");
+            format!("📂 LS: {}", path)
+        }
+        "BashOutput" => match input.get("filter").and_then(|v| v.as_str()) {
+            Some(filter) => format!("💻 [bg {}] {} ({}: {})", shell_id(input), labels.output_requested, labels.filter, filter),
+            None => format!("💻 [bg {}] {}", shell_id(input), labels.output_requested),
+        },
+        "KillShell" | "KillBash" => format!("🛑 {} {}", labels.killed_shell, shell_id(input)),
+        "AskUserQuestion" => {
+            let questions = questions(input);
+            let question = questions.first().and_then(|q| q.get("question")).and_then(|v| v.as_str()).unwrap_or("?");
+            match questions.len() {
+                0 | 1 => format!("❓ {}", truncate(question, limits.width)),
+                n => format!("❓ {} (+{} {})", truncate(question, limits.width), n - 1, labels.more),
+            }
+        }
+        "ExitPlanMode" => match plan(input).map(split_plan) {
+            Some((title, _)) => format!("🗺️  Plan: {}", truncate(title, limits.width)),
+            None => "🗺️  Plan".to_string(),
+        },
+        _ => match parse_mcp(name) {
+            Some((server, tool)) => format_mcp_tool(server, tool, input, &ToolConfig::default(), limits),
+            None => format!("🔧 {}", name),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_function() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("this is a long string", 10), "this is...");
+        assert_eq!(truncate("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate("this is a long string", 0), "this is a long string");
+        assert_eq!(truncate("this is a long string", 3), "...");
+        assert_eq!(truncate("this is a long string", 2), "..");
+        assert_eq!(truncate("this is a long string", 1), ".");
+        assert_eq!(truncate("ab", 2), "ab");
+    }
+
+    #[test]
+    fn test_mcp_tools() {
+        assert_eq!(parse_mcp("mcp__github__create_issue"), Some(("github", "create_issue")));
+        assert_eq!(parse_mcp("mcp__my_server__list"), Some(("my_server", "list")));
+        assert_eq!(parse_mcp("mcp__broken"), None);
+        assert_eq!(parse_mcp("Read"), None);
+
+        let config = Config::default();
+        let limits = Limits::default();
+        let input = serde_json::json!({"limit": 5, "query": "login bug", "team": "web"});
+        assert_eq!(
+            format_tool("mcp__linear__search_issues", &input, &config, &limits),
+            "🔌 linear:search_issues: login bug"
+        );
+        assert_eq!(
+            format_tool("mcp__linear__list_teams", &serde_json::json!({}), &config, &limits),
+            "🔌 linear:list_teams"
+        );
+
+        let config = Config::parse(
+            "[servers.linear]\nemoji = \"📐\"\nlabel = \"Linear\"\nfields = [\"team\", \"query\"]\n\n[tools.mcp__linear__list_teams]\nlabel = \"Teams\"",
+        )
+        .unwrap();
+        assert_eq!(
+            format_tool("mcp__linear__search_issues", &input, &config, &limits),
+            "📐 Linear:search_issues: web login bug"
+        );
+        assert_eq!(
+            format_tool("mcp__linear__list_teams", &serde_json::json!({}), &config, &limits),
+            "🔌 Teams"
+        );
+    }
+
+    #[test]
+    fn test_notebook_tools() {
+        let limits = Limits::default();
+        let input = serde_json::json!({"notebook_path": "analysis.ipynb", "cell_id": "3"});
+        assert_eq!(format_tool_use("NotebookRead", &input, &Labels::default(), &limits), "📓 NotebookRead: analysis.ipynb [cell 3]");
+        let input = serde_json::json!({"notebook_path": "analysis.ipynb"});
+        assert_eq!(format_tool_use("NotebookRead", &input, &Labels::default(), &limits), "📓 NotebookRead: analysis.ipynb");
+        let input = serde_json::json!({"notebook_path": "a.ipynb", "cell_number": 2, "edit_mode": "insert", "new_source": "x = 1"});
+        assert_eq!(format_tool_use("NotebookEdit", &input, &Labels::default(), &limits), "📓 NotebookEdit: a.ipynb [cell 2] (insert)");
+        let input = serde_json::json!({"notebook_path": "a.ipynb", "cell_id": "abc", "new_source": "x = 1"});
+        assert_eq!(format_tool_use("NotebookEdit", &input, &Labels::default(), &limits), "📓 NotebookEdit: a.ipynb [cell abc] (replace)");
+    }
+
+    #[test]
+    fn test_format_thinking() {
+        let thinking = "\nThe test fails because\nthe fixture is stale.\n";
+        assert_eq!(format_thinking(thinking, 0), "🧠 The test fails because\n   the fixture is stale.");
+        assert_eq!(format_thinking(thinking, 30), "🧠 The test fails because\n   the ...");
+    }
+
+    #[test]
+    fn test_format_permission() {
+        let config = Config::default();
+        let limits = Limits::default();
+        assert_eq!(
+            format_permission_request("Bash", &serde_json::json!({"command": "rm -rf build"}), &config, &limits),
+            "🔐 Permission requested: Bash (rm -rf build)"
+        );
+        assert_eq!(
+            format_permission_request("TodoWrite", &serde_json::json!({}), &config, &limits),
+            "🔐 Permission requested: TodoWrite"
+        );
+        let labels = Labels::default();
+        assert_eq!(format_permission_response(true, None, Some("Bash"), &labels), "✅ Permission granted: Bash");
+        assert_eq!(
+            format_permission_response(false, Some("not in CI"), None, &labels),
+            "🚫 Denied — not in CI"
+        );
+    }
+
+    #[test]
+    fn test_truncate_wide_and_multibyte() {
+        // Each CJK character is two columns wide.
+        assert_eq!(truncate("日本語のテキスト", 9), "日本語...");
+        assert_eq!(truncate("héllo wörld", 8), "héllo...");
+        // A family emoji is one grapheme made of several code points.
+        assert_eq!(truncate("👨‍👩‍👧 family", 5), "👨‍👩‍👧...");
+        assert_eq!(display_width("日本"), 4);
+    }
+
+    #[test]
+    fn test_format_tool_input() {
+        let input = serde_json::json!({"command": "ls"});
+        assert_eq!(format_tool_input(&input), "    {\n      \"command\": \"ls\"\n    }");
+    }
+
+    #[test]
+    fn test_unlimited_tool_result() {
+        let content = ToolResultContent::Text("1\n2\n3\n4\n5\n6".to_string());
+        assert_eq!(format_tool_result(Some(&content), &Labels::default(), &Limits::UNLIMITED).lines().count(), 6);
+    }
+
+    #[test]
+    fn test_bash_exit_code() {
+        assert_eq!(bash_exit_code("Exit code 1\nerror: 2 tests failed"), (Some(1), "error: 2 tests failed"));
+        assert_eq!(bash_exit_code("Error: Exit code 127\nsh: foo: not found"), (Some(127), "sh: foo: not found"));
+        assert_eq!(bash_exit_code("Exit code 2"), (Some(2), ""));
+        assert_eq!(bash_exit_code("test result: ok"), (None, "test result: ok"));
+    }
+
+    #[test]
+    fn test_background_shells() {
+        let limits = Limits::default();
+        assert_eq!(format_tool_use("BashOutput", &serde_json::json!({"bash_id": "a1b2"}), &Labels::default(), &limits), "💻 [bg a1b2] output requested");
+        assert_eq!(
+            format_tool_use("BashOutput", &serde_json::json!({"bash_id": "a1b2", "filter": "error"}), &Labels::default(), &limits),
+            "💻 [bg a1b2] output requested (filter: error)"
+        );
+        assert_eq!(format_tool_use("KillShell", &serde_json::json!({"shell_id": "a1b2"}), &Labels::default(), &limits), "🛑 killed shell a1b2");
+        assert_eq!(background_shell("Command running in background with ID: a1b2"), Some("a1b2"));
+        assert_eq!(background_shell("ok"), None);
+
+        let labels = Labels::default();
+        let text = "<status>running</status>\n\n<stdout>\nCompiling app\nRunning tests\n</stdout>\n\n<timestamp>2025-08-20T10:00:00Z</timestamp>";
+        assert_eq!(
+            format_shell_output("a1b2", text, &labels, &limits),
+            "  ↳ [bg a1b2] running\n    Compiling app\n    Running tests"
+        );
+        let text = "<status>completed</status>\n\n<exit_code>1</exit_code>\n\n<stderr>\nerror: 2 failed\n</stderr>";
+        assert_eq!(format_shell_output("a1b2", text, &labels, &limits), "  ↳ [bg a1b2] completed (exit 1)\n    error: 2 failed");
+        assert_eq!(format_shell_output("a1b2", "No output", &labels, &limits), "  ↳ [bg a1b2]\n    No output");
+    }
+
+    #[test]
+    fn test_questions() {
+        let input = serde_json::json!({"questions": [
+            {"question": "Which database?", "header": "DB", "multiSelect": false, "options": [
+                {"label": "Postgres", "description": "Already deployed"},
+                {"label": "SQLite"},
+            ]},
+            {"question": "Add a migration?", "options": [{"label": "Yes"}, {"label": "No"}]},
+        ]});
+        assert_eq!(format_tool_use("AskUserQuestion", &input, &Labels::default(), &Limits::default()), "❓ Which database? (+1 more)");
+        assert_eq!(
+            format_questions(&input).unwrap(),
+            "    1. Postgres — Already deployed\n    2. SQLite\n❓ Add a migration?\n    1. Yes\n    2. No"
+        );
+
+        let input = serde_json::json!({"question": "Proceed?", "options": ["yes", "no"]});
+        assert_eq!(format_tool_use("AskUserQuestion", &input, &Labels::default(), &Limits::default()), "❓ Proceed?");
+        assert_eq!(format_questions(&input).unwrap(), "    1. yes\n    2. no");
+        assert_eq!(format_questions(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_plan() {
+        let input = serde_json::json!({"plan": "## Fix the login bug\n\n1. Reproduce it\n2. Fix the session check\n   - add a test"});
+        assert_eq!(format_tool_use("ExitPlanMode", &input, &Labels::default(), &Limits::default()), "🗺️  Plan: Fix the login bug");
+        assert_eq!(
+            split_plan(plan(&input).unwrap()),
+            ("Fix the login bug", "1. Reproduce it\n2. Fix the session check\n   - add a test")
+        );
+        assert_eq!(format_tool_use("ExitPlanMode", &serde_json::json!({"plan": " "}), &Labels::default(), &Limits::default()), "🗺️  Plan");
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("short", 10), vec!["short"]);
+        assert_eq!(wrap("the quick brown fox jumps", 10), vec!["the quick", "brown fox", "jumps"]);
+        assert_eq!(wrap("- the quick brown fox", 12), vec!["- the quick", "  brown fox"]);
+        assert_eq!(wrap("  12. one two three", 12), vec!["  12. one", "      two", "      three"]);
+        assert_eq!(wrap("  ↳ abcdefghijkl", 10), vec!["  ↳ abcdef", "    ghijkl"]);
+        assert_eq!(wrap("supercalifragilistic", 8), vec!["supercal", "ifragili", "stic"]);
+        assert_eq!(wrap("anything", 0), vec!["anything"]);
+    }
+
+    #[test]
+    fn test_wrap_styles() {
+        let line = "\x1b[97mthe quick brown\x1b[0m fox";
+        assert_eq!(
+            wrap(line, 10),
+            vec!["\x1b[97mthe quick\x1b[0m", "\x1b[97mbrown\x1b[0m fox"]
+        );
+    }
+
+    #[test]
+    fn test_cap_lines() {
+        assert_eq!(cap_lines("a\nb\nc\nd", 2), ("a\nb", 2));
+        assert_eq!(cap_lines("a\nb", 2), ("a\nb", 0));
+        assert_eq!(cap_lines("a\nb\n", 2), ("a\nb", 0));
+        assert_eq!(cap_lines("a\nb\nc", 0), ("a\nb\nc", 0));
+        assert_eq!(more_lines(4920, &Labels::default()), "… (+4920 more lines, use --verbose)");
+    }
+
+    #[test]
+    fn test_wrapped_tool_result() {
+        let limits = Limits {
+            line: 12,
+            wrap: true,
+            ..Limits::default()
+        };
+        assert_eq!(format_output("one two three four", &Labels::default(), &limits), "  ↳ one two\n    three\n    four");
+    }
+
+    #[test]
+    fn test_web_result() {
+        let page = format!(
+            "<html><head><TITLE>Serde derive</TITLE></head>\n# Derive\n\nLine one\nLine two\n{}",
+            "x\n".repeat(5)
+        );
+        assert_eq!(
+            format_web_result(&page, &Labels::default(), &Limits::default()),
+            "  ↳ 📄 Serde derive (85 B)\n    # Derive\n    Line one\n    Line two\n    x\n    x\n    … +3 lines"
+        );
+
+        let search = r#"Web search results for query: "serde derive"
+
+Links: [{"title":"Derive | Serde","url":"https://serde.rs/derive.html"},{"title":"serde_derive - Rust","url":"https://docs.rs/serde_derive"}]"#;
+        assert_eq!(
+            format_web_result(search, &Labels::default(), &Limits::default()),
+            "  ↳ 📄 Web search results for query: \"serde derive\" (187 B)\n    • Derive | Serde\n    • serde_derive - Rust"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(12_345), "12.3 KB");
+        assert_eq!(format_bytes(4_200_000), "4.2 MB");
+    }
+
+    #[test]
+    fn test_format_tokens() {
+        assert_eq!(format_tokens(950), "950");
+        assert_eq!(format_tokens(1_234), "1.2k");
+        assert_eq!(format_tokens(2_000), "2k");
+        assert_eq!(format_tokens(15_420), "15k");
+        assert_eq!(format_tokens(2_300_000), "2.3M");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(12_400), "12.4s");
+        assert_eq!(format_duration(185_000), "3m 05s");
+    }
+
+    #[test]
+    fn test_configured_tool() {
+        let config = Config::parse(
+            r#"
+            [tools.Read]
+            emoji = "👀"
+
+            [tools.deploy]
+            emoji = "🚀"
+            label = "Deploy"
+            fields = ["env", "replicas", "missing"]
+            max_len = 10
+            "#,
+        )
+        .unwrap();
+        let limits = Limits::default();
+
+        let input = serde_json::json!({"file_path": "/a.rs"});
+        assert_eq!(format_tool("Read", &input, &config, &limits), "👀 Read: /a.rs");
+        assert_eq!(format_tool("Write", &input, &config, &limits), "📝 Write: /a.rs");
+
+        let input = serde_json::json!({"env": "production", "replicas": 3});
+        assert_eq!(format_tool("deploy", &input, &config, &limits), "🚀 Deploy: product...");
+    }
+
+    #[test]
+    fn test_templated_tool() {
+        let config = Config::parse(
+            r#"
+            [tools.Read]
+            emoji = "👀"
+
+            [templates]
+            Read = "📖 {input.file_path} ({input.limit|default:all} lines)"
+            "#,
+        )
+        .unwrap();
+        let input = serde_json::json!({"file_path": "/a.rs"});
+        assert_eq!(format_tool("Read", &input, &config, &Limits::default()), "📖 /a.rs (all lines)");
+    }
+
+    #[test]
+    fn test_short_model() {
+        assert_eq!(short_model("claude-sonnet-4-20250514"), "sonnet-4");
+        assert_eq!(short_model("claude-3-5-haiku-20241022"), "3-5-haiku");
+        assert_eq!(short_model("claude-opus-4-1"), "opus-4-1");
+        assert_eq!(short_model("gpt-x"), "gpt-x");
+    }
+
+    #[test]
+    fn test_format_init() {
+        let msg: StreamMessage = serde_json::from_str(r#"{"type": "system", "subtype": "init", "model": "claude-sonnet-4-20250514", "cwd": "/work/app", "session_id": "abc12345-6789"}"#).unwrap();
+        assert_eq!(format_init(&msg, &Labels::default()), "🚀 claude (sonnet-4) in /work/app [session abc12345]");
+
+        let msg: StreamMessage = serde_json::from_str(r#"{"type": "system", "subtype": "init"}"#).unwrap();
+        assert_eq!(format_init(&msg, &Labels::default()), "🚀 claude");
+    }
+
+    #[test]
+    fn test_format_resumed() {
+        let labels = Labels::default();
+        assert_eq!(format_resumed("abc12345-6789", 14, &labels), "↩️ Resumed session abc12345 (previously 14 turns)");
+        assert_eq!(format_resumed("abc12345-6789", 0, &labels), "↩️ Resumed session abc12345");
+        let result: StreamMessage = serde_json::from_str(r#"{"type": "result", "num_turns": 3, "duration_ms": 1000}"#).unwrap();
+        assert_eq!(format_stats(&result, 14, &labels).unwrap(), "3 turns, 17 in session, 1.0s");
+        assert_eq!(format_stats(&result, 0, &labels).unwrap(), "3 turns, 1.0s");
+    }
+
+    #[test]
+    fn test_todos() {
+        let input = serde_json::json!({"todos": [
+            {"content": "Write parser", "status": "completed"},
+            {"content": "Run the tests", "status": "in_progress"},
+            {"content": "Update docs", "status": "pending"},
+        ]});
+        assert_eq!(
+            format_tool_use("TodoWrite", &input, &Labels::default(), &Limits::default()),
+            "📋 TodoWrite: 1/3 done, ◐ Run the tests"
+        );
+        assert_eq!(
+            format_todos(&input).unwrap(),
+            "    ☑ Write parser\n    ◐ Run the tests\n    ☐ Update docs"
+        );
+        assert_eq!(format_todos(&serde_json::json!({"todos": []})), None);
+    }
+
+    #[test]
+    fn test_multiedit() {
+        let input = serde_json::json!({"file_path": "/src/a.rs", "edits": [
+            {"old_string": "a", "new_string": "b"},
+            {"old_string": "c", "new_string": "d"},
+        ]});
+        assert_eq!(format_tool_use("MultiEdit", &input, &Labels::default(), &Limits::default()), "✏️  MultiEdit: /src/a.rs (2 edits)");
+        let input = serde_json::json!({"file_path": "/src/a.rs", "edits": [{"old_string": "a", "new_string": "b"}]});
+        assert_eq!(format_tool_use("MultiEdit", &input, &Labels::default(), &Limits::default()), "✏️  MultiEdit: /src/a.rs (1 edit)");
+    }
+
+    #[test]
+    fn test_ls_tool() {
+        let input = serde_json::json!({"path": "/repo/src", "ignore": ["target"]});
+        assert_eq!(format_tool_use("LS", &input, &Labels::default(), &Limits::default()), "📂 LS: /repo/src");
+        assert_eq!(format_tool_use("LS", &serde_json::json!({}), &Labels::default(), &Limits::default()), "📂 LS: ?");
+    }
+
+    #[test]
+    fn test_agent_tools() {
+        let limits = Limits::default();
+        let input = serde_json::json!({
+            "description": "Review the parser",
+            "prompt": "\nLook over src/parser.rs for unhandled edge cases in nested groups and report back",
+            "subagent_type": "code-reviewer",
+        });
+        assert_eq!(
+            format_tool_use("Agent", &input, &Labels::default(), &limits),
+            "🤖 Agent (code-reviewer): Review the parser — Look over src/parser.rs for unhandled..."
+        );
+        let input = serde_json::json!({"prompt": "Find every caller of parse_group"});
+        assert_eq!(format_tool_use("dispatch_agent", &input, &Labels::default(), &limits), "🤖 dispatch_agent: Find every caller of parse_group");
+        assert_eq!(format_tool_use("Agent", &serde_json::json!({}), &Labels::default(), &limits), "🤖 Agent");
+        assert!(is_agent_tool("Task") && is_agent_tool("Agent") && !is_agent_tool("Bash"));
+    }
+
+    #[test]
+    fn test_web_tools() {
+        let limits = Limits::default();
+        let input = serde_json::json!({"url": "https://docs.rs/serde", "prompt": "Summarize the derive attributes and list every container attribute"});
+        assert_eq!(
+            format_tool_use("WebFetch", &input, &Labels::default(), &limits),
+            "🌐 WebFetch: https://docs.rs/serde — Summarize the derive attributes and l..."
+        );
+        let input = serde_json::json!({"url": "https://example.com"});
+        assert_eq!(format_tool_use("WebFetch", &input, &Labels::default(), &limits), "🌐 WebFetch: https://example.com");
+
+        let input = serde_json::json!({"query": "tokio select"});
+        assert_eq!(format_tool_use("WebSearch", &input, &Labels::default(), &limits), "🔎 WebSearch: tokio select");
+        let input = serde_json::json!({"query": "tokio select", "allowed_domains": ["docs.rs", "github.com"]});
+        assert_eq!(
+            format_tool_use("WebSearch", &input, &Labels::default(), &limits),
+            "🔎 WebSearch: tokio select (only docs.rs, github.com)"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! `--output gha`: GitHub Actions workflow commands, which fold each tool
+//! call into a collapsible group in the job log and raise failures as
+//! annotations on the run.
+
+use crate::style::strip_ansi;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+
+/// Inserted before a `:` that starts a line of the stream's own text, so
+/// the runner doesn't take a model's reply or a tool's output for a
+/// command such as `::add-mask::` or `::stop-commands::`. It isn't
+/// whitespace, so the runner doesn't trim it, and it doesn't show.
+const DEFUSE: char = '\u{200B}';
+
+/// Marks the formatter's own commands until [`sanitize`] writes them out.
+/// It is random for each run, so text in the stream can't pass for one.
+fn marker() -> &'static str {
+    static MARKER: OnceLock<String> = OnceLock::new();
+    MARKER.get_or_init(|| format!("\u{1}{:016x}\u{1}", RandomState::new().build_hasher().finish()))
+}
+
+/// A workflow command line such as `::error title=Bash failed::exit 1`,
+/// marked as the formatter's own for [`sanitize`].
+pub fn command(name: &str, properties: &[(&str, &str)], message: &str) -> String {
+    let properties: Vec<String> =
+        properties.iter().map(|(key, value)| format!("{}={}", key, escape_property(value))).collect();
+    if properties.is_empty() {
+        format!("{}::{}::{}", marker(), name, escape_data(message))
+    } else {
+        format!("{}::{} {}::{}", marker(), name, properties.join(","), escape_data(message))
+    }
+}
+
+/// `lines` folded into a group titled with the first line, or left as they
+/// are when there's nothing under it to fold.
+pub fn group(lines: Vec<String>) -> Vec<String> {
+    let text = lines.join("\n");
+    match text.split_once('\n') {
+        Some((title, body)) => vec![command("group", &[], &strip_ansi(title)), body.to_string(), command("endgroup", &[], "")],
+        None if text.is_empty() => Vec::new(),
+        None => vec![text],
+    }
+}
+
+/// Whether `line` is one of the formatter's own workflow commands.
+pub fn is_command(line: &str) -> bool {
+    line.starts_with(marker())
+}
+
+/// `output` as it is written to the job log: the formatter's own commands
+/// as they are, and every other line that the runner could read as a
+/// command defused. Each piece of output is taken to start a line, so a
+/// command split across streamed deltas is caught too.
+pub fn sanitize(output: &str) -> String {
+    output
+        .split('\n')
+        .map(|line| match line.strip_prefix(marker()) {
+            Some(command) => command.to_string(),
+            None => defuse(line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `line` with [`DEFUSE`] before a `:` it starts with, after any
+/// whitespace and colors.
+fn defuse(line: &str) -> String {
+    let mut rest = line;
+    loop {
+        let trimmed = rest.trim_start();
+        match trimmed.strip_prefix("\x1b[") {
+            Some(escape) => rest = escape.find(|c: char| c.is_ascii_alphabetic()).map_or("", |end| &escape[end + 1..]),
+            None => {
+                rest = trimmed;
+                break;
+            }
+        }
+    }
+    if !rest.starts_with(':') {
+        return line.to_string();
+    }
+    let at = line.len() - rest.len();
+    format!("{}{}{}", &line[..at], DEFUSE, rest)
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(sanitize(&command("endgroup", &[], "")), "::endgroup::");
+        assert_eq!(
+            sanitize(&command("error", &[("title", "Bash failed")], "exit 1\n100% broken")),
+            "::error title=Bash failed::exit 1%0A100%25 broken"
+        );
+        assert_eq!(sanitize(&command("warning", &[("title", "a: b, c")], "x")), "::warning title=a%3A b%2C c::x");
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let own = command("group", &[], "💻 Bash: ls");
+        let text = format!("{}\n::add-mask::secret\n  \x1b[2m::stop-commands::x\nsee ::this::\n{}", own, "\u{1}0000000000000000\u{1}::error::forged");
+        assert_eq!(
+            sanitize(&text),
+            "::group::💻 Bash: ls\n\u{200B}::add-mask::secret\n  \x1b[2m\u{200B}::stop-commands::x\nsee ::this::\n\u{1}0000000000000000\u{1}::error::forged"
+        );
+        // The second half of a command streamed in two deltas.
+        assert_eq!(sanitize(":set-output name=x::1"), "\u{200B}:set-output name=x::1");
+    }
+
+    #[test]
+    fn test_group() {
+        assert_eq!(group(vec!["💻 Bash: ls".to_string()]), ["💻 Bash: ls"]);
+        let grouped: Vec<String> = group(vec!["💻 Bash: ls".to_string(), "  ↳ a.rs\n  ↳ b.rs".to_string()]).iter().map(|s| sanitize(s)).collect();
+        assert_eq!(grouped, ["::group::💻 Bash: ls", "  ↳ a.rs\n  ↳ b.rs", "::endgroup::"]);
+        assert_eq!(group(Vec::new()), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,79 @@
+//! Context tracking for `--grep`, which shows only the events matching a
+//! pattern plus a number of events around each match.
+
+use std::collections::VecDeque;
+
+/// Which events to show, given whether each one matched.
+#[derive(Default)]
+pub struct GrepContext {
+    /// Events after the last shown one that may become leading context.
+    before: VecDeque<String>,
+    /// How many more events to show as trailing context.
+    after: usize,
+    printed: bool,
+    /// Whether events were dropped since the last one shown.
+    skipped: bool,
+}
+
+impl GrepContext {
+    /// Returns what to print for `output`: nothing, the event itself, or the
+    /// event preceded by its leading context. Like grep, non-adjacent groups
+    /// of events are separated by `separator`, if there is one.
+    pub fn filter(&mut self, output: String, matched: bool, context: usize, separator: Option<&str>) -> Option<String> {
+        if !matched && self.after == 0 {
+            if self.before.len() == context {
+                self.skipped |= self.before.pop_front().is_some() || context == 0;
+            }
+            if context > 0 {
+                self.before.push_back(output);
+            }
+            return None;
+        }
+
+        let mut lines = Vec::new();
+        if let Some(separator) = separator.filter(|_| self.printed && self.skipped) {
+            lines.push(separator.to_string());
+        }
+        lines.extend(self.before.drain(..));
+        lines.push(output);
+        self.printed = true;
+        self.skipped = false;
+        self.after = if matched { context } else { self.after - 1 };
+        Some(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(events: &[(&str, bool)], context: usize) -> Vec<String> {
+        let mut grep = GrepContext::default();
+        events
+            .iter()
+            .filter_map(|(output, matched)| grep.filter(output.to_string(), *matched, context, Some("--")))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_context() {
+        let events = [("a", true), ("b", false), ("c", true), ("d", true)];
+        assert_eq!(run(&events, 0), vec!["a", "--\nc", "d"]);
+    }
+
+    #[test]
+    fn test_context() {
+        let events = [
+            ("a", false),
+            ("b", false),
+            ("c", true),
+            ("d", false),
+            ("e", false),
+            ("f", false),
+            ("g", false),
+            ("h", true),
+        ];
+        assert_eq!(run(&events, 1), vec!["b\nc", "d", "--\ng\nh"]);
+        assert_eq!(run(&events, 2), vec!["a\nb\nc", "d", "e", "f\ng\nh"]);
+    }
+}
@@ -0,0 +1,285 @@
+//! A small lexical syntax highlighter for code shown in the terminal.
+//!
+//! This only distinguishes comments, strings, numbers and keywords, which is
+//! enough to make code readable without pulling in a full grammar engine.
+
+use crate::style::{Style, Theme};
+
+struct Language {
+    keywords: &'static [&'static str],
+    line_comment: &'static str,
+    /// Whether `'a` is a lifetime or label rather than the start of a
+    /// string.
+    lifetimes: bool,
+}
+
+const RUST: Language = Language {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: "//",
+    lifetimes: true,
+};
+
+const PYTHON: Language = Language {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+        "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+        "try", "while", "with", "yield",
+    ],
+    line_comment: "#",
+    lifetimes: false,
+};
+
+const JAVASCRIPT: Language = Language {
+    keywords: &[
+        "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+        "delete", "do", "else", "export", "extends", "false", "finally", "for", "from",
+        "function", "if", "import", "in", "instanceof", "interface", "let", "new", "null",
+        "return", "static", "switch", "this", "throw", "true", "try", "type", "typeof",
+        "undefined", "var", "void", "while", "yield",
+    ],
+    line_comment: "//",
+    lifetimes: false,
+};
+
+const GO: Language = Language {
+    keywords: &[
+        "break", "case", "chan", "const", "continue", "default", "defer", "else", "false",
+        "for", "func", "go", "if", "import", "interface", "map", "nil", "package", "range",
+        "return", "select", "struct", "switch", "true", "type", "var",
+    ],
+    line_comment: "//",
+    lifetimes: false,
+};
+
+const SHELL: Language = Language {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "until", "while",
+    ],
+    line_comment: "#",
+    lifetimes: false,
+};
+
+const C_LIKE: Language = Language {
+    keywords: &[
+        "break", "case", "char", "class", "const", "continue", "default", "do", "double",
+        "else", "enum", "false", "float", "for", "if", "int", "long", "new", "null", "private",
+        "protected", "public", "return", "static", "struct", "switch", "this", "true", "void",
+        "while",
+    ],
+    line_comment: "//",
+    lifetimes: false,
+};
+
+const PLAIN: Language = Language {
+    keywords: &[],
+    line_comment: "",
+    lifetimes: false,
+};
+
+fn language(name: &str) -> &'static Language {
+    match name.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => &RUST,
+        "python" | "py" => &PYTHON,
+        "javascript" | "js" | "jsx" | "mjs" | "typescript" | "ts" | "tsx" => &JAVASCRIPT,
+        "go" => &GO,
+        "sh" | "bash" | "zsh" | "shell" | "console" => &SHELL,
+        "c" | "h" | "cpp" | "cc" | "hpp" | "java" | "cs" | "csharp" | "kotlin" | "kt"
+        | "swift" => &C_LIKE,
+        "toml" | "yaml" | "yml" | "ruby" | "rb" | "dockerfile" => &Language {
+            keywords: &[],
+            line_comment: "#",
+            lifetimes: false,
+        },
+        _ => &PLAIN,
+    }
+}
+
+/// The lexical class of a piece of code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+/// Highlights `code` written in `lang` (a fence info string or file
+/// extension). Unknown languages still get strings and numbers colored.
+pub fn highlight(code: &str, lang: &str, theme: &Theme) -> String {
+    code.lines()
+        .map(|line| highlight_line(line, lang, Style::default(), theme))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Highlights one line of `lang` code, painting the text between tokens
+/// with `plain`, such as the color of added lines in a diff.
+pub fn highlight_line(line: &str, lang: &str, plain: Style, theme: &Theme) -> String {
+    tokenize(line, lang)
+        .into_iter()
+        .map(|(token, text)| match token {
+            Token::Plain => plain.paint(text),
+            Token::Keyword => theme.keyword.paint(text),
+            Token::String => theme.string.paint(text),
+            Token::Comment => theme.comment.paint(text),
+            Token::Number => theme.number.paint(text),
+        })
+        .collect()
+}
+
+/// The language of the file at `path` for [`highlight`]: its extension,
+/// or its name if it has none, such as `Dockerfile`.
+pub fn path_lang(path: &str) -> &str {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    name.rsplit_once('.').map_or(name, |(_, ext)| ext)
+}
+
+/// Splits one line of `lang` code into tokens. Adjacent plain characters
+/// are merged into a single token.
+pub fn tokenize<'a>(line: &'a str, lang: &str) -> Vec<(Token, &'a str)> {
+    let language = language(lang);
+    let mut tokens: Vec<(Token, &str)> = Vec::new();
+    let mut plain_start = None;
+    let mut pos = 0;
+
+    while let Some(c) = line[pos..].chars().next() {
+        let rest = &line[pos..];
+        let label = if language.lifetimes { lifetime(rest) } else { None };
+        let (token, len) = if !language.line_comment.is_empty() && rest.starts_with(language.line_comment) {
+            (Token::Comment, rest.len())
+        } else if let Some(len) = label {
+            (Token::Plain, len)
+        } else if c == '"' || c == '\'' || c == '`' {
+            let end = rest[1..]
+                .find(c)
+                .map(|i| i + 2)
+                .unwrap_or(rest.len());
+            (Token::String, end)
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .unwrap_or(rest.len());
+            (Token::Number, end)
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            if language.keywords.contains(&&rest[..end]) {
+                (Token::Keyword, end)
+            } else {
+                (Token::Plain, end)
+            }
+        } else {
+            (Token::Plain, c.len_utf8())
+        };
+
+        if token == Token::Plain {
+            plain_start.get_or_insert(pos);
+        } else {
+            if let Some(start) = plain_start.take() {
+                tokens.push((Token::Plain, &line[start..pos]));
+            }
+            tokens.push((token, &rest[..len]));
+        }
+        pos += len;
+    }
+    if let Some(start) = plain_start {
+        tokens.push((Token::Plain, &line[start..]));
+    }
+    tokens
+}
+
+/// The length of the lifetime or label at the start of `rest`, such as
+/// `'a` or `'static`, as opposed to a char literal like `'a'`.
+fn lifetime(rest: &str) -> Option<usize> {
+    let name = rest.strip_prefix('\'')?;
+    if !name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_') {
+        return None;
+    }
+    let end = name.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(name.len());
+    let char_literal = name[end..].starts_with('\'') && name[..end].chars().count() == 1;
+    (!char_literal).then_some(1 + end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::ThemeName;
+
+    #[test]
+    fn test_plain_theme_is_identity() {
+        let code = "fn main() {\n    let x = \"hi\"; // greet\n}";
+        assert_eq!(highlight(code, "rust", &Theme::default()), code);
+    }
+
+    #[test]
+    fn test_highlight_tokens() {
+        let theme = Theme::new(ThemeName::Dark);
+        let out = highlight("let x = 42; // answer", "rust", &theme);
+        assert!(out.starts_with(&theme.keyword.paint("let")));
+        assert!(out.contains(&theme.number.paint("42")));
+        assert!(out.ends_with(&theme.comment.paint("// answer")));
+
+        let out = highlight("print('a # b')", "python", &theme);
+        assert!(out.contains(&theme.string.paint("'a # b'")));
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("if x == 1 # check", "python"),
+            vec![
+                (Token::Keyword, "if"),
+                (Token::Plain, " x == "),
+                (Token::Number, "1"),
+                (Token::Plain, " "),
+                (Token::Comment, "# check"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lifetimes() {
+        assert_eq!(
+            tokenize("fn f<'a>(x: &'a str) -> char { 'x' }", "rust"),
+            vec![
+                (Token::Keyword, "fn"),
+                (Token::Plain, " f<'a>(x: &'a str) -> char { "),
+                (Token::String, "'x'"),
+                (Token::Plain, " }"),
+            ]
+        );
+        // Elsewhere a quote still opens a string.
+        assert_eq!(tokenize("'a b'", "python"), vec![(Token::String, "'a b'")]);
+    }
+
+    #[test]
+    fn test_highlight_line() {
+        let theme = Theme::new(ThemeName::Dark);
+        assert_eq!(
+            highlight_line("x = 1", "py", theme.added, &theme),
+            format!("{}{}", theme.added.paint("x = "), theme.number.paint("1"))
+        );
+    }
+
+    #[test]
+    fn test_path_lang() {
+        assert_eq!(path_lang("/repo/src/main.rs"), "rs");
+        assert_eq!(path_lang("/repo/v1.2/Dockerfile"), "Dockerfile");
+        assert_eq!(path_lang("app.test.ts"), "ts");
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let theme = Theme::new(ThemeName::Dark);
+        assert_eq!(highlight("\"open", "rust", &theme), theme.string.paint("\"open"));
+    }
+}
@@ -0,0 +1,151 @@
+//! `--session-file`: past sessions, read from the JSONL transcripts Claude
+//! Code keeps under `~/.claude/projects/`.
+//!
+//! Each record of a transcript wraps a user or assistant message with its
+//! `uuid`, `timestamp`, `sessionId` and `cwd`. Records are translated into
+//! the stream-json lines a live session would have printed, so the rest of
+//! the formatter doesn't need to know where they came from.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// One record of a session transcript.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    #[serde(rename = "type")]
+    record_type: String,
+    message: Option<Value>,
+    session_id: Option<String>,
+    cwd: Option<String>,
+    /// Set on the caveats and command output Claude Code adds around slash
+    /// commands, which were never part of the conversation.
+    #[serde(default)]
+    is_meta: bool,
+}
+
+/// Translates the lines of a session transcript into stream-json.
+#[derive(Default)]
+pub struct SessionFile {
+    /// Messages held back until the first assistant message names the
+    /// model for the session header.
+    pending: Vec<String>,
+    /// The session and working directory for the header, from the first
+    /// record.
+    session: Option<(Option<String>, Option<String>)>,
+    started: bool,
+}
+
+impl SessionFile {
+    /// The stream-json lines for one line of a transcript. Lines that are
+    /// already stream-json, or not JSON at all, are returned unchanged.
+    pub fn translate(&mut self, line: &str) -> Vec<String> {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) => return vec![line.to_string()],
+        };
+        let is_record = value.get("sessionId").is_some() || value.get("leafUuid").is_some();
+        if !is_record || value.get("session_id").is_some() {
+            return vec![line.to_string()];
+        }
+        let Ok(record) = serde_json::from_value::<Record>(value) else {
+            return vec![line.to_string()];
+        };
+        // Summaries, file snapshots and the like have no stream-json form.
+        if !matches!(record.record_type.as_str(), "user" | "assistant") || record.is_meta {
+            return Vec::new();
+        }
+        let Some(mut message) = record.message else {
+            return Vec::new();
+        };
+        // Prompts are stored as a plain string rather than content blocks.
+        if let Some(text) = message.get("content").and_then(Value::as_str) {
+            message["content"] = json!([{"type": "text", "text": text}]);
+        }
+        self.session.get_or_insert_with(|| (record.session_id.clone(), record.cwd.clone()));
+        let model = message.get("model").and_then(Value::as_str).map(str::to_string);
+        let line = json!({
+            "type": record.record_type,
+            "message": message,
+            "session_id": record.session_id,
+        })
+        .to_string();
+
+        if self.started {
+            return vec![line];
+        }
+        self.pending.push(line);
+        match model {
+            Some(model) => self.start(Some(model)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns whatever is still held back at the end of the transcript.
+    pub fn finish(&mut self) -> Vec<String> {
+        if self.started {
+            return Vec::new();
+        }
+        self.start(None)
+    }
+
+    /// The session header, followed by the messages held back for it.
+    fn start(&mut self, model: Option<String>) -> Vec<String> {
+        self.started = true;
+        let Some((session_id, cwd)) = self.session.take() else {
+            return std::mem::take(&mut self.pending);
+        };
+        let init = json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": session_id,
+            "cwd": cwd,
+            "model": model,
+        });
+        let mut lines = vec![init.to_string()];
+        lines.append(&mut self.pending);
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let mut file = SessionFile::default();
+        let summary = r#"{"type": "summary", "summary": "Fix the tests", "leafUuid": "u0"}"#;
+        let prompt = r#"{"type": "user", "uuid": "u1", "parentUuid": null, "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:00:00Z", "message": {"role": "user", "content": "Fix the tests"}}"#;
+        let reply = r#"{"type": "assistant", "uuid": "u2", "parentUuid": "u1", "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:00:03Z", "message": {"model": "claude-sonnet-4-20250514", "role": "assistant", "content": [{"type": "text", "text": "On it."}]}}"#;
+        assert_eq!(file.translate(summary), Vec::<String>::new());
+        assert_eq!(file.translate(prompt), Vec::<String>::new());
+        assert_eq!(
+            file.translate(reply),
+            vec![
+                r#"{"type":"system","subtype":"init","session_id":"abc","cwd":"/repo","model":"claude-sonnet-4-20250514"}"#,
+                r#"{"type":"user","message":{"role":"user","content":[{"type":"text","text":"Fix the tests"}]},"session_id":"abc"}"#,
+                r#"{"type":"assistant","message":{"model":"claude-sonnet-4-20250514","role":"assistant","content":[{"type":"text","text":"On it."}]},"session_id":"abc"}"#,
+            ]
+        );
+        assert_eq!(file.finish(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_stream_json_unchanged() {
+        let mut file = SessionFile::default();
+        let line = r#"{"type": "result", "session_id": "abc", "result": "ok"}"#;
+        assert_eq!(file.translate(line), vec![line.to_string()]);
+        assert_eq!(file.translate("not json"), vec!["not json".to_string()]);
+    }
+
+    #[test]
+    fn test_finish_without_model() {
+        let mut file = SessionFile::default();
+        let prompt = r#"{"type": "user", "uuid": "u1", "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:00:00Z", "message": {"role": "user", "content": "hi"}}"#;
+        assert_eq!(file.translate(prompt), Vec::<String>::new());
+        let lines = file.finish();
+        assert_eq!(lines[0], r#"{"type":"system","subtype":"init","session_id":"abc","cwd":"/repo","model":null}"#);
+        assert_eq!(lines.len(), 2);
+    }
+}
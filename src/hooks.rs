@@ -0,0 +1,158 @@
+//! `--on-event`: user commands run with the event's JSON on stdin when
+//! matching events occur, for automation around agent runs.
+
+use crate::event::{Event, EventKind};
+use crate::watch::wildcard;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// A `SELECTOR=COMMAND` rule. The selector is an event type from
+/// `--output json`, such as `tool_use` or `result`, optionally followed by
+/// `:TOOL` (with `*` and `?` wildcards), or `error` for any failure.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hook {
+    /// The event type; `None` for `error`.
+    kind: Option<EventKind>,
+    tool: Option<String>,
+    pub command: String,
+}
+
+impl FromStr for Hook {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (selector, command) = s.split_once('=').ok_or("expected EVENT=COMMAND")?;
+        if command.trim().is_empty() {
+            return Err("expected EVENT=COMMAND".to_string());
+        }
+        let (kind, tool) = match selector.split_once(':') {
+            Some((kind, tool)) => (kind, Some(tool.to_string())),
+            None => (selector, None),
+        };
+        let kind = match kind {
+            "error" => None,
+            kind => Some(
+                serde_json::from_value(serde_json::Value::String(kind.to_string()))
+                    .map_err(|_| format!("unknown event `{}`", kind))?,
+            ),
+        };
+        Ok(Hook {
+            kind,
+            tool,
+            command: command.to_string(),
+        })
+    }
+}
+
+impl Hook {
+    pub fn matches(&self, event: &Event) -> bool {
+        let kind = match self.kind {
+            Some(kind) => event.kind == kind,
+            None => event.is_error,
+        };
+        let tool = match &self.tool {
+            Some(pattern) => event.tool.as_deref().is_some_and(|tool| wildcard(pattern, tool)),
+            None => true,
+        };
+        kind && tool
+    }
+}
+
+/// A command started by [`run`], until its event has been written to it.
+pub struct Running {
+    written: Receiver<()>,
+}
+
+impl Running {
+    /// Whether the event has been written, or never will be.
+    pub fn is_written(&self) -> bool {
+        !matches!(self.written.try_recv(), Err(TryRecvError::Empty))
+    }
+
+    /// Waits up to `timeout` for the event to be written, so it isn't lost
+    /// when the stream ends first.
+    pub fn finish(self, timeout: Duration) {
+        let _ = self.written.recv_timeout(timeout);
+    }
+}
+
+/// Starts `command` with `sh -c`, writing `event` to its stdin, without
+/// waiting for it to finish. The event type is also passed in `CSF_EVENT`.
+pub fn run(command: &str, event: &Event) -> io::Result<Running> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("CSF_EVENT", serde_json::to_value(event.kind)?.as_str().unwrap_or_default())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    let json = event.to_json();
+    let command = command.to_string();
+    let (tx, written) = mpsc::channel();
+    // The event is written, and the command reaped, in the background: a
+    // large event, such as a Write's whole file, can fill the pipe, and a
+    // hook that doesn't read its stdin, or is slow, mustn't hold up the
+    // stream.
+    thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() {
+            match writeln!(stdin, "{}", json) {
+                // A hook that exits without reading its event is fine.
+                Err(err) if err.kind() != io::ErrorKind::BrokenPipe => {
+                    eprintln!("claude-stream-format: {}: {}", command, err);
+                }
+                _ => {}
+            }
+        }
+        let _ = tx.send(());
+        child.wait()
+    });
+    Ok(Running { written })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: EventKind, tool: Option<&str>, is_error: bool) -> Event {
+        let mut event = Event::new(kind, String::new());
+        event.tool = tool.map(str::to_string);
+        event.is_error = is_error;
+        event
+    }
+
+    #[test]
+    fn test_hook() {
+        let bash: Hook = "tool_use:Bash=./log_bash.sh".parse().unwrap();
+        assert_eq!(bash.command, "./log_bash.sh");
+        assert!(bash.matches(&event(EventKind::ToolUse, Some("Bash"), false)));
+        assert!(!bash.matches(&event(EventKind::ToolUse, Some("Read"), false)));
+        assert!(!bash.matches(&event(EventKind::Result, None, false)));
+
+        let mcp: Hook = "tool_use:mcp__*=log".parse().unwrap();
+        assert!(mcp.matches(&event(EventKind::ToolUse, Some("mcp__github__create_issue"), false)));
+
+        let error: Hook = "error=notify.sh a=b".parse().unwrap();
+        assert_eq!(error.command, "notify.sh a=b");
+        assert!(error.matches(&event(EventKind::ToolResult, None, true)));
+        assert!(error.matches(&event(EventKind::Result, None, true)));
+        assert!(!error.matches(&event(EventKind::Result, None, false)));
+
+        assert!("result=./done.sh".parse::<Hook>().unwrap().matches(&event(EventKind::Result, None, false)));
+        assert!("bogus=x".parse::<Hook>().is_err());
+        assert!("result".parse::<Hook>().is_err());
+    }
+
+    #[test]
+    fn test_run_large_event() {
+        // More than a pipe holds, to a command that never reads it.
+        let mut event = event(EventKind::ToolUse, Some("Write"), false);
+        event.summary = "x".repeat(1 << 20);
+        let running = run("sleep 1", &event).unwrap();
+        assert!(!running.is_written());
+        running.finish(Duration::from_secs(5));
+    }
+}
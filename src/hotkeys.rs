@@ -0,0 +1,144 @@
+//! Keys pressed while the output is on a terminal, read from `/dev/tty` so
+//! they work with the stream piped into stdin: `t` toggles tool results,
+//! `k` thinking, `p` pauses output and `s` prints the summary so far.
+
+use std::io;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    ToolResults,
+    Thinking,
+    Pause,
+    Summary,
+}
+
+impl Key {
+    pub fn of(c: char) -> Option<Key> {
+        match c.to_ascii_lowercase() {
+            't' => Some(Key::ToolResults),
+            'k' => Some(Key::Thinking),
+            'p' => Some(Key::Pause),
+            's' => Some(Key::Summary),
+            _ => None,
+        }
+    }
+}
+
+/// The terminal, taken out of line mode so keys arrive as they are
+/// pressed, without echoing them. It is put back when dropped.
+pub struct Hotkeys {
+    keys: Receiver<Key>,
+    #[cfg(unix)]
+    tty: unix::Tty,
+}
+
+impl Hotkeys {
+    /// Starts reading keys from the terminal.
+    #[cfg(unix)]
+    pub fn open() -> io::Result<Self> {
+        let (tty, keys) = unix::Tty::open()?;
+        Ok(Hotkeys { keys, tty })
+    }
+
+    #[cfg(not(unix))]
+    pub fn open() -> io::Result<Self> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// A key pressed since the last call, if any.
+    pub fn try_key(&self) -> Option<Key> {
+        self.keys.try_recv().ok()
+    }
+
+    /// Waits up to `timeout` for a key.
+    pub fn key_timeout(&self, timeout: Duration) -> Result<Key, RecvTimeoutError> {
+        self.keys.recv_timeout(timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Hotkeys {
+    fn drop(&mut self) {
+        self.tty.restore();
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::Key;
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::mem::MaybeUninit;
+    use std::os::unix::io::AsRawFd;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    pub struct Tty {
+        file: File,
+        saved: libc::termios,
+    }
+
+    impl Tty {
+        pub fn open() -> io::Result<(Self, Receiver<Key>)> {
+            let file = File::open("/dev/tty")?;
+            let mut saved = MaybeUninit::<libc::termios>::uninit();
+            // SAFETY: tcgetattr fills in `saved` when it succeeds.
+            let saved = unsafe {
+                if libc::tcgetattr(file.as_raw_fd(), saved.as_mut_ptr()) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                saved.assume_init()
+            };
+            let mut keys = saved;
+            // Signals are left alone, so Ctrl-C still works; it stops the
+            // formatter cleanly, leaving it to put the terminal back.
+            keys.c_lflag &= !(libc::ICANON | libc::ECHO);
+            keys.c_cc[libc::VMIN] = 1;
+            keys.c_cc[libc::VTIME] = 0;
+            set(&file, &keys)?;
+            let tty = Tty { file, saved };
+
+            let (tx, rx) = mpsc::channel();
+            let mut reader = tty.file.try_clone()?;
+            thread::spawn(move || {
+                let mut byte = [0];
+                while let Ok(1) = reader.read(&mut byte) {
+                    if let Some(key) = Key::of(char::from(byte[0])) {
+                        if tx.send(key).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+            Ok((tty, rx))
+        }
+
+        pub fn restore(&self) {
+            let _ = set(&self.file, &self.saved);
+        }
+    }
+
+    fn set(file: &File, termios: &libc::termios) -> io::Result<()> {
+        // SAFETY: tcsetattr only reads `termios`.
+        if unsafe { libc::tcsetattr(file.as_raw_fd(), libc::TCSANOW, termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(Key::of('t'), Some(Key::ToolResults));
+        assert_eq!(Key::of('K'), Some(Key::Thinking));
+        assert_eq!(Key::of('p'), Some(Key::Pause));
+        assert_eq!(Key::of('s'), Some(Key::Summary));
+        assert_eq!(Key::of('x'), None);
+    }
+}
@@ -0,0 +1,385 @@
+//! A standalone HTML transcript for `--output html`.
+//!
+//! Unlike the text and JSON output, HTML is buffered until the end of the
+//! stream so that each tool's result can be placed inside the collapsible
+//! section of the call that produced it.
+
+use crate::config::Labels;
+use crate::diff::{diff_lines, DiffLine};
+use crate::format::{format_init, format_stats, format_tool, format_tool_input, Limits};
+use crate::formatter::{Kind, Options};
+use crate::highlight::{path_lang, tokenize, Token};
+use crate::message::{ContentBlock, StreamMessage};
+use crate::style::Theme;
+use crate::summary::Summary;
+use std::collections::HashMap;
+
+const STYLE: &str = "
+body { font: 14px/1.5 -apple-system, 'Segoe UI', sans-serif; max-width: 960px; margin: 2em auto; padding: 0 1em; color: #1f2328; }
+header { font-weight: 600; border-bottom: 1px solid #d0d7de; padding-bottom: .5em; margin-bottom: 1em; }
+header .tools { font-weight: normal; color: #656d76; font-size: 12px; }
+pre, code { font: 12px/1.45 ui-monospace, SFMono-Regular, Menlo, monospace; }
+pre { background: #f6f8fa; border-radius: 6px; padding: .75em; overflow-x: auto; }
+.text { white-space: pre-wrap; margin: .75em 0; }
+details.tool { border: 1px solid #d0d7de; border-radius: 6px; margin: .5em 0; }
+details.tool > summary { cursor: pointer; padding: .35em .75em; font-family: ui-monospace, monospace; font-size: 13px; }
+details.tool > :not(summary) { margin: 0 .75em .75em; }
+details.tool.error { border-color: #cf222e; }
+.result-output.error { background: #ffebe9; }
+details.thinking { color: #656d76; font-style: italic; margin: .5em 0; }
+details.thinking > summary { cursor: pointer; }
+.outcome { padding: .5em .75em; border-radius: 6px; margin: 1em 0; background: #dafbe1; }
+.outcome.error { background: #ffebe9; }
+.diff { padding: 0; }
+.diff span { display: block; padding: 0 .75em; }
+.diff .added { background: #dafbe1; }
+.diff .removed { background: #ffebe9; }
+.diff .hunk { color: #656d76; }
+.kw { color: #cf222e; }
+.str { color: #0a3069; }
+.com { color: #6e7781; font-style: italic; }
+.num { color: #0550ae; }
+";
+
+enum Entry {
+    Header(String),
+    Text(usize, String),
+    Thinking(usize, String),
+    Tool {
+        depth: usize,
+        line: String,
+        name: String,
+        input: serde_json::Value,
+        result: Option<(String, bool)>,
+    },
+    Outcome(String, bool),
+}
+
+/// The messages of a stream, collected for rendering as one HTML page.
+#[derive(Default)]
+pub struct Transcript {
+    entries: Vec<Entry>,
+    /// Index in `entries` of the call for each tool_use id.
+    tools: HashMap<String, usize>,
+}
+
+impl Transcript {
+    /// Adds the parts of `msg` that `options` selects, at sub-agent `depth`.
+    pub fn record(&mut self, msg: &StreamMessage, depth: usize, options: &Options) {
+        match msg.msg_type.as_str() {
+            "system"
+                if msg.subtype.as_deref() == Some("init") && !options.no_header && options.shows(Kind::Header) =>
+            {
+                let mut header = escape(&format_init(msg, &options.config.labels));
+                if let Some(tools) = msg.tools.as_ref().filter(|_| options.verbose) {
+                    header.push_str(&format!(
+                        "<div class=\"tools\">tools: {}</div>",
+                        escape(&tools.join(", "))
+                    ));
+                }
+                self.entries.push(Entry::Header(header));
+            }
+            "assistant" => {
+                let Some(message) = &msg.message else {
+                    return;
+                };
+                for block in &message.content {
+                    match block {
+                        ContentBlock::Text { text } if options.shows(Kind::Text) && !text.trim().is_empty() => {
+                            self.entries.push(Entry::Text(depth, text.clone()));
+                        }
+                        ContentBlock::Thinking { thinking }
+                            if options.show_thinking && options.shows(Kind::Text) && !thinking.trim().is_empty() =>
+                        {
+                            self.entries.push(Entry::Thinking(depth, thinking.clone()));
+                        }
+                        ContentBlock::ToolUse { id, name, input } if options.shows(Kind::Tools) => {
+                            if !id.is_empty() {
+                                self.tools.insert(id.clone(), self.entries.len());
+                            }
+                            self.entries.push(Entry::Tool {
+                                depth,
+                                line: format_tool(name, input, &options.config, &Limits::default().with_config(&options.config.truncate)),
+                                name: name.clone(),
+                                input: input.clone(),
+                                result: None,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "user" => {
+                let Some(message) = &msg.message else {
+                    return;
+                };
+                for block in &message.content {
+                    let ContentBlock::ToolResult {
+                        tool_use_id,
+                        content,
+                        is_error,
+                    } = block
+                    else {
+                        continue;
+                    };
+                    let Some(&index) = self.tools.get(tool_use_id) else {
+                        continue;
+                    };
+                    if let Entry::Tool { result, .. } = &mut self.entries[index] {
+                        let text = content.as_ref().map(|c| c.text()).unwrap_or_default();
+                        *result = Some((text, *is_error));
+                    }
+                }
+            }
+            "result" if options.shows(Kind::Result) => {
+                let Some(result) = msg.result_text(&options.config.labels) else {
+                    return;
+                };
+                let labels = &options.config.labels;
+                let label = if msg.is_error_result() {
+                    format!("❌ {}", labels.error)
+                } else {
+                    format!("✅ {}", labels.done)
+                };
+                let line = match format_stats(msg, 0, &options.config.labels).filter(|_| !options.no_stats) {
+                    Some(stats) => format!("{} ({}): {}", label, stats, result),
+                    None => format!("{}: {}", label, result),
+                };
+                self.entries.push(Entry::Outcome(line, msg.is_error_result()));
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders the complete HTML document, with `summary` at the end if given.
+    pub fn render(&self, summary: Option<&Summary>, labels: &Labels) -> String {
+        let mut body = String::new();
+        for entry in &self.entries {
+            body.push_str(&render_entry(entry, labels));
+            body.push('\n');
+        }
+        if let Some(summary) = summary {
+            body.push_str(&format!("<pre class=\"summary\">{}</pre>\n", escape(&summary.render(&Theme::default(), labels))));
+        }
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Claude session transcript</title>\n<style>{}</style>\n</head>\n\
+             <body>\n<main>\n{}</main>\n</body>\n</html>",
+            STYLE, body
+        )
+    }
+}
+
+fn render_entry(entry: &Entry, labels: &Labels) -> String {
+    match entry {
+        Entry::Header(header) => format!("<header>{}</header>", header),
+        Entry::Text(depth, text) => indented(*depth, render_text(text)),
+        Entry::Thinking(depth, thinking) => indented(
+            *depth,
+            format!(
+                "<details class=\"thinking\"><summary>🧠 {}</summary><div class=\"text\">{}</div></details>",
+                escape(&labels.thinking),
+                escape(thinking.trim())
+            ),
+        ),
+        Entry::Tool {
+            depth,
+            line,
+            name,
+            input,
+            result,
+        } => {
+            let failed = matches!(result, Some((_, true)));
+            let mut html = format!(
+                "<details class=\"tool{}\"><summary>{}</summary>",
+                if failed { " error" } else { "" },
+                escape(line)
+            );
+            html.push_str(&render_tool_input(name, input));
+            if let Some((output, is_error)) = result {
+                let class = if *is_error { "result-output error" } else { "result-output" };
+                html.push_str(&format!("<pre class=\"{}\">{}</pre>", class, escape(output)));
+            }
+            html.push_str("</details>");
+            indented(*depth, html)
+        }
+        Entry::Outcome(line, is_error) => format!(
+            "<div class=\"outcome{}\">{}</div>",
+            if *is_error { " error" } else { "" },
+            escape(line)
+        ),
+    }
+}
+
+/// Wraps sub-agent output so it sits further right than its parent's.
+fn indented(depth: usize, html: String) -> String {
+    if depth == 0 {
+        html
+    } else {
+        format!("<div style=\"margin-left: {}em\">{}</div>", depth * 2, html)
+    }
+}
+
+/// Assistant text, with fenced code blocks highlighted.
+fn render_text(text: &str) -> String {
+    let mut html = String::new();
+    let mut prose = Vec::new();
+    let mut fence: Option<(&str, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some((lang, code)) = &mut fence {
+            if trimmed.starts_with("```") {
+                html.push_str(&code_block(&code.join("\n"), lang));
+                fence = None;
+            } else {
+                code.push(line);
+            }
+            continue;
+        }
+        if let Some(lang) = trimmed.strip_prefix("```") {
+            push_prose(&mut html, &mut prose);
+            fence = Some((lang.trim(), Vec::new()));
+            continue;
+        }
+        prose.push(line);
+    }
+    if let Some((lang, code)) = fence {
+        html.push_str(&code_block(&code.join("\n"), lang));
+    }
+    push_prose(&mut html, &mut prose);
+    html
+}
+
+fn push_prose(html: &mut String, prose: &mut Vec<&str>) {
+    let text = prose.join("\n");
+    if !text.trim().is_empty() {
+        html.push_str(&format!("<div class=\"text\">{}</div>", escape(text.trim_matches('\n'))));
+    }
+    prose.clear();
+}
+
+/// The detail shown when a tool's section is expanded.
+fn render_tool_input(name: &str, input: &serde_json::Value) -> String {
+    let str_field = |key: &str| input.get(key).and_then(|v| v.as_str());
+    match name {
+        "Edit" => render_diff(str_field("old_string").unwrap_or(""), str_field("new_string").unwrap_or("")),
+        "MultiEdit" => {
+            let edits = input.get("edits").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            edits
+                .iter()
+                .enumerate()
+                .map(|(i, edit)| {
+                    let old = edit.get("old_string").and_then(|v| v.as_str()).unwrap_or("");
+                    let new = edit.get("new_string").and_then(|v| v.as_str()).unwrap_or("");
+                    format!(
+                        "<pre class=\"diff\"><span class=\"hunk\">@@ {}/{} @@</span>{}</pre>",
+                        i + 1,
+                        edits.len(),
+                        diff_spans(old, new)
+                    )
+                })
+                .collect()
+        }
+        "Write" => {
+            let path = str_field("file_path").unwrap_or("");
+            code_block(str_field("content").unwrap_or(""), path_lang(path))
+        }
+        "Bash" => code_block(str_field("command").unwrap_or(""), "sh"),
+        _ => code_block(&format_tool_input(input), ""),
+    }
+}
+
+fn render_diff(old: &str, new: &str) -> String {
+    format!("<pre class=\"diff\">{}</pre>", diff_spans(old, new))
+}
+
+fn diff_spans(old: &str, new: &str) -> String {
+    diff_lines(old, new)
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Same(l) => format!("<span>  {}</span>", escape(l)),
+            DiffLine::Removed(l) => format!("<span class=\"removed\">- {}</span>", escape(l)),
+            DiffLine::Added(l) => format!("<span class=\"added\">+ {}</span>", escape(l)),
+        })
+        .collect()
+}
+
+/// A `<pre>` block of `code` highlighted as `lang`.
+fn code_block(code: &str, lang: &str) -> String {
+    let lines: Vec<String> = code
+        .lines()
+        .map(|line| {
+            tokenize(line, lang)
+                .into_iter()
+                .map(|(token, text)| match token {
+                    Token::Plain => escape(text),
+                    Token::Keyword => format!("<span class=\"kw\">{}</span>", escape(text)),
+                    Token::String => format!("<span class=\"str\">{}</span>", escape(text)),
+                    Token::Comment => format!("<span class=\"com\">{}</span>", escape(text)),
+                    Token::Number => format!("<span class=\"num\">{}</span>", escape(text)),
+                })
+                .collect()
+        })
+        .collect();
+    format!("<pre><code>{}</code></pre>", lines.join("\n"))
+}
+
+/// Escapes `s` for use in HTML text and attribute values.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(lines: &[&str]) -> String {
+        let mut transcript = Transcript::default();
+        for line in lines {
+            let msg: StreamMessage = serde_json::from_str(line).unwrap();
+            transcript.record(&msg, 0, &Options::default());
+        }
+        transcript.render(None, &Labels::default())
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a href=\"x\">&'"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;");
+    }
+
+    #[test]
+    fn test_tool_result_inside_its_call() {
+        let html = transcript(&[
+            r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls <dir>"}}]}}"#,
+            r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "a.txt", "is_error": true}]}}"#,
+        ]);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(
+            "<details class=\"tool error\"><summary>💻 Bash: ls &lt;dir&gt;</summary>\
+             <pre><code>ls &lt;dir&gt;</code></pre><pre class=\"result-output error\">a.txt</pre></details>"
+        ));
+    }
+
+    #[test]
+    fn test_edit_diff_and_text() {
+        let html = transcript(&[
+            r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Fixing:\n```rust\nlet x = 1;\n```"}, {"type": "tool_use", "id": "t1", "name": "Edit", "input": {"file_path": "a.rs", "old_string": "a", "new_string": "b"}}]}}"#,
+            r#"{"type": "result", "result": "Done"}"#,
+        ]);
+        assert!(html.contains("<div class=\"text\">Fixing:</div><pre><code><span class=\"kw\">let</span> x = <span class=\"num\">1</span>;</code></pre>"));
+        assert!(html.contains("<pre class=\"diff\"><span class=\"removed\">- a</span><span class=\"added\">+ b</span></pre>"));
+        assert!(html.contains("<div class=\"outcome\">✅ Done: Done</div>"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Images in prompts and tool results, such as a screenshot from a browser
+//! MCP tool: shown as a line saying what they are, and with
+//! `--save-images` written out so they can be opened.
+
+use crate::config::Labels;
+use crate::format::format_bytes;
+use crate::message::ImageSource;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The line for an image, e.g. `🖼️ image (png, 234.0 KB)`, followed by
+/// where it was saved, or why it couldn't be.
+pub fn format_image(source: &ImageSource, saved: Option<&io::Result<PathBuf>>, labels: &Labels) -> String {
+    let format = source.media_type.as_deref().map(|media_type| media_type.strip_prefix("image/").unwrap_or(media_type));
+    let about = match (&source.data, &source.url, format) {
+        (Some(data), _, Some(format)) => format!(" ({}, {})", format, format_bytes(decoded_len(data))),
+        (Some(data), _, None) => format!(" ({})", format_bytes(decoded_len(data))),
+        (None, Some(url), _) => format!(" ({})", url),
+        (None, None, Some(format)) => format!(" ({})", format),
+        (None, None, None) => String::new(),
+    };
+    match saved {
+        Some(Ok(path)) => format!("🖼️ {}{} → {}", labels.image, about, path.display()),
+        Some(Err(err)) => format!("🖼️ {}{}, {}: {}", labels.image, about, labels.not_saved, err),
+        None => format!("🖼️ {}{}", labels.image, about),
+    }
+}
+
+/// Writes an inline image to a new file in `dir`, `image-1.png` or the
+/// first number not taken, so images from earlier runs are kept. `None` if
+/// the image isn't inline.
+pub fn save(dir: &Path, source: &ImageSource) -> Option<io::Result<PathBuf>> {
+    let data = source.data.as_deref()?;
+    let Some(bytes) = decode(data) else {
+        return Some(Err(io::Error::new(io::ErrorKind::InvalidData, "not base64")));
+    };
+    let extension = match source.media_type.as_deref().and_then(|media_type| media_type.strip_prefix("image/")) {
+        Some("jpeg") => "jpg",
+        Some("svg+xml") => "svg",
+        Some(format) if !format.is_empty() && format.chars().all(|c| c.is_ascii_alphanumeric()) => format,
+        _ => "bin",
+    };
+    Some(write_new(dir, extension, &bytes))
+}
+
+fn write_new(dir: &Path, extension: &str, bytes: &[u8]) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    for n in 1.. {
+        let path = dir.join(format!("image-{}.{}", n, extension));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => return file.write_all(bytes).map(|_| path),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!()
+}
+
+/// The number of bytes base64 `data` decodes to.
+fn decoded_len(data: &str) -> usize {
+    let data = data.trim_end();
+    let padding = data.len() - data.trim_end_matches('=').len();
+    let digits = data.bytes().filter(|b| !b.is_ascii_whitespace()).count();
+    (digits * 3 / 4).saturating_sub(padding)
+}
+
+/// Decodes standard base64, ignoring whitespace and missing padding.
+fn decode(data: &str) -> Option<Vec<u8>> {
+    const ENGINE: GeneralPurpose =
+        GeneralPurpose::new(&alphabet::STANDARD, GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent));
+    let digits: Vec<u8> = data.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    ENGINE.decode(digits).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(data: &str) -> ImageSource {
+        ImageSource {
+            kind: "base64".to_string(),
+            media_type: Some("image/png".to_string()),
+            data: Some(data.to_string()),
+            url: None,
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("").unwrap(), b"");
+        assert_eq!(decode("Zg==").unwrap(), b"f");
+        assert_eq!(decode("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode("Zm9v\nYmFy").unwrap(), b"foobar");
+        assert_eq!(decode("Zm9v!"), None);
+        assert_eq!(decode("Zm8").unwrap(), b"fo");
+        for data in ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg=="] {
+            assert_eq!(decoded_len(data), decode(data).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn test_format_image() {
+        assert_eq!(format_image(&png(&"A".repeat(4000)), None, &Labels::default()), "🖼️ image (png, 3.0 KB)");
+        let url = ImageSource { kind: "url".to_string(), url: Some("https://example.com/a.png".to_string()), ..ImageSource::default() };
+        assert_eq!(format_image(&url, None, &Labels::default()), "🖼️ image (https://example.com/a.png)");
+        let saved = Ok(PathBuf::from("shots/image-1.png"));
+        assert_eq!(format_image(&png("Zm9v"), Some(&saved), &Labels::default()), "🖼️ image (png, 3 B) → shots/image-1.png");
+    }
+
+    #[test]
+    fn test_save() {
+        let dir = std::env::temp_dir().join(format!("csf-images-{}", std::process::id()));
+        let first = save(&dir, &png("iVBORw0KGgo=")).unwrap().unwrap();
+        let second = save(&dir, &png("iVBORw0KGgo=")).unwrap().unwrap();
+        assert_eq!(first, dir.join("image-1.png"));
+        assert_eq!(second, dir.join("image-2.png"));
+        assert_eq!(std::fs::read(&first).unwrap(), b"\x89PNG\r\n\x1a\n");
+        assert!(save(&dir, &png("not base64!")).unwrap().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(save(&dir, &ImageSource::default()).is_none());
+    }
+}
@@ -0,0 +1,59 @@
+//! Formats Claude Code's `--output-format stream-json` output into
+//! human-readable text.
+
+pub mod announce;
+pub mod ascii;
+pub mod bell;
+pub mod check;
+pub mod clipboard;
+pub mod compact;
+pub mod compare;
+pub mod config;
+pub mod cost;
+pub mod dialect;
+pub mod diff;
+pub mod event;
+pub mod exec;
+pub mod export;
+pub mod follow;
+pub mod format;
+pub mod formatter;
+pub mod gha;
+pub mod grep;
+pub mod highlight;
+pub mod history;
+pub mod hooks;
+pub mod hotkeys;
+pub mod html;
+pub mod image;
+pub mod lines;
+pub mod links;
+pub mod logfile;
+pub mod markdown;
+pub mod message;
+pub mod metrics;
+pub mod notify;
+pub mod pager;
+pub mod paths;
+pub mod problems;
+pub mod record;
+pub mod recover;
+pub mod redact;
+pub mod sessions;
+pub mod sink;
+pub mod spinner;
+pub mod stats;
+pub mod style;
+pub mod summary;
+pub mod template;
+pub mod time;
+pub mod watch;
+pub mod webhook;
+pub mod writer;
+#[cfg(feature = "tui")]
+pub mod tui;
+
+pub use formatter::{
+    FailOn, Kind, Options, Output, OutputFormat, Passthrough, Route, RunResult, StreamFormatter, DEFAULT_INDENT,
+};
+pub use message::StreamMessage;
@@ -0,0 +1,82 @@
+//! Reading a stream a line at a time as bytes, so that a line that isn't
+//! UTF-8, as a corrupted or truncated stream can hold, is one bad line
+//! rather than the end of the input.
+
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::str::Utf8Error;
+
+/// One line of input, without its line ending.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    bytes: Vec<u8>,
+}
+
+impl Line {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn text(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.bytes)
+    }
+
+    /// The line with anything that isn't UTF-8 replaced by `�`.
+    pub fn lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+}
+
+impl From<String> for Line {
+    fn from(text: String) -> Self {
+        Line { bytes: text.into_bytes() }
+    }
+}
+
+/// The lines of `input`, ending at `\n` or `\r\n`, as [`BufRead::lines`]
+/// does.
+pub fn lines<R: BufRead>(input: R) -> Lines<R> {
+    Lines { input }
+}
+
+pub struct Lines<R> {
+    input: R,
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<io::Result<Line>> {
+        let mut bytes = Vec::new();
+        match self.input.read_until(b'\n', &mut bytes) {
+            Ok(0) => None,
+            Ok(_) => {
+                if bytes.ends_with(b"\n") {
+                    bytes.pop();
+                    if bytes.ends_with(b"\r") {
+                        bytes.pop();
+                    }
+                }
+                Some(Ok(Line { bytes }))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let input: &[u8] = b"{\"a\": 1}\r\nbad \xff line\n\nlast";
+        let lines: Vec<Line> = lines(input).collect::<io::Result<_>>().unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].text(), Ok("{\"a\": 1}"));
+        assert_eq!(lines[1].text().unwrap_err().valid_up_to(), 4);
+        assert_eq!(lines[1].lossy(), "bad \u{FFFD} line");
+        assert_eq!(lines[2].bytes(), b"");
+        assert_eq!(lines[3].text(), Ok("last"));
+    }
+}
@@ -0,0 +1,99 @@
+//! `--links`: OSC 8 hyperlinks on the file paths of tool lines, so
+//! clicking one opens the file, in the editor of the user's choosing with
+//! `--link-template`.
+
+/// Opens files with whatever handles `file://` URLs.
+pub const DEFAULT_TEMPLATE: &str = "file://{path}";
+
+/// Tools whose file path is linked.
+pub const LINKED_TOOLS: [&str; 5] = ["Read", "Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// Terminals that set `TERM_PROGRAM` and open OSC 8 links.
+const LINKING_PROGRAMS: [&str; 6] = ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper", "tmux"];
+
+/// `TERM` values of terminals that open OSC 8 links.
+const LINKING_TERMS: [&str; 5] = ["xterm-kitty", "xterm-ghostty", "wezterm", "alacritty", "foot"];
+
+/// Whether the terminal, as described by the environment variables `var`
+/// reads, is known to open OSC 8 links. Others may print the escape codes
+/// as text, so they are left out: the Linux console, and GNU screen, which
+/// doesn't pass them through.
+pub fn supported(var: impl Fn(&str) -> Option<String>) -> bool {
+    let term = var("TERM").unwrap_or_default();
+    if term == "dumb" || term == "linux" || (term.starts_with("screen") && var("TMUX").is_none()) || var("STY").is_some() {
+        return false;
+    }
+    // VTE terminals, such as GNOME Terminal, from 0.50.
+    let vte = var("VTE_VERSION").and_then(|version| version.parse::<u32>().ok()).is_some_and(|version| version >= 5000);
+    vte || var("TERM_PROGRAM").is_some_and(|program| LINKING_PROGRAMS.contains(&program.as_str()))
+        || LINKING_TERMS.contains(&term.as_str())
+        // Windows Terminal, Kitty and Konsole.
+        || ["WT_SESSION", "KITTY_WINDOW_ID", "KONSOLE_VERSION"].iter().any(|name| var(name).is_some())
+}
+
+/// `text` as a hyperlink to `url`.
+pub fn link(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// The URL for `path` from `template`, filling in `{path}` with the
+/// absolute path, resolved from `cwd` if it is relative, and `{line}` with
+/// `line`.
+pub fn url(template: &str, path: &str, cwd: Option<&str>, line: u64) -> String {
+    let path = match cwd {
+        Some(cwd) if !path.starts_with('/') => format!("{}/{}", cwd.trim_end_matches('/'), path),
+        _ => path.to_string(),
+    };
+    template.replace("{path}", &encode(&path)).replace("{line}", &line.to_string())
+}
+
+/// Percent-encodes the bytes of `path` that can't appear in a URL as they
+/// are, leaving its slashes.
+fn encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~:@!$&'()*+,;=".contains(&byte) {
+            out.push(char::from(byte));
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        assert_eq!(url(DEFAULT_TEMPLATE, "/repo/src/main.rs", None, 1), "file:///repo/src/main.rs");
+        assert_eq!(url(DEFAULT_TEMPLATE, "src/my file.rs", Some("/repo/"), 1), "file:///repo/src/my%20file.rs");
+        assert_eq!(
+            url("vscode://file/{path}:{line}", "/repo/né.rs", Some("/elsewhere"), 40),
+            "vscode://file//repo/n%C3%A9.rs:40"
+        );
+    }
+
+    #[test]
+    fn test_supported() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string())
+        };
+        assert!(supported(env(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "iTerm.app")])));
+        assert!(supported(env(&[("TERM", "xterm-256color"), ("VTE_VERSION", "7600")])));
+        assert!(supported(env(&[("TERM", "xterm-256color"), ("WT_SESSION", "abc")])));
+        assert!(supported(env(&[("TERM", "xterm-kitty")])));
+        assert!(supported(env(&[("TERM", "screen-256color"), ("TMUX", "/tmp/tmux-1000/default,1,0"), ("TERM_PROGRAM", "tmux")])));
+        assert!(!supported(env(&[("TERM", "xterm-256color"), ("VTE_VERSION", "4803")])));
+        assert!(!supported(env(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "Apple_Terminal")])));
+        assert!(!supported(env(&[("TERM", "linux")])));
+        assert!(!supported(env(&[("TERM", "screen"), ("VTE_VERSION", "7600")])));
+        assert!(!supported(env(&[])));
+    }
+
+    #[test]
+    fn test_link() {
+        assert_eq!(link("a.rs", "file:///a.rs"), "\x1b]8;;file:///a.rs\x1b\\a.rs\x1b]8;;\x1b\\");
+    }
+}
@@ -0,0 +1,128 @@
+//! `--log-file`: a plain-text copy of the output, appended to a file that
+//! is rotated once it grows past `--log-rotate`, so a long-lived agent
+//! keeps a bounded history without a logrotate config.
+
+use crate::style::strip_ansi;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Rotated files kept, as `PATH.1` (the newest) to `PATH.3`.
+const KEEP: usize = 3;
+
+pub struct LogFile {
+    path: PathBuf,
+    /// Rotate once the file reaches this many bytes; 0 never does.
+    limit: u64,
+    file: File,
+    size: u64,
+    /// Whether the last write ended a line. Files are only rotated between
+    /// lines, so streamed text isn't split across two.
+    at_line_start: bool,
+}
+
+impl LogFile {
+    /// Opens `path` for appending.
+    pub fn open(path: &Path, limit: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            limit,
+            file,
+            size,
+            at_line_start: true,
+        })
+    }
+
+    /// Moves `PATH` to `PATH.1`, `PATH.1` to `PATH.2` and so on, dropping
+    /// the oldest, and starts a new `PATH`.
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        for n in (1..KEEP).rev() {
+            if rotated(n).exists() {
+                fs::rename(rotated(n), rotated(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(1))?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for LogFile {
+    /// Writes `buf` without its color codes.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.limit > 0 && self.size >= self.limit && self.at_line_start {
+            self.rotate()?;
+        }
+        let text = strip_ansi(&String::from_utf8_lossy(buf));
+        self.file.write_all(text.as_bytes())?;
+        self.size += text.len() as u64;
+        self.at_line_start = buf.ends_with(b"\n");
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Parses a size such as `10MB`, `512k` or `4096`. Units are powers of
+/// 1024.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let count: u64 = s[..digits].parse().map_err(|_| format!("invalid size: {}", s))?;
+    let unit = match s[digits..].trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        unit => return Err(format!("unknown size unit: {}", unit)),
+    };
+    count.checked_mul(unit).ok_or_else(|| format!("size too large: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("10MB"), Ok(10 << 20));
+        assert_eq!(parse_size("512k"), Ok(512 << 10));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("1 GiB"), Ok(1 << 30));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let dir = std::env::temp_dir().join(format!("csf-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.log");
+        let mut log = LogFile::open(&path, 10).unwrap();
+        write!(log, "\x1b[1mstreamed").unwrap();
+        writeln!(log, " text\x1b[0m").unwrap();
+        writeln!(log, "second").unwrap();
+        for n in 0..4 {
+            writeln!(log, "line {}", n).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line 3\n");
+        assert_eq!(fs::read_to_string(dir.join("out.log.1")).unwrap(), "line 1\nline 2\n");
+        assert_eq!(fs::read_to_string(dir.join("out.log.2")).unwrap(), "second\nline 0\n");
+        assert_eq!(fs::read_to_string(dir.join("out.log.3")).unwrap(), "streamed text\n");
+        assert!(!dir.join("out.log.4").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
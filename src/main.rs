@@ -1,243 +1,1490 @@
-use serde::Deserialize;
-use std::io::{self, BufRead, Write};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use claude_stream_format::announce::Speaker;
+use claude_stream_format::ascii::{to_ascii, AsciiChoice};
+use claude_stream_format::bell::{self, Alert};
+use claude_stream_format::check::Report;
+use claude_stream_format::clipboard;
+use claude_stream_format::compare::{self, Run};
+use claude_stream_format::config::{Config, Labels, Noun};
+use claude_stream_format::dialect::{Dialect, Translator};
+use claude_stream_format::exec;
+use claude_stream_format::follow::Follow;
+use claude_stream_format::format::{plural, truncate};
+use claude_stream_format::history::SessionFile;
+use claude_stream_format::hooks::{self, Hook};
+use claude_stream_format::hotkeys::{Hotkeys, Key};
+use claude_stream_format::lines::{self, Line};
+use claude_stream_format::links;
+use claude_stream_format::logfile::{self, LogFile};
+use claude_stream_format::message::StreamMessage;
+use claude_stream_format::metrics::Metrics;
+use claude_stream_format::notify::notify;
+use claude_stream_format::pager;
+use claude_stream_format::record::{Header, Recorder, Recording, Terminal};
+use claude_stream_format::recover::Reassembler;
+use claude_stream_format::redact::Redactor;
+use claude_stream_format::sessions::Sessions;
+use claude_stream_format::sink::{FileSink, OutputSpec};
+use claude_stream_format::spinner::{Spinner, SPINNER_DELAY, SPINNER_TICK};
+use claude_stream_format::stats;
+use claude_stream_format::style::{ColorChoice, Theme, ThemeName};
+use claude_stream_format::template::Template;
+use claude_stream_format::time;
+use claude_stream_format::watch::Rule;
+use claude_stream_format::webhook;
+use claude_stream_format::writer::{FlushPolicy, Writer, FLUSH_INTERVAL};
+#[cfg(feature = "tui")]
+use claude_stream_format::tui;
+use claude_stream_format::{FailOn, Kind, Options, Output, OutputFormat, Passthrough, Route, StreamFormatter, DEFAULT_INDENT};
+use regex::Regex;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[derive(Deserialize)]
-struct StreamMessage {
-    #[serde(rename = "type")]
-    msg_type: String,
-    message: Option<AssistantMessage>,
-    result: Option<String>,
+/// The buffer size for reading input.
+const INPUT_BUFFER: usize = 256 * 1024;
+
+/// How many lines the reader gets ahead of the formatter before it waits,
+/// so that while output is paused the input is held up in the pipe.
+const LINE_BACKLOG: usize = 1024;
+
+/// How long, at the end of the stream, to wait for an `--on-event` command
+/// to take its event before leaving it.
+const HOOK_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Environment variables setting options start with this, followed by the
+/// option's name, e.g. `CLAUDE_STREAM_FORMAT_COLOR` for `--color`.
+const ENV_PREFIX: &str = "CLAUDE_STREAM_FORMAT_";
+
+#[derive(Parser)]
+#[command(
+    version,
+    about,
+    after_help = "Every option can also be set with an environment variable named after it, \
+                  e.g. CLAUDE_STREAM_FORMAT_COLOR=always for --color. Options given on the \
+                  command line take precedence."
+)]
+struct Cli {
+    /// Print assistant text live from partial message deltas
+    /// (requires `--include-partial-messages`)
+    #[arg(long)]
+    partial: bool,
+
+    /// Show a preview of each tool's output under its tool_use line
+    #[arg(long)]
+    show_tool_results: bool,
+
+    /// When each Bash command finishes, show its exit code and a preview of
+    /// its output
+    #[arg(long)]
+    show_bash_output: bool,
+
+    /// Write each image shown, such as a screenshot a tool took, to a new
+    /// file in this directory, and show its path
+    #[arg(long, value_name = "DIR", conflicts_with = "tui")]
+    save_images: Option<PathBuf>,
+
+    /// Print each tool line when the call finishes, with how long it took,
+    /// highlighting calls that take at least SLOW (default 30s)
+    #[arg(long, value_name = "SLOW", value_parser = time::parse_duration, num_args = 0..=1, default_missing_value = "30s")]
+    tool_durations: Option<Duration>,
+
+    /// Render Markdown in assistant text (headings, lists, code fences)
+    #[arg(long)]
+    markdown: bool,
+
+    /// Show extended thinking, dimmed and prefixed with 🧠
+    #[arg(long)]
+    show_thinking: bool,
+
+    /// Mark tool calls matching TOOL:PATTERN with ⚠️ in bold red, e.g.
+    /// 'Bash:rm ' or 'Write:*.env' (repeatable; `*` and `?` are wildcards)
+    #[arg(long, value_name = "TOOL:PATTERN")]
+    highlight: Vec<Rule>,
+
+    /// Condense each assistant turn to one line: the first sentence of its
+    /// text and a count of its tool calls, e.g. `Fixing the parser… [2
+    /// edits, 1 bash]`
+    #[arg(long, conflicts_with = "group")]
+    compact: bool,
+
+    /// After N identical tool calls in a row (default 5), print
+    /// `🔁 repeated 5× in a row`, and again after each N more
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "5")]
+    warn_repeats: Option<usize>,
+
+    /// With --warn-repeats, hide identical tool calls after the first,
+    /// leaving the notes to count them
+    #[arg(long, requires = "warn_repeats")]
+    collapse_repeats: bool,
+
+    /// Append each assistant message's token usage, e.g. [1.2k→340 tok],
+    /// and print the running total at the end
+    #[arg(long)]
+    tokens: bool,
+
+    /// Show the text of user messages (the prompt and any later turns),
+    /// prefixed with 👤
+    #[arg(long)]
+    show_user: bool,
+
+    /// Cut each thinking block to this many columns (0 shows it all)
+    #[arg(long, value_name = "COLUMNS", default_value_t = 0, requires = "show_thinking")]
+    thinking_length: usize,
+
+    /// Disable truncation and print each tool's full input as JSON
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Show file paths relative to PATH, or to the session's working
+    /// directory if no PATH is given
+    #[arg(long, value_name = "PATH", num_args = 0..=1)]
+    cwd: Option<Option<String>>,
+
+    /// Shorten file paths longer than N columns (default 40) with a … in
+    /// the middle
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "40")]
+    shorten_paths: Option<usize>,
+
+    /// When to make the file paths of Read, Edit and Write lines clickable
+    /// hyperlinks: auto (in terminals known to open them), always or never
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, hide_possible_values = true)]
+    links: ColorChoice,
+
+    /// The URL file path links open, with {path} and {line} filled in,
+    /// e.g. vscode://file/{path}:{line}
+    #[arg(long, value_name = "TEMPLATE", default_value = links::DEFAULT_TEMPLATE)]
+    link_template: String,
+
+    /// Show a diff of each Edit's old and new text
+    #[arg(long)]
+    show_diffs: bool,
+
+    /// List every TodoWrite item instead of a one-line count
+    #[arg(long)]
+    expand_todos: bool,
+
+    /// Leave turns, duration, cost and token usage off the result line
+    #[arg(long)]
+    no_stats: bool,
+
+    /// Print the result text even when it repeats the assistant's last text
+    #[arg(long)]
+    no_dedupe_result: bool,
+
+    /// Prefix each line with the wall-clock time (UTC)
+    #[arg(long)]
+    timestamps: bool,
+
+    /// Prefix each line with the time since the stream started
+    #[arg(long)]
+    elapsed: bool,
+
+    /// Only show these kinds of output (repeatable or comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KIND")]
+    only: Vec<Kind>,
+
+    /// Hide these kinds of output (repeatable or comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "KIND")]
+    hide: Vec<Kind>,
+
+    /// Send kinds of output to stdout or stderr, e.g. tools,header=stderr
+    /// to pipe on only the text and result (repeatable)
+    #[arg(long, value_name = "KINDS=CHANNEL")]
+    route: Vec<Route>,
+
+    /// Collapse runs of at least N consecutive calls to the same tool into
+    /// one line
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "3", conflicts_with = "grep")]
+    group: Option<usize>,
+
+    /// Only show events whose output or tool input matches this regex
+    #[arg(long, value_name = "PATTERN", value_parser = Regex::new)]
+    grep: Option<Regex>,
+
+    /// Also show this many events before and after each --grep match
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "grep")]
+    grep_context: usize,
+
+    /// Mask secrets such as AWS keys, bearer tokens and passwords in the
+    /// commands, tool inputs and results shown
+    #[arg(long, conflicts_with = "tui")]
+    redact: bool,
+
+    /// Also mask text matching this regex; implies --redact (repeatable)
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new, conflicts_with = "tui")]
+    redact_pattern: Vec<Regex>,
+
+    /// Print a heading where each turn starts, with its number
+    #[arg(long)]
+    number_turns: bool,
+
+    /// Only show the turns from this one on, counting from 1, e.g. of a
+    /// recorded session; the session header is always shown
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    from_turn: Option<u64>,
+
+    /// Only show the turns up to this one
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    to_turn: Option<u64>,
+
+    /// Print only the final result's full text, for extracting the answer
+    /// in scripts
+    #[arg(short, long, conflicts_with_all = ["output", "tui", "summary", "grep", "group"])]
+    quiet: bool,
+
+    /// Print tool counts, files touched and other statistics at the end
+    #[arg(long)]
+    summary: bool,
+
+    /// Print each file read, edited or written at the end, with how many
+    /// times, changed files first
+    #[arg(long)]
+    files_report: bool,
+
+    /// Indentation for each level of sub-agent (Task) output
+    #[arg(long, value_name = "PREFIX", default_value = DEFAULT_INDENT)]
+    indent: String,
+
+    /// Show an interactive view with scrollback and collapsible tool output
+    /// instead of printing (needs the `tui` build feature)
+    #[arg(long, conflicts_with_all = ["output", "by_session", "tee", "metrics", "export_md", "grep", "group"])]
+    tui: bool,
+
+    /// On a terminal, page the output through $PAGER (default `less`) as
+    /// it streams, like `git log`; done anyway when $PAGER is set
+    #[arg(long, conflicts_with = "tui")]
+    pager: bool,
+
+    /// Don't page the output, even with $PAGER set
+    #[arg(long, conflicts_with = "pager")]
+    no_pager: bool,
+
+    /// Tag each line with its session, keeping headers and statistics
+    /// separate for each session in an interleaved stream
+    #[arg(long, conflicts_with_all = ["metrics", "export_md"])]
+    by_session: bool,
+
+    /// Don't print the session header from the init message
+    #[arg(long)]
+    no_header: bool,
+
+    /// Output format: text, json, html, logfmt, ndjson, gha, problems or
+    /// columns. FORMAT=PATH writes that format to a file as well, alongside
+    /// stdout (repeatable)
+    #[arg(long, value_name = "FORMAT[=PATH]", value_parser = str::parse::<OutputSpec>)]
+    output: Vec<OutputSpec>,
+
+    /// Print each event on one line of aligned columns, without emoji:
+    /// time, event type, tool and summary (the same as --output columns)
+    #[arg(long)]
+    columns: bool,
+
+    /// Read from a log file instead of stdin, waiting for new lines as it
+    /// grows (like `tail -f`)
+    #[arg(short, long, value_name = "PATH")]
+    follow: Option<PathBuf>,
+
+    /// Read a past session from one of the transcripts Claude Code keeps
+    /// under ~/.claude/projects instead of stdin
+    #[arg(long, value_name = "PATH", conflicts_with = "follow")]
+    session_file: Option<PathBuf>,
+
+    /// The shape of the input: Claude Code's stream-json, or the Messages
+    /// API's streaming events
+    #[arg(long, value_enum, default_value_t = Dialect::Auto, conflicts_with = "session_file")]
+    dialect: Dialect,
+
+    /// Also write every raw input line to this file
+    #[arg(long, value_name = "PATH")]
+    tee: Option<PathBuf>,
+
+    /// Record the raw input, with when each line arrived and the terminal
+    /// size, to a compressed file that `play` formats again later
+    #[arg(long, value_name = "PATH", conflicts_with = "session_file")]
+    record: Option<PathBuf>,
+
+    /// Also append the formatted output, without colors, to this file
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Rotate the --log-file once it reaches this size (e.g. 10MB), keeping
+    /// the last three as PATH.1 to PATH.3
+    #[arg(long, value_name = "SIZE", value_parser = logfile::parse_size, requires = "log_file")]
+    log_rotate: Option<u64>,
+
+    /// At the end of the stream, also write a Markdown transcript of the
+    /// session to this file
+    #[arg(long, value_name = "PATH")]
+    export_md: Option<PathBuf>,
+
+    /// At the end of the stream, write tool counts and timings, token usage
+    /// and cost to this file as JSON
+    #[arg(long, value_name = "PATH")]
+    metrics: Option<PathBuf>,
+
+    /// Show a desktop notification when the run finishes
+    #[arg(long)]
+    notify: bool,
+
+    /// POST a JSON summary of the run (session, status, duration, cost and
+    /// the start of the result) to this URL when it finishes; needs curl
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+
+    /// Copy the result text to the clipboard when the run finishes, with
+    /// OSC 52 over SSH
+    #[arg(long)]
+    copy_result: bool,
+
+    /// Print a warning when the session's cost passes this many dollars,
+    /// estimated from token usage until a result reports it
+    #[arg(long, value_name = "USD")]
+    max_cost: Option<f64>,
+
+    /// Once the cost passes --max-cost, stop reading and exit with status
+    /// 3, ending a command started with exec
+    #[arg(long, requires = "max_cost")]
+    exit_over_budget: bool,
+
+    /// Run COMMAND with `sh -c` and the event's JSON on stdin for each
+    /// matching event: `tool_use:Bash=./log_bash.sh`, `result=...` or
+    /// `error=...` (repeatable)
+    #[arg(long, value_name = "EVENT=COMMAND")]
+    on_event: Vec<Hook>,
+
+    /// Ring the terminal bell on these events (repeatable or
+    /// comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "EVENT")]
+    bell: Vec<Alert>,
+
+    /// Run this shell command instead of ringing the bell, with the event
+    /// name in `CSF_EVENT`
+    #[arg(long, value_name = "COMMAND", requires = "bell")]
+    bell_command: Option<String>,
+
+    /// Speak key events aloud, such as file edits and the result; which
+    /// are spoken is set in the config's [announce] table
+    #[arg(long)]
+    announce: bool,
+
+    /// Speak with this shell command, given the text on stdin and in
+    /// `CSF_TEXT`, instead of the system's speech command
+    #[arg(long, value_name = "COMMAND", requires = "announce")]
+    announce_command: Option<String>,
+
+    /// Which failures give a non-zero exit status
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = FailOn::Result)]
+    fail_on_error: FailOn,
+
+    /// Warn on stderr about each input line that isn't stream-json
+    #[arg(long)]
+    strict: bool,
+
+    /// Print input lines that aren't stream-json, such as output from hooks
+    /// or wrapper scripts, instead of skipping them
+    #[arg(long, value_enum, value_name = "STYLE", num_args = 0..=1, default_missing_value = "plain")]
+    passthrough: Option<Passthrough>,
+
+    /// Exit with status 2 if more than this many lines fail to parse
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    max_parse_errors: usize,
+
+    /// Format a tool's line with a template, e.g.
+    /// 'Bash=$ {input.command|truncate:60}' (repeatable; overrides the config
+    /// file)
+    #[arg(long, value_name = "NAME=TEMPLATE", value_parser = parse_template)]
+    template: Vec<(String, Template)>,
+
+    /// Config file [default: ~/.config/claude-stream-format/config.toml]
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Don't show a progress spinner while waiting for events on a terminal
+    #[arg(long)]
+    no_spinner: bool,
+
+    /// Don't read keys from the terminal while streaming: t to toggle tool
+    /// results, k to toggle thinking, p to pause and s to print the summary
+    /// so far
+    #[arg(long)]
+    no_hotkeys: bool,
+
+    /// Print a dimmed note when no events have arrived for this long (e.g.
+    /// 90s, 2m), and again each time as long passes, so a stalled agent
+    /// stands out in CI logs
+    #[arg(long, value_name = "DURATION", value_parser = time::parse_duration)]
+    idle_warning: Option<Duration>,
+
+    /// Write output at most this often (e.g. 20/s), coalescing the text
+    /// streamed in between, to cut flicker and CPU with --partial
+    #[arg(long, value_name = "RATE", value_parser = time::parse_rate)]
+    rate_limit: Option<Duration>,
+
+    /// When to flush output: batched sends results and errors at once and
+    /// anything else within 50 ms (or --rate-limit)
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = FlushPolicy::Batched)]
+    flush_policy: FlushPolicy,
+
+    /// Truncate tool and result lines to this many columns
+    /// [default: terminal width when stdout is a terminal]
+    #[arg(long, value_name = "COLUMNS")]
+    max_width: Option<usize>,
+
+    /// Cut Bash commands to this many columns (0 for no limit; default 80)
+    #[arg(long, value_name = "COLUMNS")]
+    truncate_command: Option<usize>,
+
+    /// Cut the result text and each line of tool output to this many
+    /// columns (0 for no limit; default 80)
+    #[arg(long, value_name = "COLUMNS")]
+    truncate_result: Option<usize>,
+
+    /// Cut each block of assistant text to this many columns (0 for no
+    /// limit, the default); text streamed with --partial is never cut
+    #[arg(long, value_name = "COLUMNS")]
+    truncate_text: Option<usize>,
+
+    /// Cut each block of assistant text to this many lines, noting how many
+    /// more there were
+    #[arg(long, value_name = "N")]
+    max_lines_per_message: Option<usize>,
+
+    /// Cut long lines of tool output instead of word-wrapping them, and
+    /// leave assistant text to the terminal to wrap
+    #[arg(long)]
+    no_wrap: bool,
+
+    /// When to use ANSI colors (respects `NO_COLOR`)
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Color theme to use when colors are enabled
+    #[arg(long, value_enum, default_value_t = ThemeName::Dark)]
+    theme: ThemeName,
+
+    /// Print ASCII tags such as [READ] in place of emoji; by default, when
+    /// the terminal doesn't look able to show emoji
+    #[arg(long, value_enum, value_name = "WHEN", num_args = 0..=1, default_value_t = AsciiChoice::Auto, default_missing_value = "always")]
+    ascii: AsciiChoice,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Cli {
+    /// The format written to stdout: `--columns`, or the `--output` given
+    /// without a path, otherwise text. `None` if more than one was given.
+    fn stdout_format(&self) -> Option<OutputFormat> {
+        let columns = self.columns.then_some(OutputFormat::Columns);
+        let mut formats = self.output.iter().filter(|spec| spec.path.is_none()).map(|spec| spec.format).chain(columns);
+        let format = formats.next().unwrap_or(OutputFormat::Text);
+        formats.next().is_none().then_some(format)
+    }
 }
 
-#[derive(Deserialize)]
-struct AssistantMessage {
-    content: Vec<ContentBlock>,
+#[derive(Subcommand)]
+enum Command {
+    /// Run claude with the flags for stream-json output and format what it
+    /// prints, e.g. `claude-stream-format exec -- claude -p "fix the tests"`
+    Exec {
+        /// The command to run: claude and its arguments
+        #[arg(value_name = "COMMAND", required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Compare two recorded stream-json runs, e.g. the `--tee` copies of one
+    /// prompt given to two models: turns, tool use, files, cost and result.
+    /// Exits 1 if their tool calls or results differ
+    Diff {
+        /// The earlier run
+        old: PathBuf,
+        /// The run to compare it with
+        new: PathBuf,
+    },
+    /// Check that a stream is valid stream-json without formatting it:
+    /// counts each type of message and lists the lines that fail to parse,
+    /// and why. Exits 1 if any do
+    Check {
+        /// The stream to check, or stdin
+        file: Option<PathBuf>,
+    },
+    /// Totals across many sessions: cost, tokens, the tools used, the
+    /// files edited most and how long a session runs, e.g.
+    /// `claude-stream-format stats ~/.claude/projects/**/*.jsonl`
+    Stats {
+        /// Session transcripts or recorded stream-json runs, and
+        /// directories to search for `.jsonl` files; ~/.claude/projects if
+        /// none are given
+        paths: Vec<PathBuf>,
+    },
+    /// Format a session recorded with --record, with whatever options are
+    /// given now, at the pace it arrived
+    Play {
+        /// The recording
+        file: PathBuf,
+        /// How many times faster than it was recorded to play it; 0 plays
+        /// it at once
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    },
 }
 
-#[derive(Deserialize)]
-#[serde(tag = "type")]
-enum ContentBlock {
-    #[serde(rename = "text")]
-    Text { text: String },
-    #[serde(rename = "tool_use")]
-    ToolUse { name: String, input: serde_json::Value },
-    #[serde(other)]
-    Other,
+/// Parses the command line, taking options it doesn't give from their
+/// environment variables.
+fn parse_cli() -> Cli {
+    let matches = cli_matches(std::env::args_os().collect(), |var| std::env::var_os(var));
+    Cli::from_arg_matches(&matches.unwrap_or_else(|err| err.exit())).unwrap_or_else(|err| err.exit())
 }
 
-fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len - 3])
+/// Parses `args`, then again with the options that `env` gives a variable
+/// for added, so long as `args` neither give them nor conflict with them:
+/// the command line takes precedence, and a variable never counts as given
+/// for the options it would conflict with.
+fn cli_matches(mut args: Vec<OsString>, env: impl Fn(&str) -> Option<OsString>) -> Result<ArgMatches, clap::Error> {
+    let command = Cli::command();
+    let matches = command.clone().try_get_matches_from(&args)?;
+    let given: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+    let conflict = |a: &Arg, b: &Arg| command.get_arg_conflicts_with(a).iter().any(|arg| arg.get_id() == b.get_id());
+    let mut defaults = Vec::new();
+    for arg in command.get_arguments() {
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        // An empty variable is taken as unset.
+        let Some(value) = env(&env_var(long)).filter(|value| !value.is_empty()) else {
+            continue;
+        };
+        if given.iter().any(|other| other.get_id() == arg.get_id() || conflict(arg, other) || conflict(other, arg)) {
+            continue;
+        }
+        match arg.get_action() {
+            // Switches can be turned on or off with 1/0, yes/no and so on.
+            ArgAction::SetTrue => match boolish(&value.to_string_lossy()) {
+                Some(true) => defaults.push(OsString::from(format!("--{}", long))),
+                Some(false) => {}
+                None => {
+                    let message = format!("invalid value {:?} for {}: expected true or false", value, env_var(long));
+                    return Err(command.clone().error(ErrorKind::InvalidValue, message));
+                }
+            },
+            ArgAction::Set | ArgAction::Append => {
+                let mut default = OsString::from(format!("--{}=", long));
+                default.push(&value);
+                defaults.push(default);
+            }
+            _ => {}
+        }
+    }
+    if defaults.is_empty() {
+        return Ok(matches);
+    }
+    args.splice(1.min(args.len())..1.min(args.len()), defaults);
+    command.try_get_matches_from(args)
+}
+
+/// A switch's value as `yes`, `no`, `1`, `0`, `on`, `off` and the like.
+fn boolish(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "y" | "yes" | "t" | "true" | "on" => Some(true),
+        "0" | "n" | "no" | "f" | "false" | "off" => Some(false),
+        _ => None,
     }
 }
 
-fn format_tool_use(name: &str, input: &serde_json::Value) -> String {
-    match name {
-        "Read" => {
-            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("📖 Read: {}", file_path)
+/// The environment variable for the option `--long`.
+fn env_var(long: &str) -> String {
+    format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_"))
+}
+
+/// Prints how the runs at `old` and `new` differ, returning the exit code.
+fn compare_runs(old: &Path, new: &Path, config: &Config, cli: &Cli) -> i32 {
+    let read = |path: &Path| match File::open(path).and_then(|file| Run::read(BufReader::new(file), config)) {
+        Ok(run) => run,
+        Err(err) => {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            process::exit(2);
+        }
+    };
+    let (old_run, new_run) = (read(old), read(new));
+    let names = (old.display().to_string(), new.display().to_string());
+    let text = compare::render(&old_run, &new_run, (&names.0, &names.1), &Theme::resolve(cli.theme, cli.color), &config.labels);
+    println!("{}", if cli.ascii.enabled() { to_ascii(&text) } else { text });
+    i32::from(compare::differ(&old_run, &new_run))
+}
+
+/// Prints what the stream in `file`, or stdin, holds, returning the exit
+/// code.
+fn check_stream(file: Option<&Path>, labels: &Labels, cli: &Cli) -> i32 {
+    let report = match file {
+        Some(path) => File::open(path).and_then(|file| Report::read(BufReader::new(file))),
+        None => Report::read(io::stdin().lock()),
+    };
+    let report = match report {
+        Ok(report) => report,
+        Err(err) => {
+            let name = file.map_or("stdin".to_string(), |path| path.display().to_string());
+            eprintln!("claude-stream-format: {}: {}", name, err);
+            process::exit(2);
+        }
+    };
+    let text = report.render(&Theme::resolve(cli.theme, cli.color), labels);
+    println!("{}", if cli.ascii.enabled() { to_ascii(&text) } else { text });
+    i32::from(!report.is_valid())
+}
+
+/// Prints the totals of the sessions in `paths`, returning the exit code.
+fn session_stats(paths: &[PathBuf], labels: &Labels, cli: &Cli) -> i32 {
+    let paths = match paths {
+        [] => match stats::projects_dir() {
+            Some(dir) => vec![dir],
+            None => {
+                eprintln!("claude-stream-format: no session files given, and HOME isn't set");
+                return 2;
+            }
+        },
+        paths => paths.to_vec(),
+    };
+    // One file that can't be read shouldn't lose the totals of the rest.
+    let skip = |path: &Path, err: io::Error| eprintln!("claude-stream-format: skipping {}: {}", path.display(), err);
+    let totals = stats::read(&stats::session_files(&paths, skip), skip);
+    let text = totals.render(&Theme::resolve(cli.theme, cli.color), labels);
+    println!("{}", if cli.ascii.enabled() { to_ascii(&text) } else { text });
+    0
+}
+
+fn main() {
+    let cli = parse_cli();
+    // One format goes to stdout, text unless another is chosen; the rest
+    // go to their files.
+    let Some(format) = cli.stdout_format() else {
+        eprintln!("claude-stream-format: only one --output can go to stdout; give the others a file with FORMAT=PATH");
+        process::exit(2);
+    };
+    let files: Vec<(PathBuf, OutputFormat)> = cli.output.iter().filter_map(|spec| Some((spec.path.clone()?, spec.format))).collect();
+    let mut config = match Config::discover(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("claude-stream-format: {}", err);
+            process::exit(2);
+        }
+    };
+    config.templates.extend(cli.template.iter().cloned());
+    let truncate = &mut config.truncate;
+    truncate.command = cli.truncate_command.or(truncate.command);
+    truncate.result = cli.truncate_result.or(truncate.result);
+    truncate.text = cli.truncate_text.or(truncate.text);
+    if let Some(Command::Diff { old, new }) = &cli.command {
+        process::exit(compare_runs(old, new, &config, &cli));
+    }
+    if let Some(Command::Check { file }) = &cli.command {
+        process::exit(check_stream(file.as_deref(), &config.labels, &cli));
+    }
+    if let Some(Command::Stats { paths }) = &cli.command {
+        process::exit(session_stats(paths, &config.labels, &cli));
+    }
+    let recording = match &cli.command {
+        Some(Command::Play { file, speed }) => {
+            if cli.follow.is_some() || cli.session_file.is_some() {
+                eprintln!("claude-stream-format: play can't be used with --follow or --session-file");
+                process::exit(2);
+            }
+            if speed.is_nan() || *speed < 0.0 {
+                eprintln!("claude-stream-format: --speed must be 0 or more");
+                process::exit(2);
+            }
+            match Recording::open(file) {
+                Ok(recording) => Some((recording, *speed)),
+                Err(err) => {
+                    eprintln!("claude-stream-format: {}: {}", file.display(), err);
+                    process::exit(2);
+                }
+            }
         }
-        "Edit" => {
-            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("✏️  Edit: {}", file_path)
+        _ => None,
+    };
+    // A recording is read as it was recorded, and laid out for the
+    // terminal it was shown on when there isn't one now.
+    let dialect = recording
+        .as_ref()
+        .and_then(|(recording, _)| Dialect::from_str(&recording.header.dialect, true).ok())
+        .unwrap_or(cli.dialect);
+    let recorded_width = recording.as_ref().and_then(|(recording, _)| recording.header.terminal.width);
+    let follow = cli.follow.as_deref().map(|path| match Follow::open(path) {
+        Ok(follow) => follow,
+        Err(err) => {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            process::exit(2);
         }
-        "Write" => {
-            let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("📝 Write: {}", file_path)
+    });
+    let session_file = cli.session_file.as_deref().map(|path| match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) => {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            process::exit(2);
         }
-        "Bash" => {
-            let command = input.get("command").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("💻 Bash: {}", truncate(command, 80))
+    });
+    let log = cli.log_file.as_deref().map(|path| match LogFile::open(path, cli.log_rotate.unwrap_or(0)) {
+        Ok(log) => log,
+        Err(err) => {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            process::exit(2);
         }
-        "Glob" => {
-            let pattern = input.get("pattern").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("🔍 Glob: {}", pattern)
+    });
+    let mut tee = cli.tee.clone().map(|path| match File::create(&path) {
+        Ok(file) => (path, file),
+        Err(err) => {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            process::exit(2);
         }
-        "Grep" => {
-            let pattern = input.get("pattern").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("🔍 Grep: {}", pattern)
+    });
+    let mut child = match &cli.command {
+        Some(Command::Exec { command }) => {
+            if cli.follow.is_some() || cli.session_file.is_some() {
+                eprintln!("claude-stream-format: exec can't be used with --follow or --session-file");
+                process::exit(2);
+            }
+            match exec::spawn(command, cli.partial) {
+                Ok(child) => Some(child),
+                Err(err) => {
+                    eprintln!("claude-stream-format: {}: {}", command[0], err);
+                    process::exit(2);
+                }
+            }
         }
-        "TodoWrite" => "📋 TodoWrite".to_string(),
-        "Task" => {
-            let description = input.get("description").and_then(|v| v.as_str()).unwrap_or("?");
-            format!("🤖 Task: {}", description)
+        Some(Command::Diff { .. } | Command::Check { .. } | Command::Stats { .. } | Command::Play { .. }) | None => None,
+    };
+    let child_stdout = child.as_mut().and_then(|child| child.stdout.take());
+    if let Some(child) = &child {
+        if let Err(err) = exec::forward_signals(child.id()) {
+            eprintln!("claude-stream-format: can't forward signals: {}", err);
         }
-        _ => format!("🔧 {}", name),
     }
-}
-
-fn process_line(line: &str) -> Option<String> {
-    let msg: StreamMessage = serde_json::from_str(line).ok()?;
-
-    match msg.msg_type.as_str() {
-        "assistant" => {
-            let message = msg.message?;
-            let mut output = Vec::new();
+    if let (Some(from), Some(to)) = (cli.from_turn, cli.to_turn) {
+        if from > to {
+            eprintln!("claude-stream-format: --from-turn {} is after --to-turn {}", from, to);
+            process::exit(2);
+        }
+    }
+    if cli.by_session && (format == OutputFormat::Html || files.iter().any(|(_, format)| *format == OutputFormat::Html)) {
+        eprintln!("claude-stream-format: --by-session can't be used with --output html");
+        process::exit(2);
+    }
 
-            for block in message.content {
-                match block {
-                    ContentBlock::Text { text } => {
-                        if !text.trim().is_empty() {
-                            output.push(text);
-                        }
+    // Read on a separate thread so the spinner can animate (or the TUI
+    // respond to keys) between lines.
+    let (lines_tx, lines_rx) = mpsc::sync_channel(LINE_BACKLOG);
+    let mut recorder = cli.record.clone().map(|path| {
+        let name = dialect.to_possible_value().map_or(String::new(), |value| value.get_name().to_string());
+        match Recorder::create(&path, &Header::new(&name, Terminal::current())) {
+            Ok(recorder) => (path, recorder),
+            Err(err) => {
+                eprintln!("claude-stream-format: {}: {}", path.display(), err);
+                process::exit(2);
+            }
+        }
+    });
+    thread::spawn(move || {
+        if let Some(file) = session_file {
+            let mut history = SessionFile::default();
+            for line in file.lines() {
+                let lines = match line {
+                    Ok(line) => {
+                        tee_line(&mut tee, line.as_bytes());
+                        history.translate(&line)
                     }
-                    ContentBlock::ToolUse { name, input } => {
-                        output.push(format_tool_use(&name, &input));
+                    Err(err) => {
+                        let _ = lines_tx.send(Err(err));
+                        continue;
                     }
-                    ContentBlock::Other => {}
+                };
+                if lines.into_iter().any(|line| lines_tx.send(Ok(line)).is_err()) {
+                    return;
                 }
             }
-
-            if output.is_empty() {
-                None
-            } else {
-                Some(output.join("\n"))
+            for line in history.finish() {
+                let _ = lines_tx.send(Ok(line));
             }
+            return;
         }
-        "result" => {
-            let result = msg.result?;
-            Some(format!("✅ Done: {}", truncate(&result, 80)))
+        // Lines are read as bytes so that one that isn't UTF-8 still
+        // reaches the --tee copy before it's given up on.
+        let lines: Box<dyn Iterator<Item = io::Result<Line>>> = match (follow, child_stdout, recording) {
+            (_, _, Some((recording, speed))) => Box::new(recording.play(speed).map(|line| line.map(Line::from))),
+            (Some(follow), _, None) => Box::new(follow.map(|line| line.map(Line::from))),
+            (None, Some(stdout), None) => Box::new(lines::lines(BufReader::with_capacity(INPUT_BUFFER, stdout))),
+            (None, None, None) => Box::new(lines::lines(BufReader::with_capacity(INPUT_BUFFER, io::stdin().lock()))),
+        };
+        let mut translator = Translator::new(dialect);
+        for line in lines {
+            if let Ok(line) = &line {
+                tee_line(&mut tee, line.bytes());
+            }
+            let line = line.and_then(|line| line.text().map(str::to_string).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)));
+            if let (Some((path, record)), Ok(line)) = (&mut recorder, &line) {
+                if let Err(err) = record.record(line) {
+                    eprintln!("claude-stream-format: {}: {}", path.display(), err);
+                    recorder = None;
+                }
+            }
+            let lines = match line {
+                Ok(line) => translator.translate(&line),
+                Err(err) => {
+                    let _ = lines_tx.send(Err(err));
+                    continue;
+                }
+            };
+            if lines.into_iter().any(|line| lines_tx.send(Ok(line)).is_err()) {
+                break;
+            }
         }
-        _ => None,
+        if let Some((path, recorder)) = recorder {
+            if let Err(err) = recorder.finish() {
+                eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            }
+        }
+    });
+    let max_width = cli
+        .max_width
+        .or_else(|| {
+            let (width, _) = terminal_size::terminal_size_of(io::stdout())?;
+            Some(usize::from(width.0))
+        })
+        .or(recorded_width.map(usize::from));
+    let ascii = cli.ascii.enabled();
+    let labels = config.labels.clone();
+    let announce_command = config.announce.command.clone();
+    // The last rule naming a kind decides. An HTML transcript is one file,
+    // and compact turns one line each.
+    let stderr_kinds = Kind::value_variants()
+        .iter()
+        .copied()
+        .filter(|kind| cli.route.iter().rev().find(|route| route.kinds.contains(kind)).is_some_and(|route| route.stderr))
+        .filter(|_| format != OutputFormat::Html && !cli.compact)
+        .collect();
+    let links_enabled = match cli.links {
+        ColorChoice::Auto => io::stdout().is_terminal() && links::supported(|name| std::env::var(name).ok()),
+        links => links.enabled(),
+    };
+    let options = Options {
+        partial: cli.partial,
+        show_tool_results: cli.show_tool_results,
+        show_bash_output: cli.show_bash_output,
+        save_images: cli.save_images.clone(),
+        tool_durations: cli.tool_durations,
+        max_cost: cli.max_cost,
+        tokens: cli.tokens,
+        compact: cli.compact,
+        highlight: cli.highlight,
+        on_event: cli.on_event,
+        announce: cli.announce,
+        warn_repeats: cli.warn_repeats,
+        collapse_repeats: cli.collapse_repeats,
+        markdown: cli.markdown,
+        show_thinking: cli.show_thinking,
+        show_user: cli.show_user,
+        thinking_length: cli.thinking_length,
+        max_lines_per_message: cli.max_lines_per_message,
+        verbose: cli.verbose,
+        max_width,
+        no_wrap: cli.no_wrap,
+        detect_cwd: matches!(cli.cwd, Some(None)),
+        cwd: cli.cwd.flatten(),
+        shorten_paths: cli.shorten_paths.unwrap_or(0),
+        link_template: (format.is_text() && links_enabled).then_some(cli.link_template),
+        show_diffs: cli.show_diffs,
+        expand_todos: cli.expand_todos,
+        no_stats: cli.no_stats,
+        no_dedupe_result: cli.no_dedupe_result,
+        no_header: cli.no_header,
+        timestamps: cli.timestamps,
+        elapsed: cli.elapsed,
+        only: cli.only,
+        hide: cli.hide,
+        stderr_kinds,
+        summary: cli.summary,
+        files_report: cli.files_report,
+        quiet: cli.quiet,
+        group: cli.group,
+        grep: cli.grep,
+        turns: (cli.from_turn.map(|n| n as usize), cli.to_turn.map(|n| n as usize)),
+        number_turns: cli.number_turns,
+        redact: (cli.redact || !cli.redact_pattern.is_empty()).then(|| Redactor::new(&cli.redact_pattern)),
+        grep_context: cli.grep_context,
+        ascii,
+        passthrough: cli.passthrough,
+        export_md: cli.export_md.is_some(),
+        indent: Some(cli.indent),
+        output: format,
+        config,
+        theme: if format.is_text() {
+            Theme::resolve(cli.theme, cli.color)
+        } else {
+            Theme::default()
+        },
+        tag: None,
+    };
+    if cli.tui {
+        run_tui(lines_rx, options, child, cli.fail_on_error);
     }
-}
+    // Run under exec, claude is left to react to signals, and its output as
+    // it stops is still formatted. Otherwise they stop the formatter where
+    // it is.
+    let signal = match &child {
+        Some(_) => Arc::new(AtomicUsize::new(0)),
+        None => exec::catch_signals().unwrap_or_else(|err| {
+            eprintln!("claude-stream-format: can't handle signals: {}", err);
+            Arc::new(AtomicUsize::new(0))
+        }),
+    };
+    let mut files: Vec<FileSink> = files
+        .iter()
+        .map(|(path, format)| match FileSink::create(path, *format, &options, cli.by_session) {
+            Ok(sink) => sink,
+            Err(err) => {
+                eprintln!("claude-stream-format: {}: {}", path.display(), err);
+                process::exit(2);
+            }
+        })
+        .collect();
+    let mut sessions = Sessions::new(options, cli.by_session);
+    let mut pager = match pager::choose(cli.pager, cli.no_pager, |name| std::env::var(name).ok()) {
+        Some(command) if io::stdout().is_terminal() => match pager::spawn(&command) {
+            Ok(pager) => Some(pager),
+            Err(err) => {
+                eprintln!("claude-stream-format: {}: {}", command, err);
+                None
+            }
+        },
+        _ => None,
+    };
+    let paged = pager.as_mut().and_then(|pager| pager.stdin.take());
+    let show_spinner =
+        !cli.no_spinner && !cli.quiet && format.is_text() && io::stdout().is_terminal() && paged.is_none();
+    // Keys are read from the terminal as long as it isn't where the input
+    // comes from, and the pager isn't reading them.
+    let interactive = !cli.no_hotkeys
+        && !cli.quiet
+        && format.is_text()
+        && io::stdout().is_terminal()
+        && paged.is_none()
+        && (child.is_some() || !io::stdin().is_terminal());
+    // Run under exec, the signals are forwarded to claude instead.
+    let hotkeys = interactive.then(|| Hotkeys::open().ok()).flatten();
+    let mut paused = false;
+    // Output is written on a thread of its own and flushed by the policy,
+    // so a fast stream isn't a write per line. With --rate-limit, batched
+    // flushes are kept that far apart.
+    let output: Box<dyn Write + Send> = match paged {
+        Some(stdin) => Box::new(stdin),
+        None => Box::new(io::stdout()),
+    };
+    let mut stdout = Sink {
+        out: Writer::spawn(output, cli.flush_policy, cli.rate_limit.unwrap_or(FLUSH_INTERVAL)),
+        log,
+        closed: false,
+    };
+    let mut spinner = if ascii { Spinner::ascii() } else { Spinner::default() };
+    let mut last_event = Instant::now();
+    let mut idle_warnings = 0;
+    let mut over_budget = false;
+    let mut reassembler = Reassembler::default();
+    let mut actions = Actions {
+        strict: cli.strict,
+        notify: cli.notify,
+        webhook: cli.webhook,
+        copy_result: cli.copy_result,
+        labels,
+        warn_on_stdout: format.is_text() && !cli.quiet,
+        bell: cli.bell,
+        bell_command: cli.bell_command,
+        speaker: cli.announce.then(|| Speaker::spawn(cli.announce_command.or(announce_command))),
+        hooks: Vec::new(),
+    };
 
-fn main() {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+    loop {
+        if signal.load(Ordering::Relaxed) != 0 || stdout.closed() {
+            break;
+        }
+        if let Some(hotkeys) = &hotkeys {
+            while let Some(key) = hotkeys.try_key() {
+                paused = press(key, paused, &mut sessions, &mut stdout, &mut spinner);
+            }
+            if paused {
+                // Nothing is formatted while paused. Once the reader is
+                // LINE_BACKLOG lines ahead it stops too, and the input
+                // waits in the pipe.
+                let _ = stdout.flush();
+                if let Ok(key) = hotkeys.key_timeout(SPINNER_TICK) {
+                    paused = press(key, paused, &mut sessions, &mut stdout, &mut spinner);
+                }
+                continue;
+            }
+        }
+        let line = match lines_rx.recv_timeout(SPINNER_TICK) {
+            Ok(Ok(line)) => line,
+            Ok(Err(_)) => continue,
+            Err(RecvTimeoutError::Timeout) => {
+                let waited = last_event.elapsed();
+                let current = sessions.current();
+                if let Some(idle) = cli.idle_warning.filter(|_| !cli.quiet && format.is_text()) {
+                    let due = idle.checked_mul(idle_warnings + 1).unwrap_or(Duration::MAX);
+                    if let Some(formatter) = current.filter(|formatter| waited >= due && !formatter.mid_line()) {
+                        if let Some(clear) = spinner.clear() {
+                            let _ = write!(stdout.out, "{}", clear);
+                        }
+                        let _ = writeln!(stdout, "{}", formatter.idle_warning(due));
+                        let _ = stdout.flush();
+                        idle_warnings += 1;
+                    }
+                }
+                if show_spinner && waited >= SPINNER_DELAY && !current.is_some_and(StreamFormatter::mid_line) {
+                    let tokens = current.and_then(StreamFormatter::token_total).map(|tokens| if ascii { to_ascii(&tokens) } else { tokens });
+                    let _ = write!(stdout.out, "{}", spinner.draw(current.and_then(StreamFormatter::last_tool), waited, tokens.as_deref()));
+                    let _ = stdout.flush();
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        last_event = Instant::now();
+        idle_warnings = 0;
 
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
+        // Each document is parsed once, for stdout and every file. Most
+        // lines are one whole document; only those that don't parse are
+        // split up or joined by the reassembler.
+        let parsed = reassembler.is_idle().then(|| sessions.options().parse(&line)).flatten();
+        let docs: Vec<(String, Option<StreamMessage>)> = match parsed {
+            Some(msg) => vec![(line, Some(msg))],
+            None => reassembler.push(&line).into_iter().map(|doc| (doc, None)).collect(),
         };
+        for (doc, msg) in docs {
+            let msg = msg.or_else(|| sessions.options().parse(&doc));
+            write_files(&mut files, &doc, msg.as_ref());
+            let formatter = sessions.route_message(msg.as_ref());
+            handle(formatter, &mut actions, &doc, msg, &mut stdout, &mut spinner);
+            over_budget |= cli.exit_over_budget && formatter.over_budget();
+        }
+        if over_budget {
+            break;
+        }
+    }
+    // The terminal goes back to normal before anything else is printed.
+    drop(hotkeys);
+    let signal = signal.load(Ordering::Relaxed);
+    // Stopped by a signal or by the output closing, what was gathered so far
+    // is summed up on stderr.
+    let stopped = signal != 0 || stdout.closed();
+    if let Some(rest) = reassembler.finish().filter(|_| !stopped) {
+        let msg = sessions.options().parse(&rest);
+        write_files(&mut files, &rest, msg.as_ref());
+        handle(sessions.route_message(msg.as_ref()), &mut actions, &rest, msg, &mut stdout, &mut spinner);
+    }
+    // Whatever was read is written out, however the stream ended.
+    for sink in files {
+        let path = sink.path().to_path_buf();
+        if let Err(err) = sink.finish() {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+        }
+    }
 
-        if let Some(output) = process_line(&line) {
-            let _ = writeln!(stdout, "{}", output);
-            let _ = stdout.flush();
+    if let Some(clear) = spinner.clear() {
+        let _ = write!(stdout.out, "{}", clear);
+    }
+    let mut parse_errors = 0;
+    let mut exit_code = 0;
+    let mut summaries = Vec::new();
+    for formatter in sessions.formatters_mut() {
+        if stopped {
+            summaries.push(formatter.summary_so_far());
+        } else if let Some(summary) = formatter.finish() {
+            let _ = writeln!(stdout, "{}", summary);
         }
+        parse_errors += formatter.parse_errors();
+        exit_code = exit_code.max(formatter.exit_code(cli.fail_on_error, usize::MAX));
     }
-}
+    if parse_errors > cli.max_parse_errors {
+        exit_code = 2;
+    }
+    let _ = stdout.flush();
+    // Closing its input lets the pager show the end; it is done once the
+    // user quits it.
+    drop(stdout);
+    if let Some(mut pager) = pager {
+        let _ = pager.wait();
+    }
+    for summary in summaries {
+        eprintln!("{}", summary);
+    }
+    // The last events are written to their hooks, and the last
+    // announcement, such as the result, said.
+    for running in actions.hooks.drain(..) {
+        running.finish(HOOK_WRITE_TIMEOUT);
+    }
+    let labels = std::mem::take(&mut actions.labels);
+    drop(actions);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    if cli.strict && parse_errors > 0 {
+        eprintln!("⚠️  {} unparsed", plural(parse_errors, &labels.line));
+    }
 
-    #[test]
-    fn test_text_message() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Hello world"}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("Hello world".to_string()));
+    // Both conflict with --by-session, so there is only one formatter.
+    if let Some(formatter) = sessions.formatters_mut().next() {
+        if let Some((path, markdown)) = cli.export_md.as_ref().zip(formatter.export_markdown()) {
+            if let Err(err) = std::fs::write(path, markdown) {
+                eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            }
+        }
+
+        if let Some(path) = &cli.metrics {
+            let metrics = Metrics::new(formatter.summary());
+            if let Err(err) = std::fs::write(path, metrics.to_json() + "\n") {
+                eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            }
+        }
     }
 
-    #[test]
-    fn test_read_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Read", "input": {"file_path": "/src/main.rs"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("📖 Read: /src/main.rs".to_string()));
+    // claude's own failure takes precedence over what was made of its
+    // output.
+    if let Some(mut child) = child {
+        if over_budget || stopped {
+            exec::terminate(&mut child);
+        }
+        match child.wait() {
+            Ok(status) if !status.success() && !over_budget && !stopped => process::exit(exec::exit_code(status)),
+            Ok(_) => {}
+            Err(err) => eprintln!("claude-stream-format: {}", err),
+        }
     }
 
-    #[test]
-    fn test_edit_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Edit", "input": {"file_path": "/src/lib.rs"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("✏️  Edit: /src/lib.rs".to_string()));
+    if signal != 0 {
+        // As a shell reports a process a signal ended.
+        process::exit(128 + signal as i32);
+    }
+    if over_budget {
+        process::exit(3);
     }
+    match exit_code {
+        0 => {}
+        2 => {
+            let lines = plural(parse_errors, &Noun::new("input line", "input lines"));
+            eprintln!("claude-stream-format: {} could not be parsed", lines);
+            process::exit(2);
+        }
+        code => process::exit(code),
+    }
+}
 
-    #[test]
-    fn test_write_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Write", "input": {"file_path": "/new_file.txt"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("📝 Write: /new_file.txt".to_string()));
+/// Runs the interactive view until the user quits, then exits.
+#[cfg(feature = "tui")]
+fn run_tui(lines: mpsc::Receiver<io::Result<String>>, options: Options, child: Option<Child>, fail_on: FailOn) -> ! {
+    if !io::stdout().is_terminal() {
+        eprintln!("claude-stream-format: --tui needs a terminal");
+        process::exit(2);
     }
+    let mut app = tui::App::new(options);
+    if let Err(err) = tui::run(lines, &mut app) {
+        eprintln!("claude-stream-format: {}", err);
+        process::exit(2);
+    }
+    // As without --tui, claude's own failure takes precedence. Quit while
+    // it is still running, it is stopped.
+    if let Some(mut child) = child {
+        let stopped = matches!(child.try_wait(), Ok(None));
+        if stopped {
+            exec::terminate(&mut child);
+        }
+        match child.wait() {
+            Ok(status) if !status.success() && !stopped => process::exit(exec::exit_code(status)),
+            Ok(_) => {}
+            Err(err) => eprintln!("claude-stream-format: {}", err),
+        }
+    }
+    process::exit(if app.failed() && fail_on != FailOn::Never { 1 } else { 0 });
+}
 
-    #[test]
-    fn test_bash_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Bash", "input": {"command": "ls -la"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("💻 Bash: ls -la".to_string()));
+#[cfg(not(feature = "tui"))]
+fn run_tui(_lines: mpsc::Receiver<io::Result<String>>, _options: Options, _child: Option<Child>, _fail_on: FailOn) -> ! {
+    eprintln!("claude-stream-format: --tui isn't available; rebuild with `--features tui`");
+    process::exit(2);
+}
+
+/// Where output goes: stdout or the pager, and a copy of all but the
+/// spinner to the `--log-file`.
+struct Sink {
+    out: Writer,
+    log: Option<LogFile>,
+    /// Whether whatever reads the output has gone, e.g. the pager quit.
+    closed: bool,
+}
+
+impl Sink {
+    fn closed(&self) -> bool {
+        self.closed || self.out.closed()
     }
 
-    #[test]
-    fn test_bash_tool_truncation() {
-        let long_cmd = "a".repeat(100);
-        let input = format!(r#"{{"type": "assistant", "message": {{"content": [{{"type": "tool_use", "name": "Bash", "input": {{"command": "{}"}}}}]}}}}"#, long_cmd);
-        let result = process_line(&input).unwrap();
-        assert!(result.len() < 100);
-        assert!(result.ends_with("..."));
+    fn check<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        if result.as_ref().is_err_and(|err| err.kind() == io::ErrorKind::BrokenPipe) {
+            self.closed = true;
+        }
+        result
     }
+}
 
-    #[test]
-    fn test_glob_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Glob", "input": {"pattern": "**/*.rs"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("🔍 Glob: **/*.rs".to_string()));
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(buf);
+        let written = self.check(written)?;
+        if let Some(log) = &mut self.log {
+            if let Err(err) = log.write_all(&buf[..written]) {
+                eprintln!("claude-stream-format: log file: {}", err);
+                self.log = None;
+            }
+        }
+        Ok(written)
     }
 
-    #[test]
-    fn test_grep_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Grep", "input": {"pattern": "fn main"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("🔍 Grep: fn main".to_string()));
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(log) = &mut self.log {
+            let _ = log.flush();
+        }
+        let flushed = self.out.flush();
+        self.check(flushed)
     }
+}
 
-    #[test]
-    fn test_todowrite_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "TodoWrite", "input": {"todos": []}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("📋 TodoWrite".to_string()));
+/// What to do besides formatting as each message is read.
+struct Actions {
+    /// Warn about unparseable input.
+    strict: bool,
+    /// Show a desktop notification for the result.
+    notify: bool,
+    /// Where to post a summary of the result.
+    webhook: Option<String>,
+    /// Copy the result text to the clipboard.
+    copy_result: bool,
+    /// The words for notification and webhook titles.
+    labels: Labels,
+    /// Whether warnings go in the output rather than to stderr.
+    warn_on_stdout: bool,
+    /// Events to ring the bell for.
+    bell: Vec<Alert>,
+    /// What to run instead of ringing the bell.
+    bell_command: Option<String>,
+    /// Says what `--announce` picks out.
+    speaker: Option<Speaker>,
+    /// `--on-event` commands whose event is still being written to them.
+    hooks: Vec<hooks::Running>,
+}
+
+/// Formats one JSON document (or unparseable piece of input), parsed as
+/// `msg`, and writes the output.
+fn handle(
+    formatter: &mut StreamFormatter,
+    actions: &mut Actions,
+    doc: &str,
+    msg: Option<StreamMessage>,
+    stdout: &mut Sink,
+    spinner: &mut Spinner,
+) {
+    let parse_errors = formatter.parse_errors();
+    let output = formatter.process_parsed(doc, msg);
+    if actions.strict && formatter.parse_errors() > parse_errors {
+        eprintln!("⚠️  unparsed: {}", truncate(doc, 60));
+    }
+    if let Some(result) = formatter.take_result() {
+        if actions.notify {
+            if let Err(err) = notify(&result.title(&actions.labels), &truncate(&result.text, 120)) {
+                eprintln!("claude-stream-format: notification failed: {}", err);
+            }
+        }
+        if let Some(url) = &actions.webhook {
+            if let Err(err) = webhook::post(url, &webhook::payload(&result, &actions.labels)) {
+                eprintln!("claude-stream-format: webhook failed: {}", err);
+            }
+        }
+        if actions.copy_result && !result.text.is_empty() {
+            if let Err(err) = clipboard::copy(&result.text) {
+                eprintln!("claude-stream-format: copying the result failed: {}", err);
+            }
+        }
+    }
+    actions.hooks.retain(|running| !running.is_written());
+    for (command, event) in formatter.take_hook_runs() {
+        match hooks::run(&command, &event) {
+            Ok(running) => actions.hooks.push(running),
+            Err(err) => eprintln!("claude-stream-format: {}: {}", command, err),
+        }
+    }
+    for announcement in formatter.take_announcements() {
+        if let Some(speaker) = &actions.speaker {
+            speaker.say(announcement);
+        }
+    }
+    // One bell per message, however many of the chosen events it raised.
+    if let Some(alert) = formatter.alerts().iter().find(|alert| actions.bell.contains(alert)) {
+        if let Err(err) = bell::ring(actions.bell_command.as_deref(), *alert) {
+            eprintln!("claude-stream-format: bell command failed: {}", err);
+        }
     }
 
-    #[test]
-    fn test_task_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Task", "input": {"description": "Search for files"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("🤖 Task: Search for files".to_string()));
+    if let Some(output) = output {
+        if let Some(clear) = spinner.clear() {
+            let _ = write!(stdout.out, "{}", clear);
+        }
+        let _ = match output {
+            Output::Line(output) => writeln!(stdout, "{}", output),
+            Output::Delta(text) => write!(stdout, "{}", text),
+        };
+        if formatter.alerts().iter().any(|alert| matches!(alert, Alert::OnResult | Alert::OnError)) {
+            stdout.out.urgent();
+        }
+    }
+    let routed = formatter.take_stderr();
+    if !routed.is_empty() {
+        if let Some(clear) = spinner.clear() {
+            let _ = write!(stdout.out, "{}", clear);
+        }
+        // Keep the two in order where they share a terminal.
+        let _ = stdout.flush();
+        let mut stderr = io::stderr().lock();
+        for output in routed {
+            let _ = match output {
+                Output::Line(output) => writeln!(stderr, "{}", output),
+                Output::Delta(text) => write!(stderr, "{}", text),
+            };
+        }
     }
+    warn_budget(formatter, actions, stdout);
+}
 
-    #[test]
-    fn test_other_tool() {
-        let input = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "WebFetch", "input": {"url": "https://example.com"}}]}}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("🔧 WebFetch".to_string()));
+/// Copies one raw input line to the --tee file, straight through so the
+/// copy is complete even if the stream is interrupted, giving up on it if
+/// it can't be written.
+fn tee_line(tee: &mut Option<(PathBuf, File)>, line: &[u8]) {
+    if let Some((path, file)) = tee {
+        if let Err(err) = file.write_all(line).and_then(|()| file.write_all(b"\n")) {
+            eprintln!("claude-stream-format: {}: {}", path.display(), err);
+            *tee = None;
+        }
     }
+}
 
-    #[test]
-    fn test_result_message() {
-        let input = r#"{"type": "result", "result": "Task completed successfully."}"#;
-        let result = process_line(input);
-        assert_eq!(result, Some("✅ Done: Task completed successfully.".to_string()));
+/// Formats one JSON document for each `--output` file, giving up on any
+/// that can't be written.
+fn write_files(files: &mut Vec<FileSink>, doc: &str, msg: Option<&StreamMessage>) {
+    files.retain_mut(|sink| match sink.process(doc, msg) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("claude-stream-format: {}: {}", sink.path().display(), err);
+            false
+        }
+    });
+}
+
+/// Acts on a hotkey, returning whether output is now paused.
+fn press(key: Key, paused: bool, sessions: &mut Sessions, stdout: &mut Sink, spinner: &mut Spinner) -> bool {
+    let labels = sessions.options().config.labels.clone();
+    let on_off = |on| if on { &labels.on } else { &labels.off };
+    let note = match key {
+        Key::ToolResults => {
+            sessions.update_options(|options| options.show_tool_results = !options.show_tool_results);
+            format!("{} {}", labels.tool_results, on_off(sessions.options().show_tool_results))
+        }
+        Key::Thinking => {
+            sessions.update_options(|options| options.show_thinking = !options.show_thinking);
+            format!("{} {}", labels.thinking, on_off(sessions.options().show_thinking))
+        }
+        Key::Pause if paused => labels.resumed.clone(),
+        Key::Pause => labels.paused.clone(),
+        Key::Summary => String::new(),
+    };
+    let paused = paused != (key == Key::Pause);
+    // Streamed text is left to finish its line; the key still takes effect.
+    let Some(current) = sessions.current().filter(|formatter| !formatter.mid_line()) else {
+        return paused;
+    };
+    let note = match key {
+        Key::Summary => current.summary_so_far(),
+        _ => sessions.options().theme.comment.paint(&note),
+    };
+    if let Some(clear) = spinner.clear() {
+        let _ = write!(stdout.out, "{}", clear);
     }
+    let _ = writeln!(stdout.out, "{}", note);
+    let _ = stdout.out.flush();
+    paused
+}
 
-    #[test]
-    fn test_result_truncation() {
-        let long_result = "a".repeat(100);
-        let input = format!(r#"{{"type": "result", "result": "{}"}}"#, long_result);
-        let result = process_line(&input).unwrap();
-        assert!(result.len() < 100);
-        assert!(result.ends_with("..."));
+/// Prints the warning for passing `--max-cost`, if `formatter` has one.
+fn warn_budget(formatter: &mut StreamFormatter, actions: &Actions, stdout: &mut impl Write) {
+    // Held until streamed text finishes its line.
+    if actions.warn_on_stdout && formatter.mid_line() {
+        return;
+    }
+    let Some(warning) = formatter.take_budget_warning() else {
+        return;
+    };
+    if actions.warn_on_stdout {
+        let _ = writeln!(stdout, "{}", warning);
+    } else {
+        eprintln!("{}", warning);
     }
+}
 
-    #[test]
-    fn test_malformed_json() {
-        let input = "this is not valid json";
-        let result = process_line(input);
-        assert_eq!(result, None);
+/// Parses a `--template` argument.
+fn parse_template(arg: &str) -> Result<(String, Template), String> {
+    let (name, template) = arg.split_once('=').ok_or("expected NAME=TEMPLATE")?;
+    let template = Template::parse(template).map_err(|err| err.to_string())?;
+    Ok((name.trim().to_string(), template))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], env: &[(&str, &str)]) -> Result<Cli, clap::Error> {
+        let args = std::iter::once("claude-stream-format").chain(args.iter().copied()).map(OsString::from).collect();
+        let env = |var: &str| env.iter().find(|(name, _)| *name == var).map(|(_, value)| OsString::from(value));
+        Cli::from_arg_matches(&cli_matches(args, env)?)
     }
 
     #[test]
-    fn test_unknown_message_type() {
-        let input = r#"{"type": "unknown", "data": {}}"#;
-        let result = process_line(input);
-        assert_eq!(result, None);
+    fn test_env_defaults() {
+        let cli = parse(&[], &[("CLAUDE_STREAM_FORMAT_COMPACT", "yes"), ("CLAUDE_STREAM_FORMAT_MAX_COST", "2.5")]).unwrap();
+        assert!(cli.compact);
+        assert_eq!(cli.max_cost, Some(2.5));
+        assert!(!parse(&[], &[("CLAUDE_STREAM_FORMAT_COMPACT", "0")]).unwrap().compact);
+        assert!(parse(&[], &[("CLAUDE_STREAM_FORMAT_COMPACT", "maybe")]).is_err());
+        // The command line takes precedence, even over a conflicting option.
+        assert_eq!(parse(&["--max-cost", "1"], &[("CLAUDE_STREAM_FORMAT_MAX_COST", "2.5")]).unwrap().max_cost, Some(1.0));
+        for compact in ["1", "0"] {
+            let cli = parse(&["--group", "3"], &[("CLAUDE_STREAM_FORMAT_COMPACT", compact)]).unwrap();
+            assert!(!cli.compact);
+            assert_eq!(cli.group, Some(3));
+        }
+        assert!(parse(&["--group", "3", "--compact"], &[]).is_err());
+        // Options still come before a subcommand.
+        assert!(parse(&["check", "run.jsonl"], &[("CLAUDE_STREAM_FORMAT_COLOR", "never")]).is_ok());
     }
 
     #[test]
-    fn test_truncate_function() {
-        assert_eq!(truncate("short", 10), "short");
-        assert_eq!(truncate("this is a long string", 10), "this is...");
-        assert_eq!(truncate("exactly10!", 10), "exactly10!");
+    fn test_columns() {
+        let format = |args: &[&str]| parse(args, &[]).unwrap().stdout_format();
+        assert_eq!(format(&[]), Some(OutputFormat::Text));
+        assert_eq!(format(&["--columns"]), Some(OutputFormat::Columns));
+        assert_eq!(format(&["--columns", "--output", "html=run.html"]), Some(OutputFormat::Columns));
+        assert_eq!(format(&["--columns", "--output", "json"]), None);
     }
 }
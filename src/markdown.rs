@@ -0,0 +1,190 @@
+//! Terminal rendering of the Markdown found in assistant text.
+//!
+//! Block structure (bullets, code fences, quotes) is always rendered. Inline
+//! markers such as `**bold**` are only replaced when the theme has a style
+//! for them, so uncolored output keeps its Markdown emphasis.
+
+use crate::highlight::highlight;
+use crate::style::{Style, Theme};
+
+/// Renders Markdown `text` for the terminal.
+pub fn render(text: &str, theme: &Theme) -> String {
+    let mut out = Vec::new();
+    // The open fence's marker, a run of ` or ~, language and code lines.
+    let mut fence: Option<(&str, &str, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some((marker, lang, code)) = &mut fence {
+            // Closed by a run of the same character at least as long, with
+            // nothing after it.
+            let close = trimmed.trim_end();
+            let run = close.trim_start_matches(marker.chars().next().unwrap_or('`'));
+            if run.is_empty() && close.len() >= marker.len() {
+                out.push(render_code(&code.join("\n"), lang, theme));
+                fence = None;
+            } else {
+                code.push(line);
+            }
+            continue;
+        }
+
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            let len = trimmed.len() - trimmed.trim_start_matches(marker.chars().next().unwrap_or('`')).len();
+            let lang = trimmed[len..].trim();
+            fence = Some((&trimmed[..len], lang, Vec::new()));
+            continue;
+        }
+        out.push(render_line(line, theme));
+    }
+
+    // An unterminated fence still renders as code.
+    if let Some((_, lang, code)) = fence {
+        out.push(render_code(&code.join("\n"), lang, theme));
+    }
+    out.join("\n")
+}
+
+fn render_code(code: &str, lang: &str, theme: &Theme) -> String {
+    let gutter = theme.comment.paint("│ ");
+    highlight(code, lang, theme)
+        .lines()
+        .map(|line| format!("  {}{}", gutter, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_line(line: &str, theme: &Theme) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+        if theme.heading.is_plain() {
+            return line.to_string();
+        }
+        return theme.heading.paint(trimmed[hashes..].trim());
+    }
+
+    let first = trimmed.chars().next();
+    if trimmed.len() >= 3
+        && matches!(first, Some('-' | '*' | '_'))
+        && trimmed.chars().all(|ch| Some(ch) == first)
+    {
+        return theme.comment.paint(&"─".repeat(40));
+    }
+
+    if let Some(rest) = trimmed.strip_prefix('>') {
+        return format!("{}{}", theme.comment.paint("│ "), inline(rest.trim_start(), theme));
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(bullet) {
+            return format!("{}• {}", indent, inline(rest, theme));
+        }
+    }
+
+    format!("{}{}", indent, inline(trimmed, theme))
+}
+
+/// Renders inline code spans, bold and italic text.
+fn inline(text: &str, theme: &Theme) -> String {
+    let mut out = String::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let span = if c == '`' {
+            closed_span(rest, "`", theme.code)
+        } else if rest.starts_with("**") || rest.starts_with("__") {
+            closed_span(rest, &rest[..2], theme.bold)
+        } else if c == '*' || (c == '_' && !plain.ends_with(|p: char| p.is_alphanumeric())) {
+            closed_span(rest, &rest[..1], theme.italic)
+        } else {
+            None
+        };
+
+        match span {
+            Some((rendered, len)) => {
+                out.push_str(&theme.text.paint(&plain));
+                plain.clear();
+                out.push_str(&rendered);
+                rest = &rest[len..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out.push_str(&theme.text.paint(&plain));
+    out
+}
+
+/// Renders a span opened by `marker` at the start of `text`, returning the
+/// rendered span and the number of bytes consumed, if the span is closed.
+fn closed_span(text: &str, marker: &str, style: Style) -> Option<(String, usize)> {
+    let inner_start = marker.len();
+    let inner = &text[inner_start..];
+    if inner.starts_with(' ') || inner.is_empty() {
+        return None;
+    }
+    let end = inner.find(marker)?;
+    if end == 0 {
+        return None;
+    }
+    let len = inner_start + end + marker.len();
+    if style.is_plain() {
+        Some((text[..len].to_string(), len))
+    } else {
+        Some((style.paint(&inner[..end]), len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::ThemeName;
+
+    #[test]
+    fn test_plain_structure() {
+        let theme = Theme::default();
+        let text = "# Title\n\n- one\n  * two\n> quoted **bold**\n```rust\nlet x = 1;\n```";
+        assert_eq!(
+            render(text, &theme),
+            "# Title\n\n• one\n  • two\n│ quoted **bold**\n  │ let x = 1;"
+        );
+    }
+
+    #[test]
+    fn test_styled_inline() {
+        let theme = Theme::new(ThemeName::Dark);
+        let out = render("Use `cargo test` to **verify** it", &theme);
+        assert!(out.contains(&theme.code.paint("cargo test")));
+        assert!(out.contains(&theme.bold.paint("verify")));
+        assert!(!out.contains("**"));
+    }
+
+    #[test]
+    fn test_styled_heading() {
+        let theme = Theme::new(ThemeName::Dark);
+        assert_eq!(render("## Plan", &theme), theme.heading.paint("Plan"));
+    }
+
+    #[test]
+    fn test_snake_case_is_not_italic() {
+        let theme = Theme::new(ThemeName::Dark);
+        assert_eq!(render("snake_case_name", &theme), theme.text.paint("snake_case_name"));
+    }
+
+    #[test]
+    fn test_nested_fence() {
+        let text = "````md\n```rust\nlet x = 1;\n```\n````\n~~~\n```\n~~~";
+        assert_eq!(render(text, &Theme::default()), "  │ ```rust\n  │ let x = 1;\n  │ ```\n  │ ```");
+    }
+
+    #[test]
+    fn test_unterminated_fence() {
+        assert_eq!(render("```\ncode", &Theme::default()), "  │ code");
+    }
+}
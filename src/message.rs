@@ -0,0 +1,251 @@
+//! The stream-json message schema emitted by `claude --output-format stream-json`.
+
+use crate::config::Labels;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// One line of the stream. Its strings are owned rather than borrowed
+/// from the line: the long ones, text and tool output, are full of escapes
+/// that would need copying anyway, and parts are kept across lines.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StreamMessage {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub subtype: Option<String>,
+    pub message: Option<Message>,
+    pub result: Option<String>,
+    #[serde(default)]
+    pub is_error: bool,
+    pub event: Option<StreamEvent>,
+    pub total_cost_usd: Option<f64>,
+    pub duration_ms: Option<u64>,
+    pub num_turns: Option<u32>,
+    pub usage: Option<Usage>,
+    pub session_id: Option<String>,
+    /// Set on messages from a sub-agent to the id of the Task tool_use that
+    /// spawned it.
+    pub parent_tool_use_id: Option<String>,
+    pub model: Option<String>,
+    pub cwd: Option<String>,
+    pub tools: Option<Vec<String>>,
+    /// The body of a `control_request`, such as a permission prompt.
+    pub request: Option<ControlRequest>,
+    /// The body of a `control_response` answering a `control_request`.
+    pub response: Option<ControlResponse>,
+    pub request_id: Option<String>,
+}
+
+/// A request from Claude Code to its host, e.g. asking whether a tool may
+/// run (subtype `can_use_tool`).
+#[derive(Clone, Debug, Deserialize)]
+pub struct ControlRequest {
+    pub subtype: Option<String>,
+    pub tool_name: Option<String>,
+    #[serde(default)]
+    pub input: serde_json::Value,
+}
+
+/// The host's answer to a [`ControlRequest`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct ControlResponse {
+    pub request_id: Option<String>,
+    /// For permission prompts, `{"behavior": "allow" | "deny", "message": ...}`.
+    pub response: Option<serde_json::Value>,
+}
+
+impl ControlResponse {
+    /// Whether a permission prompt was allowed, with the reason given for a
+    /// denial. `None` if this doesn't answer a permission prompt.
+    pub fn permission(&self) -> Option<(bool, Option<&str>)> {
+        let response = self.response.as_ref()?;
+        let allowed = match response.get("behavior")?.as_str()? {
+            "allow" => true,
+            "deny" => false,
+            _ => return None,
+        };
+        Some((allowed, response.get("message").and_then(|m| m.as_str())))
+    }
+}
+
+/// Token counts reported by the API.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    /// All input tokens, including those written to or read from the cache.
+    pub fn total_input(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    /// Adds `other`'s counts to these.
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+impl StreamMessage {
+    /// Whether this is a result reporting a failed run, either flagged with
+    /// `is_error` or with an `error_*` subtype such as `error_max_turns`.
+    pub fn is_error_result(&self) -> bool {
+        self.msg_type == "result"
+            && (self.is_error || self.subtype.as_deref().is_some_and(|s| s.starts_with("error")))
+    }
+
+    /// The result text, or a description of the error subtype when an error
+    /// result carries no text.
+    pub fn result_text(&self, labels: &Labels) -> Option<String> {
+        if let Some(result) = &self.result {
+            return Some(result.clone());
+        }
+        match self.subtype.as_deref()? {
+            "error_max_turns" => Some(labels.max_turns_reached.clone()),
+            "error_during_execution" => Some(labels.error_during_execution.clone()),
+            other if other.starts_with("error") => Some(other.replace('_', " ")),
+            _ => None,
+        }
+    }
+}
+
+/// The `message` payload of an `assistant` or `user` line.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Message {
+    pub id: Option<String>,
+    pub model: Option<String>,
+    /// Tokens used by this API call, on assistant messages.
+    pub usage: Option<Usage>,
+    /// Why the model stopped, such as `tool_use` or `max_tokens`.
+    pub stop_reason: Option<String>,
+    #[serde(deserialize_with = "content_blocks")]
+    pub content: Vec<ContentBlock>,
+}
+
+/// Message content, which for a user's prompt may be a plain string rather
+/// than content blocks.
+fn content_blocks<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<ContentBlock>, D::Error> {
+    Ok(match ToolResultContent::deserialize(deserializer)? {
+        ToolResultContent::Text(text) => vec![ContentBlock::Text { text }],
+        ToolResultContent::Blocks(blocks) => blocks,
+    })
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum ContentBlock {
+    #[serde(rename = "text")]
+    Text { text: String },
+    /// Extended thinking, sent before the text it leads to.
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String },
+    #[serde(rename = "tool_result")]
+    ToolResult {
+        #[serde(default)]
+        tool_use_id: String,
+        content: Option<ToolResultContent>,
+        #[serde(default)]
+        is_error: bool,
+    },
+    /// An image, in a prompt or a tool's result, such as a screenshot.
+    #[serde(rename = "image")]
+    Image {
+        #[serde(default)]
+        source: ImageSource,
+    },
+    #[serde(rename = "tool_use")]
+    ToolUse {
+        #[serde(default)]
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Where an image block's data is: inline as base64 (type `base64`), or at
+/// a `url`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ImageSource {
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    /// E.g. `image/png`.
+    pub media_type: Option<String>,
+    pub data: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ToolResultContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl ToolResultContent {
+    /// The images in the result.
+    pub fn images(&self) -> Vec<&ImageSource> {
+        match self {
+            ToolResultContent::Text(_) => Vec::new(),
+            ToolResultContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Image { source } => Some(source),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The text of the result, with text blocks joined by newlines.
+    pub fn text(&self) -> String {
+        match self {
+            ToolResultContent::Text(text) => text.clone(),
+            ToolResultContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// A raw API streaming event, sent with `--include-partial-messages`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum StreamEvent {
+    #[serde(rename = "message_start")]
+    MessageStart { message: StartedMessage },
+    #[serde(rename = "content_block_delta")]
+    ContentBlockDelta { delta: Delta },
+    #[serde(rename = "content_block_stop")]
+    ContentBlockStop,
+    #[serde(other)]
+    Other,
+}
+
+/// The message a `message_start` event begins, of which only the id and
+/// model are needed: its content arrives in later events.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StartedMessage {
+    pub id: Option<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum Delta {
+    #[serde(rename = "text_delta")]
+    TextDelta { text: String },
+    #[serde(other)]
+    Other,
+}
@@ -0,0 +1,97 @@
+//! The JSON document written by `--metrics`, for aggregating runs in
+//! dashboards.
+
+use crate::message::Usage;
+use crate::summary::Summary;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize)]
+pub struct Metrics {
+    pub assistant_messages: usize,
+    pub tool_calls: usize,
+    pub tools: BTreeMap<String, ToolMetrics>,
+    pub files: Vec<String>,
+    /// Time between the first and last messages of the stream.
+    pub duration_ms: u64,
+    pub num_turns: u32,
+    pub usage: Usage,
+    pub cost_usd: f64,
+    pub is_error: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolMetrics {
+    pub calls: usize,
+    /// Calls whose result arrived, which the timings cover.
+    pub completed: usize,
+    pub total_ms: u64,
+    pub mean_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Metrics {
+    pub fn new(summary: &Summary) -> Self {
+        let tools = summary
+            .tool_counts
+            .iter()
+            .map(|(name, &calls)| {
+                let durations = summary.tool_durations.get(name).map(Vec::as_slice).unwrap_or_default();
+                let total_ms: u64 = durations.iter().map(|d| d.as_millis() as u64).sum();
+                let max_ms = durations.iter().map(|d| d.as_millis() as u64).max().unwrap_or(0);
+                let metrics = ToolMetrics {
+                    calls,
+                    completed: durations.len(),
+                    total_ms,
+                    mean_ms: total_ms.checked_div(durations.len() as u64).unwrap_or(0),
+                    max_ms,
+                };
+                (name.clone(), metrics)
+            })
+            .collect();
+
+        Metrics {
+            assistant_messages: summary.assistant_messages,
+            tool_calls: summary.tool_calls(),
+            tools,
+            files: summary.files.iter().cloned().collect(),
+            duration_ms: summary.time_span().as_millis() as u64,
+            num_turns: summary.num_turns,
+            usage: summary.usage,
+            cost_usd: summary.cost_usd,
+            is_error: summary.is_error,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tool_metrics() {
+        let mut summary = Summary::default();
+        summary.tool_counts.insert("Bash".to_string(), 3);
+        summary.tool_counts.insert("Read".to_string(), 1);
+        summary.tool_durations.insert(
+            "Bash".to_string(),
+            vec![Duration::from_millis(100), Duration::from_millis(300)],
+        );
+        summary.files.insert("/a.rs".to_string());
+
+        let json: serde_json::Value = serde_json::from_str(&Metrics::new(&summary).to_json()).unwrap();
+        assert_eq!(json["tool_calls"], 4);
+        assert_eq!(json["tools"]["Bash"]["completed"], 2);
+        assert_eq!(json["tools"]["Bash"]["total_ms"], 400);
+        assert_eq!(json["tools"]["Bash"]["mean_ms"], 200);
+        assert_eq!(json["tools"]["Bash"]["max_ms"], 300);
+        assert_eq!(json["tools"]["Read"]["mean_ms"], 0);
+        assert_eq!(json["files"][0], "/a.rs");
+        assert_eq!(json["usage"]["output_tokens"], 0);
+    }
+}
@@ -0,0 +1,55 @@
+//! Desktop notifications, sent through the platform's own command-line
+//! tool so no notification library has to be linked in.
+
+use std::io;
+use std::process::{Command, Stdio};
+
+/// Shows a desktop notification. Does nothing on platforms without a
+/// supported notifier.
+pub fn notify(title: &str, body: &str) -> io::Result<()> {
+    let Some(mut command) = command(title, body) else {
+        return Ok(());
+    };
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Option<Command> {
+    let script = format!(
+        "display notification {} with title {}",
+        applescript_string(body),
+        applescript_string(title)
+    );
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(script);
+    Some(command)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command(title: &str, body: &str) -> Option<Command> {
+    let mut command = Command::new("notify-send");
+    command.arg("--app-name=claude-stream-format").arg(title).arg(body);
+    Some(command)
+}
+
+#[cfg(not(unix))]
+fn command(_title: &str, _body: &str) -> Option<Command> {
+    None
+}
+
+/// Quotes `s` as an AppleScript string literal.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applescript_string() {
+        assert_eq!(applescript_string(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+    }
+}
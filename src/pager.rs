@@ -0,0 +1,57 @@
+//! `--pager`: output piped through a pager on a terminal, as `git log`
+//! does, so a long session can be scrolled and searched as it streams.
+
+use std::io;
+use std::process::{Child, Command, Stdio};
+
+/// The pager to use: `$PAGER`, or `less`. `None` if `$PAGER` is empty or
+/// `cat`, which mean not to page.
+pub fn command(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let pager = var("PAGER").unwrap_or_else(|| "less".to_string());
+    let pager = pager.trim();
+    (!pager.is_empty() && pager != "cat").then(|| pager.to_string())
+}
+
+/// The pager for a run on a terminal: with `--pager`, or whenever `$PAGER`
+/// is set, as setting it asks for paging; never with `--no-pager`.
+pub fn choose(pager: bool, no_pager: bool, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if no_pager || !(pager || var("PAGER").is_some()) {
+        return None;
+    }
+    command(var)
+}
+
+/// Starts `command` with `sh -c`, reading from a pipe. As with git, `less`
+/// is told to pass colors through (`R`), to quit if everything fits on
+/// one screen (`F`) and not to clear it on exit (`X`), unless `$LESS` says
+/// otherwise.
+pub fn spawn(command: &str) -> io::Result<Child> {
+    let mut pager = Command::new("sh");
+    pager.arg("-c").arg(command).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        pager.env("LESS", "FRX");
+    }
+    pager.spawn()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(command(|_| None).as_deref(), Some("less"));
+        assert_eq!(command(|_| Some("most -s".to_string())).as_deref(), Some("most -s"));
+        assert_eq!(command(|_| Some("cat".to_string())), None);
+        assert_eq!(command(|_| Some(String::new())), None);
+    }
+
+    #[test]
+    fn test_choose() {
+        let most = |_: &str| Some("most".to_string());
+        assert_eq!(choose(false, false, most).as_deref(), Some("most"));
+        assert_eq!(choose(false, true, most), None);
+        assert_eq!(choose(true, false, |_| None).as_deref(), Some("less"));
+        assert_eq!(choose(false, false, |_| None), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! Making the file paths in tool lines shorter to read.
+
+use crate::format::display_width;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Tool input fields holding a file or directory path.
+const PATH_FIELDS: [&str; 3] = ["file_path", "notebook_path", "path"];
+
+/// Tool input fields that may start with a path, such as Glob patterns.
+const PATTERN_FIELDS: [&str; 1] = ["pattern"];
+
+/// `path` relative to `cwd`, or `path` itself if it is outside `cwd`.
+pub fn relative(path: &str, cwd: &str) -> String {
+    let cwd = cwd.trim_end_matches('/');
+    if cwd.is_empty() {
+        return path.to_string();
+    }
+    if path == cwd {
+        return ".".to_string();
+    }
+    match path.strip_prefix(cwd).and_then(|rest| rest.strip_prefix('/')) {
+        Some(rest) if !rest.is_empty() => rest.to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Shortens `path` to at most `max_width` columns by replacing directories
+/// in the middle with `…`, keeping the first directory and as much of the
+/// end as fits: `src/…/parser/mod.rs`.
+pub fn shorten(path: &str, max_width: usize) -> String {
+    if max_width == 0 || display_width(path) <= max_width {
+        return path.to_string();
+    }
+
+    let parts: Vec<&str> = path.split('/').collect();
+    if parts.len() > 2 {
+        let head = if parts[0].is_empty() { format!("/{}", parts[1]) } else { parts[0].to_string() };
+        let skip = if parts[0].is_empty() { 2 } else { 1 };
+        let mut tail = String::new();
+        for part in parts[skip..].iter().rev() {
+            let candidate = if tail.is_empty() { part.to_string() } else { format!("{}/{}", part, tail) };
+            if display_width(&head) + display_width(&candidate) + 3 > max_width {
+                break;
+            }
+            tail = candidate;
+        }
+        if !tail.is_empty() {
+            return format!("{}/…/{}", head, tail);
+        }
+    }
+
+    // Not even the file name fits beside the first directory; cut the
+    // middle of the string instead, by columns so wide characters count
+    // for two.
+    let keep = max_width.saturating_sub(1);
+    let end = keep / 2;
+    let start = keep - end;
+    let mut tail = fit(path.graphemes(true).rev(), end);
+    tail.reverse();
+    format!("{}…{}", fit(path.graphemes(true), start).concat(), tail.concat())
+}
+
+/// As many of `graphemes` as fit in `width` columns.
+fn fit<'a>(graphemes: impl Iterator<Item = &'a str>, width: usize) -> Vec<&'a str> {
+    let mut used = 0;
+    graphemes
+        .take_while(|grapheme| {
+            used += display_width(grapheme);
+            used <= width
+        })
+        .collect()
+}
+
+/// How paths in tool input should be shown.
+#[derive(Clone, Debug, Default)]
+pub struct PathDisplay {
+    /// Show paths relative to this directory.
+    pub cwd: Option<String>,
+    /// Shorten paths longer than this many columns; 0 leaves them whole.
+    pub max_width: usize,
+}
+
+impl PathDisplay {
+    pub fn is_identity(&self) -> bool {
+        self.cwd.is_none() && self.max_width == 0
+    }
+
+    /// `path` as it should be shown.
+    pub fn path(&self, path: &str) -> String {
+        match &self.cwd {
+            Some(cwd) => shorten(&relative(path, cwd), self.max_width),
+            None => shorten(path, self.max_width),
+        }
+    }
+
+    /// A copy of `input` with its paths rewritten for display.
+    pub fn apply(&self, input: &serde_json::Value) -> serde_json::Value {
+        let mut input = input.clone();
+        let Some(fields) = input.as_object_mut() else {
+            return input;
+        };
+        for (key, value) in fields.iter_mut() {
+            let Some(s) = value.as_str() else {
+                continue;
+            };
+            let is_path = PATH_FIELDS.contains(&key.as_str());
+            if !is_path && !PATTERN_FIELDS.contains(&key.as_str()) {
+                continue;
+            }
+            let mut display = match &self.cwd {
+                Some(cwd) => relative(s, cwd),
+                None => s.to_string(),
+            };
+            if is_path {
+                display = shorten(&display, self.max_width);
+            }
+            *value = serde_json::Value::String(display);
+        }
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative() {
+        assert_eq!(relative("/work/app/src/main.rs", "/work/app"), "src/main.rs");
+        assert_eq!(relative("/work/app/src/main.rs", "/work/app/"), "src/main.rs");
+        assert_eq!(relative("/work/app", "/work/app"), ".");
+        assert_eq!(relative("/work/application/x.rs", "/work/app"), "/work/application/x.rs");
+        assert_eq!(relative("/work/app/**/*.rs", "/work/app"), "**/*.rs");
+    }
+
+    #[test]
+    fn test_shorten() {
+        assert_eq!(shorten("src/a.rs", 20), "src/a.rs");
+        assert_eq!(shorten("src/formatter/parser/nested/mod.rs", 24), "src/…/nested/mod.rs");
+        assert_eq!(shorten("/home/user/project/src/lib.rs", 20), "/home/…/src/lib.rs");
+        assert_eq!(shorten("a_really_long_file_name.rs", 10), "a_rea…e.rs");
+        // Each CJK character is two columns wide.
+        assert_eq!(shorten("日本語のファイル名.rs", 10), "日本….rs");
+        assert!(display_width(&shorten("日本語のファイル名.rs", 9)) <= 9);
+    }
+
+    #[test]
+    fn test_apply() {
+        let display = PathDisplay {
+            cwd: Some("/work".to_string()),
+            max_width: 0,
+        };
+        let input = serde_json::json!({"file_path": "/work/src/a.rs", "command": "cat /work/x", "pattern": "/work/**/*.rs"});
+        let shown = display.apply(&input);
+        assert_eq!(shown["file_path"], "src/a.rs");
+        assert_eq!(shown["command"], "cat /work/x");
+        assert_eq!(shown["pattern"], "**/*.rs");
+    }
+}
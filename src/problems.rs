@@ -0,0 +1,136 @@
+//! `--output problems`: one `path:line:col: info: message` record per file
+//! change, the format compilers print and editors' problem matchers read,
+//! so an editor can list the places the agent modified.
+
+use crate::config::Noun;
+use crate::format::plural;
+use crate::message::{ContentBlock, StreamMessage};
+use serde_json::Value;
+
+/// The files whose contents [`records`] needs to place the edits in `msg`.
+pub fn edited_files(msg: &StreamMessage) -> Vec<&str> {
+    if msg.msg_type != "assistant" {
+        return Vec::new();
+    }
+    msg.message
+        .iter()
+        .flat_map(|m| &m.content)
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { name, input, .. } if matches!(name.as_str(), "Edit" | "MultiEdit") => {
+                input.get("file_path").and_then(Value::as_str)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The records for the Edit, MultiEdit, Write and NotebookEdit calls in
+/// `msg`. Each edit is placed by finding its text in the file's contents,
+/// given by `contents`: the old text if the edit hasn't been made yet,
+/// otherwise the new text. An edit that can't be found is placed at the
+/// top.
+pub fn records<'a>(msg: &StreamMessage, contents: impl Fn(&str) -> Option<&'a str>) -> Vec<String> {
+    if msg.msg_type != "assistant" {
+        return Vec::new();
+    }
+    let mut records = Vec::new();
+    for block in msg.message.iter().flat_map(|m| &m.content) {
+        let ContentBlock::ToolUse { name, input, .. } = block else {
+            continue;
+        };
+        let field = |key: &str| input.get(key).and_then(Value::as_str);
+        let Some(path) = field("file_path").or_else(|| field("notebook_path")) else {
+            continue;
+        };
+        let edits: Vec<(&str, &str)> = match name.as_str() {
+            "Edit" => vec![(field("old_string").unwrap_or(""), field("new_string").unwrap_or(""))],
+            "MultiEdit" => input
+                .get("edits")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|edit| {
+                    let text = |key: &str| edit.get(key).and_then(Value::as_str).unwrap_or("");
+                    (text("old_string"), text("new_string"))
+                })
+                .collect(),
+            "Write" => {
+                let lines = field("content").map_or(0, |content| content.lines().count());
+                records.push(record(path, (1, 1), &format!("Write: wrote {}", plural(lines, &line()))));
+                continue;
+            }
+            "NotebookEdit" => {
+                let mode = field("edit_mode").unwrap_or("replace");
+                let cell = field("cell_id").map(|id| format!(" cell {}", id)).unwrap_or_default();
+                records.push(record(path, (1, 1), &format!("NotebookEdit: {}{}", mode, cell)));
+                continue;
+            }
+            _ => continue,
+        };
+        let contents = contents(path);
+        for (old, new) in edits {
+            let location = contents.and_then(|contents| locate(contents, old).or_else(|| locate(contents, new)));
+            let message = format!("{}: replaced {} with {}", name, plural(old.lines().count(), &line()), new.lines().count());
+            records.push(record(path, location.unwrap_or((1, 1)), &message));
+        }
+    }
+    records
+}
+
+fn record(path: &str, (line, column): (usize, usize), message: &str) -> String {
+    format!("{}:{}:{}: info: {}", path, line, column, message)
+}
+
+/// The 1-based line and column at which `needle` first appears.
+fn locate(contents: &str, needle: &str) -> Option<(usize, usize)> {
+    if needle.is_empty() {
+        return None;
+    }
+    let start = contents.find(needle)?;
+    let before = &contents[..start];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    Some((line, column))
+}
+
+/// Records are read by tools rather than people, so aren't translated.
+fn line() -> Noun {
+    Noun::new("line", "lines")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records_for(line: &str, contents: &str) -> Vec<String> {
+        records(&serde_json::from_str(line).unwrap(), |_| Some(contents))
+    }
+
+    #[test]
+    fn test_edit_located() {
+        let contents = "fn a() {}\n\nfn b() {\n    old();\n}\n";
+        let edit = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Edit", "input": {"file_path": "/src/lib.rs", "old_string": "old();", "new_string": "new();\n    newer();"}}]}}"#;
+        assert_eq!(records_for(edit, contents), ["/src/lib.rs:4:5: info: Edit: replaced 1 line with 2"]);
+        // Once the edit is made, the new text is found instead.
+        let edited = "fn a() {}\n\nfn b() {\n    new();\n    newer();\n}\n";
+        assert_eq!(records_for(edit, edited), ["/src/lib.rs:4:5: info: Edit: replaced 1 line with 2"]);
+        assert_eq!(records_for(edit, ""), ["/src/lib.rs:1:1: info: Edit: replaced 1 line with 2"]);
+    }
+
+    #[test]
+    fn test_other_tools() {
+        let multi = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "MultiEdit", "input": {"file_path": "a.rs", "edits": [{"old_string": "x", "new_string": "y"}, {"old_string": "b", "new_string": "c"}]}}]}}"#;
+        assert_eq!(
+            records_for(multi, "a\nb\nx"),
+            ["a.rs:3:1: info: MultiEdit: replaced 1 line with 1", "a.rs:2:1: info: MultiEdit: replaced 1 line with 1"]
+        );
+        let write = r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Write", "input": {"file_path": "NOTES.md", "content": "one\ntwo\n"}}, {"type": "tool_use", "name": "Read", "input": {"file_path": "a.rs"}}]}}"#;
+        assert_eq!(records_for(write, ""), ["NOTES.md:1:1: info: Write: wrote 2 lines"]);
+        let result = r#"{"type": "result", "result": "ok"}"#;
+        assert_eq!(records_for(result, ""), Vec::<String>::new());
+
+        let msg = serde_json::from_str(multi).unwrap();
+        assert_eq!(edited_files(&msg), ["a.rs"]);
+        assert!(edited_files(&serde_json::from_str(write).unwrap()).is_empty());
+    }
+}
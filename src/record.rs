@@ -0,0 +1,202 @@
+//! `--record` and `play`: the raw input, each line with when it arrived,
+//! and the terminal it was shown on, gzipped into one file, so a session
+//! can be formatted again later, at its own pace, with any options.
+//!
+//! Uncompressed, a recording is JSON lines: a header, then `[ms, line]`
+//! for each line of input, timed from the start.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Marks the first line of a recording.
+const FORMAT: &str = "claude-stream-format recording";
+const VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub format: String,
+    pub version: u32,
+    /// When recording started, in milliseconds since the Unix epoch.
+    pub started: u64,
+    /// The `--dialect` the input was read as.
+    pub dialect: String,
+    pub terminal: Terminal,
+}
+
+/// What the output was shown on.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Terminal {
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    /// `$TERM` and `$COLORTERM`.
+    pub term: Option<String>,
+    pub colorterm: Option<String>,
+}
+
+impl Terminal {
+    /// The terminal stdout is, if it is one.
+    pub fn current() -> Terminal {
+        let size = terminal_size::terminal_size_of(io::stdout());
+        Terminal {
+            width: size.map(|(width, _)| width.0),
+            height: size.map(|(_, height)| height.0),
+            term: std::env::var("TERM").ok(),
+            colorterm: std::env::var("COLORTERM").ok(),
+        }
+    }
+}
+
+impl Header {
+    pub fn new(dialect: &str, terminal: Terminal) -> Header {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Header {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            started: started.as_millis() as u64,
+            dialect: dialect.to_string(),
+            terminal,
+        }
+    }
+}
+
+/// Writes a recording as the input arrives. Each line is written through,
+/// so a recording cut short still plays up to where it stopped.
+pub struct Recorder {
+    encoder: GzEncoder<BufWriter<File>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, header: &Header) -> io::Result<Recorder> {
+        let mut recorder = Recorder {
+            encoder: GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default()),
+            start: Instant::now(),
+        };
+        recorder.write(format!("{}\n", serde_json::to_string(header)?).as_bytes())?;
+        Ok(recorder)
+    }
+
+    /// Records `line` as arriving now.
+    pub fn record(&mut self, line: &str) -> io::Result<()> {
+        let at = self.start.elapsed().as_millis() as u64;
+        let event = serde_json::to_string(&(at, line))?;
+        self.write(format!("{}\n", event).as_bytes())
+    }
+
+    /// Writes `chunk` and flushes it to a byte boundary in the file, so it
+    /// can be read back even if the recording is never finished.
+    fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.encoder.write_all(chunk)?;
+        self.encoder.flush()
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        self.encoder.finish()?.flush()
+    }
+}
+
+/// A recording read back.
+#[derive(Debug)]
+pub struct Recording {
+    pub header: Header,
+    /// Each line, with when it arrived in milliseconds from the start.
+    pub lines: Vec<(u64, String)>,
+}
+
+impl Recording {
+    pub fn open(path: &Path) -> io::Result<Recording> {
+        Recording::parse(&decompress(&std::fs::read(path)?)?)
+    }
+
+    fn parse(data: &[u8]) -> io::Result<Recording> {
+        let invalid = |why: String| io::Error::new(io::ErrorKind::InvalidData, why);
+        let text = String::from_utf8_lossy(data);
+        let mut lines = text.lines();
+        let header: Header = lines
+            .next()
+            .and_then(|line| serde_json::from_str(line).ok())
+            .filter(|header: &Header| header.format == FORMAT)
+            .ok_or_else(|| invalid("not a recording".to_string()))?;
+        if header.version > VERSION {
+            return Err(invalid(format!("recorded by a newer version (format {})", header.version)));
+        }
+        // A line cut off by a recording that was killed is left out.
+        let lines = lines.filter_map(|line| serde_json::from_str(line).ok()).collect();
+        Ok(Recording { header, lines })
+    }
+
+    /// The lines, each given when it arrived, `speed` times as fast as it
+    /// was recorded, or at once if `speed` is 0.
+    pub fn play(self, speed: f64) -> impl Iterator<Item = io::Result<String>> {
+        let start = Instant::now();
+        self.lines.into_iter().map(move |(at, line)| {
+            if speed > 0.0 {
+                let due = Duration::from_millis(at).div_f64(speed);
+                thread::sleep(due.saturating_sub(start.elapsed()));
+            }
+            Ok(line)
+        })
+    }
+}
+
+/// Decompresses a recording. One cut short, as by a recording that was
+/// killed, gives what it holds so far.
+fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match GzDecoder::new(data).read_to_end(&mut out) {
+        Ok(_) => Ok(out),
+        Err(_) if !out.is_empty() => Ok(out),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let path = std::env::temp_dir().join(format!("csf-record-{}.csfr", std::process::id()));
+        let terminal = Terminal { width: Some(100), height: Some(40), term: Some("xterm-256color".to_string()), colorterm: None };
+        let mut recorder = Recorder::create(&path, &Header::new("claude-code", terminal)).unwrap();
+        recorder.record(r#"{"type": "system", "subtype": "init"}"#).unwrap();
+        recorder.record("not json").unwrap();
+        recorder.finish().unwrap();
+
+        let recording = Recording::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.header.dialect, "claude-code");
+        assert_eq!(recording.header.terminal.width, Some(100));
+        let lines: Vec<String> = recording.play(0.0).map(Result::unwrap).collect();
+        assert_eq!(lines, [r#"{"type": "system", "subtype": "init"}"#, "not json"]);
+    }
+
+    #[test]
+    fn test_cut_short() {
+        let path = std::env::temp_dir().join(format!("csf-record-cut-{}.csfr", std::process::id()));
+        let mut recorder = Recorder::create(&path, &Header::new("auto", Terminal::default())).unwrap();
+        recorder.record("a").unwrap();
+        recorder.record("b").unwrap();
+        // Never finished, as when the recording is killed.
+        std::mem::forget(recorder);
+        let recording = Recording::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.lines.iter().map(|(_, line)| line.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert!(decompress(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let header = serde_json::to_string(&Header::new("auto", Terminal::default())).unwrap();
+        let recording = Recording::parse(format!("{}\n[0,\"a\"]\n[1500,\"b\"]\n[16", header).as_bytes()).unwrap();
+        assert_eq!(recording.lines, [(0, "a".to_string()), (1500, "b".to_string())]);
+        assert!(Recording::parse(b"{\"type\": \"system\"}\n").is_err());
+    }
+}
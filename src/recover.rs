@@ -0,0 +1,114 @@
+//! Recovery of JSON documents from imperfectly framed input: several
+//! objects concatenated on one line, or one object split over several.
+
+use serde::de::IgnoredAny;
+
+/// Incomplete input held back is given up on past this size.
+const MAX_PENDING: usize = 16 * 1024 * 1024;
+
+/// Splits input lines into JSON documents.
+#[derive(Default)]
+pub struct Reassembler {
+    /// The start of a document whose end has not arrived yet.
+    pending: String,
+}
+
+impl Reassembler {
+    /// Feeds one line of input, returning the complete JSON documents it
+    /// contains or finishes. Text that can't be parsed is returned as-is so
+    /// the caller can report it.
+    pub fn push(&mut self, line: &str) -> Vec<String> {
+        if self.pending.is_empty() {
+            return self.split(line);
+        }
+
+        let joined = format!("{}{}", self.pending, line);
+        self.pending.clear();
+        let pieces = self.split(&joined);
+        if pieces.len() == 1 && pieces[0] == joined && !is_json(&joined) {
+            // The held-back text wasn't the start of this line's document
+            // after all; give it up and parse the line on its own.
+            let mut pieces = vec![joined[..joined.len() - line.len()].to_string()];
+            pieces.extend(self.split(line));
+            return pieces;
+        }
+        pieces
+    }
+
+    /// Whether no incomplete document is held back, so that a line can be
+    /// parsed on its own without going through `push`.
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns whatever incomplete input is left at the end of the stream.
+    pub fn finish(&mut self) -> Option<String> {
+        Some(std::mem::take(&mut self.pending)).filter(|pending| !pending.trim().is_empty())
+    }
+
+    fn split(&mut self, text: &str) -> Vec<String> {
+        let mut pieces = Vec::new();
+        let mut stream = serde_json::Deserializer::from_str(text).into_iter::<IgnoredAny>();
+        let mut start = 0;
+        loop {
+            match stream.next() {
+                None => break,
+                Some(Ok(_)) => {
+                    let end = stream.byte_offset();
+                    pieces.push(text[start..end].trim().to_string());
+                    start = end;
+                }
+                Some(Err(err)) if err.is_eof() && text.len() - start < MAX_PENDING => {
+                    self.pending = text[start..].to_string();
+                    break;
+                }
+                Some(Err(_)) => {
+                    pieces.push(text[start..].trim().to_string());
+                    break;
+                }
+            }
+        }
+        pieces.retain(|piece| !piece.is_empty());
+        pieces
+    }
+}
+
+fn is_json(text: &str) -> bool {
+    serde_json::from_str::<IgnoredAny>(text).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concatenated_objects() {
+        let mut reassembler = Reassembler::default();
+        assert_eq!(reassembler.push(r#"{"a":1}{"b":2} {"c":3}"#), vec![r#"{"a":1}"#, r#"{"b":2}"#, r#"{"c":3}"#]);
+        assert_eq!(reassembler.push("   "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_split_object() {
+        let mut reassembler = Reassembler::default();
+        assert_eq!(reassembler.push(r#"{"type": "result", "res"#), Vec::<String>::new());
+        assert!(!reassembler.is_idle());
+        assert_eq!(reassembler.push(r#"ult": "ok"}"#), vec![r#"{"type": "result", "result": "ok"}"#]);
+        assert_eq!(reassembler.finish(), None);
+        assert!(reassembler.is_idle());
+    }
+
+    #[test]
+    fn test_garbage() {
+        let mut reassembler = Reassembler::default();
+        assert_eq!(reassembler.push("warning: something"), vec!["warning: something"]);
+        assert_eq!(reassembler.push(r#"{"a":1} trailing"#), vec![r#"{"a":1}"#, "trailing"]);
+
+        // A truncated object followed by a complete one gives up the first.
+        assert_eq!(reassembler.push(r#"{"a": "cut"#), Vec::<String>::new());
+        assert_eq!(reassembler.push(r#"{"b":2}"#), vec![r#"{"a": "cut"#, r#"{"b":2}"#]);
+
+        assert_eq!(reassembler.push(r#"{"open": true"#), Vec::<String>::new());
+        assert_eq!(reassembler.finish(), Some(r#"{"open": true"#.to_string()));
+    }
+}
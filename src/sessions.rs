@@ -0,0 +1,148 @@
+//! `--by-session`: separate state for each session when the output of
+//! several `claude` processes is read as one stream.
+
+use crate::format::short_session;
+use crate::formatter::{Options, StreamFormatter};
+use crate::message::StreamMessage;
+use std::collections::HashMap;
+
+/// A formatter per session, or a single formatter for everything.
+pub struct Sessions {
+    options: Options,
+    by_session: bool,
+    /// The formatters, in the order their sessions first appeared.
+    formatters: Vec<StreamFormatter>,
+    /// Index in `formatters` for each session id.
+    ids: HashMap<String, usize>,
+    /// The formatter of the last line routed.
+    current: Option<usize>,
+}
+
+impl Sessions {
+    /// Formats with `options`, keeping each session apart if `by_session`
+    /// is set.
+    pub fn new(options: Options, by_session: bool) -> Self {
+        let mut sessions = Sessions {
+            options,
+            by_session,
+            formatters: Vec::new(),
+            ids: HashMap::new(),
+            current: None,
+        };
+        if !by_session {
+            sessions.current = Some(sessions.add(None));
+        }
+        sessions
+    }
+
+    /// The formatter for one line of input, already parsed as `msg`: that of
+    /// its session, or of the last line routed if it has no session id.
+    pub fn route_message(&mut self, msg: Option<&StreamMessage>) -> &mut StreamFormatter {
+        let session_id = msg.filter(|_| self.by_session).and_then(|msg| msg.session_id.as_deref());
+        self.route_to(session_id)
+    }
+
+    fn route_to(&mut self, session_id: Option<&str>) -> &mut StreamFormatter {
+        let index = match session_id {
+            Some(id) => match self.ids.get(id) {
+                Some(&index) => index,
+                None => {
+                    let index = self.add(Some(id));
+                    self.ids.insert(id.to_string(), index);
+                    index
+                }
+            },
+            None => match self.current {
+                Some(index) => index,
+                None => self.add(None),
+            },
+        };
+        self.current = Some(index);
+        &mut self.formatters[index]
+    }
+
+    /// The formatter of the last line routed.
+    pub fn current(&self) -> Option<&StreamFormatter> {
+        self.formatters.get(self.current?)
+    }
+
+    /// Every formatter, in the order their sessions first appeared.
+    pub fn formatters_mut(&mut self) -> impl Iterator<Item = &mut StreamFormatter> {
+        self.formatters.iter_mut()
+    }
+
+    /// The options new formatters are made with.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Changes the options of every formatter, and of those made for
+    /// sessions still to come.
+    pub fn update_options(&mut self, update: impl Fn(&mut Options)) {
+        for formatter in &mut self.formatters {
+            update(formatter.options_mut());
+        }
+        update(&mut self.options);
+    }
+
+    fn add(&mut self, session_id: Option<&str>) -> usize {
+        let mut options = self.options.clone();
+        if self.by_session {
+            // Deltas from different sessions would interleave mid-line.
+            options.partial = false;
+            options.tag = session_id.map(|id| {
+                let styles = &options.theme.sessions;
+                let style = styles.get(self.formatters.len() % styles.len().max(1)).copied().unwrap_or_default();
+                style.paint(short_session(id))
+            });
+        }
+        self.formatters.push(StreamFormatter::new(options));
+        self.formatters.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::Output;
+
+    fn format(sessions: &mut Sessions, line: &str) -> Option<String> {
+        let msg = sessions.options().parse(line);
+        sessions.route_message(msg.as_ref()).format_line(line)
+    }
+
+    #[test]
+    fn test_by_session() {
+        let mut sessions = Sessions::new(Options::default(), true);
+        let a = r#"{"type": "result", "session_id": "aaaaaaaa-1111", "result": "one"}"#;
+        let b = r#"{"type": "assistant", "session_id": "bbbbbbbb-2222", "message": {"content": [{"type": "text", "text": "hi\nthere"}]}}"#;
+        assert_eq!(format(&mut sessions, a).unwrap(), "aaaaaaaa ✅ Done: one");
+        assert_eq!(format(&mut sessions, b).unwrap(), "bbbbbbbb hi\nbbbbbbbb there");
+        // Lines without a session id go with the last session seen.
+        let control = r#"{"type": "control_request", "request_id": "r1", "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {"command": "ls"}}}"#;
+        assert_eq!(format(&mut sessions, control).unwrap(), "bbbbbbbb 🔐 Permission requested: Bash (ls)");
+        assert_eq!(sessions.formatters_mut().count(), 2);
+    }
+
+    #[test]
+    fn test_single_session() {
+        let mut sessions = Sessions::new(Options::default(), false);
+        let a = r#"{"type": "result", "session_id": "aaaaaaaa-1111", "result": "one"}"#;
+        let b = r#"{"type": "result", "session_id": "bbbbbbbb-2222", "result": "two"}"#;
+        assert_eq!(format(&mut sessions, a).unwrap(), "✅ Done: one");
+        assert_eq!(format(&mut sessions, b).unwrap(), "✅ Done: two");
+        assert_eq!(sessions.formatters_mut().count(), 1);
+    }
+
+    #[test]
+    fn test_route_message() {
+        let mut sessions = Sessions::new(Options::default(), true);
+        let a = r#"{"type": "result", "session_id": "aaaaaaaa-1111", "result": "one"}"#;
+        let msg = sessions.options().parse(a);
+        let output = sessions.route_message(msg.as_ref()).process_parsed(a, msg);
+        assert!(matches!(output, Some(Output::Line(line)) if line == "aaaaaaaa ✅ Done: one"));
+        // The same session, routed from the raw line.
+        assert_eq!(format(&mut sessions, a).unwrap(), "aaaaaaaa ✅ Done: one");
+        assert_eq!(sessions.formatters_mut().count(), 1);
+    }
+}
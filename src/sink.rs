@@ -0,0 +1,136 @@
+//! `--output FORMAT=PATH`: outputs written to files alongside the one on
+//! stdout. Each is given the same parsed messages and formats them with
+//! formatters of its own, so one run can show text, save an HTML transcript
+//! and log JSON events at once.
+
+use crate::formatter::{Options, Output, OutputFormat};
+use crate::message::StreamMessage;
+use crate::sessions::Sessions;
+use crate::style::Theme;
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// One `--output`: a format, and the file it is written to, or stdout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputSpec {
+    pub format: OutputFormat,
+    pub path: Option<PathBuf>,
+}
+
+impl FromStr for OutputSpec {
+    type Err = String;
+
+    /// `FORMAT` for stdout, or `FORMAT=PATH`.
+    fn from_str(arg: &str) -> Result<Self, String> {
+        let (name, path) = match arg.split_once('=') {
+            Some((name, "")) => return Err(format!("expected a file after `{}=`", name)),
+            Some((name, path)) => (name, Some(PathBuf::from(path))),
+            None => (arg, None),
+        };
+        let format = <OutputFormat as ValueEnum>::from_str(name.trim(), true).map_err(|_| {
+            let names: Vec<String> = OutputFormat::value_variants()
+                .iter()
+                .filter_map(|format| format.to_possible_value().map(|value| value.get_name().to_string()))
+                .collect();
+            format!("unknown format `{}`; expected one of {}", name.trim(), names.join(", "))
+        })?;
+        Ok(OutputSpec { format, path })
+    }
+}
+
+/// An output written to a file.
+pub struct FileSink {
+    path: PathBuf,
+    sessions: Sessions,
+    out: BufWriter<File>,
+}
+
+impl FileSink {
+    /// Creates `path` for the stream formatted as `format`, with `options`
+    /// otherwise as for stdout.
+    pub fn create(path: &Path, format: OutputFormat, options: &Options, by_session: bool) -> io::Result<FileSink> {
+        let out = BufWriter::new(File::create(path)?);
+        let sessions = Sessions::new(file_options(options, format), by_session);
+        Ok(FileSink { path: path.to_path_buf(), sessions, out })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Formats one JSON document, parsed as `msg` by [`Options::parse`],
+    /// and writes what it comes to.
+    pub fn process(&mut self, doc: &str, msg: Option<&StreamMessage>) -> io::Result<()> {
+        match self.sessions.route_message(msg).process_parsed(doc, msg.cloned()) {
+            Some(Output::Line(line)) => writeln!(self.out, "{}", line),
+            Some(Output::Delta(text)) => write!(self.out, "{}", text),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes what the end of the stream adds, such as the `--summary`
+    /// table or the HTML document.
+    pub fn finish(mut self) -> io::Result<()> {
+        for formatter in self.sessions.formatters_mut() {
+            if let Some(output) = formatter.finish() {
+                writeln!(self.out, "{}", output)?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+/// `options` for a file in `format`: plain, at full width, and without
+/// what is done once for the whole run, such as hooks, announcements,
+/// routing to stderr and saving images.
+fn file_options(options: &Options, format: OutputFormat) -> Options {
+    Options {
+        output: format,
+        theme: Theme::default(),
+        link_template: None,
+        max_width: None,
+        stderr_kinds: Vec::new(),
+        on_event: Vec::new(),
+        announce: false,
+        save_images: None,
+        export_md: false,
+        ..options.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_spec() {
+        assert_eq!("json".parse(), Ok(OutputSpec { format: OutputFormat::Json, path: None }));
+        assert_eq!(
+            "html=out/report.html".parse(),
+            Ok(OutputSpec { format: OutputFormat::Html, path: Some(PathBuf::from("out/report.html")) })
+        );
+        assert!("html=".parse::<OutputSpec>().unwrap_err().contains("expected a file"));
+        assert!("yaml=a.yml".parse::<OutputSpec>().unwrap_err().contains("expected one of text, json, html"));
+    }
+
+    #[test]
+    fn test_file_sink() {
+        let path = std::env::temp_dir().join(format!("csf-sink-{}.txt", std::process::id()));
+        let options = Options { theme: Theme::new(Default::default()), summary: true, ..Options::default() };
+        let mut sink = FileSink::create(&path, OutputFormat::Text, &options, false).unwrap();
+        for doc in [
+            r#"{"type": "assistant", "message": {"content": [{"type": "text", "text": "Fixed it."}]}}"#,
+            r#"{"type": "result", "result": "Fixed it.", "num_turns": 1}"#,
+        ] {
+            sink.process(doc, options.parse(doc).as_ref()).unwrap();
+        }
+        sink.finish().unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(text.starts_with("Fixed it.\n✅ Done (1 turn)\n📊 Summary\n"), "{}", text);
+        assert!(!text.contains('\x1b'));
+    }
+}
@@ -0,0 +1,80 @@
+//! The progress line shown on a terminal while waiting for the next event.
+
+use crate::format::truncate;
+use std::time::Duration;
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Frames for `--ascii`.
+const ASCII_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Moves to the start of the line and erases it.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+/// How long to wait after the last event before showing the spinner, so
+/// quick bursts of events don't flicker.
+pub const SPINNER_DELAY: Duration = Duration::from_millis(500);
+
+/// How often the spinner is redrawn.
+pub const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Default)]
+pub struct Spinner {
+    frame: usize,
+    visible: bool,
+    ascii: bool,
+}
+
+impl Spinner {
+    /// A spinner drawn with ASCII characters only.
+    pub fn ascii() -> Self {
+        Spinner {
+            ascii: true,
+            ..Default::default()
+        }
+    }
+
+    /// The next frame of the spinner, e.g. `⠙ Bash: cargo test … 12s`, drawn
+    /// over the current line. `label` is the last tool that was called, and
+    /// `detail` is added at the end, e.g. `· 45k→3.2k tok`.
+    pub fn draw(&mut self, label: Option<&str>, waited: Duration, detail: Option<&str>) -> String {
+        let frames: &[char] = if self.ascii { &ASCII_FRAMES } else { &FRAMES };
+        let frame = frames[self.frame % frames.len()];
+        self.frame += 1;
+        self.visible = true;
+
+        let ellipsis = if self.ascii { "..." } else { "…" };
+        let detail = detail.map(|detail| format!(" · {}", detail)).unwrap_or_default();
+        match label {
+            Some(label) => format!("{}{} {} {} {}s{}", CLEAR_LINE, frame, truncate(label, 60), ellipsis, waited.as_secs(), detail),
+            None => format!("{}{} {}s{}", CLEAR_LINE, frame, waited.as_secs(), detail),
+        }
+    }
+
+    /// Erases the spinner if it is showing.
+    pub fn clear(&mut self) -> Option<&'static str> {
+        std::mem::take(&mut self.visible).then_some(CLEAR_LINE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_and_clear() {
+        let mut spinner = Spinner::default();
+        assert_eq!(spinner.clear(), None);
+
+        let first = spinner.draw(Some("Bash: cargo test"), Duration::from_secs(12), None);
+        assert_eq!(first, "\r\x1b[2K⠋ Bash: cargo test … 12s");
+        assert_eq!(spinner.draw(None, Duration::from_secs(13), None), "\r\x1b[2K⠙ 13s");
+        assert_eq!(spinner.draw(None, Duration::from_secs(14), Some("45k→3.2k tok")), "\r\x1b[2K⠹ 14s · 45k→3.2k tok");
+
+        assert_eq!(spinner.clear(), Some(CLEAR_LINE));
+        assert_eq!(spinner.clear(), None);
+
+        let mut spinner = Spinner::ascii();
+        assert_eq!(spinner.draw(Some("Bash: ls"), Duration::from_secs(2), None), "\r\x1b[2K| Bash: ls ... 2s");
+    }
+}
@@ -0,0 +1,331 @@
+//! The `stats` subcommand: totals across many sessions, such as every
+//! transcript Claude Code keeps under `~/.claude/projects/`: what they
+//! cost, which tools they used, the files edited most and how long a
+//! session runs.
+
+use crate::config::Labels;
+use crate::format::{display_width, format_usage, plural, short_model};
+use crate::history::SessionFile;
+use crate::lines::lines;
+use crate::message::{StreamMessage, Usage};
+use crate::style::Theme;
+use crate::summary::{FileActivity, Summary};
+use crate::time::{parse_rfc3339, short};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Tools and files listed before the rest are collapsed into a count.
+const TOP: usize = 15;
+
+/// What one session did.
+#[derive(Default)]
+pub struct Session {
+    pub model: Option<String>,
+    pub summary: Summary,
+    /// From its first timestamp to its last, or the time its results
+    /// report for a stream without timestamps.
+    pub duration: Option<Duration>,
+}
+
+impl Session {
+    /// Reads a session transcript, or a recorded stream-json run, skipping
+    /// lines that are neither, or aren't UTF-8.
+    pub fn read(input: impl BufRead) -> io::Result<Session> {
+        let mut session = Session::default();
+        let mut file = SessionFile::default();
+        let (mut first, mut last): (Option<SystemTime>, Option<SystemTime>) = (None, None);
+        let mut reported_ms = None;
+        for line in lines(input) {
+            let line = line?;
+            let Ok(line) = line.text() else {
+                continue;
+            };
+            let timestamp = serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|value| parse_rfc3339(value.get("timestamp")?.as_str()?));
+            if let Some(timestamp) = timestamp {
+                first = Some(first.map_or(timestamp, |first| first.min(timestamp)));
+                last = Some(last.map_or(timestamp, |last| last.max(timestamp)));
+            }
+            for line in file.translate(line) {
+                if let Ok(msg) = serde_json::from_str::<StreamMessage>(&line) {
+                    session.record(&msg, &mut reported_ms);
+                }
+            }
+        }
+        for line in file.finish() {
+            if let Ok(msg) = serde_json::from_str::<StreamMessage>(&line) {
+                session.record(&msg, &mut reported_ms);
+            }
+        }
+        session.duration = match (first, last) {
+            (Some(first), Some(last)) => last.duration_since(first).ok(),
+            _ => reported_ms.map(Duration::from_millis),
+        };
+        Ok(session)
+    }
+
+    fn record(&mut self, msg: &StreamMessage, reported_ms: &mut Option<u64>) {
+        match msg.msg_type.as_str() {
+            "system" if msg.subtype.as_deref() == Some("init") && self.model.is_none() => {
+                self.model = msg.model.clone();
+            }
+            "assistant" if self.model.is_none() && msg.parent_tool_use_id.is_none() => {
+                self.model = msg.message.as_ref().and_then(|message| message.model.clone());
+            }
+            "result" => {
+                if let Some(ms) = msg.duration_ms {
+                    *reported_ms.get_or_insert(0) += ms;
+                }
+            }
+            _ => {}
+        }
+        self.summary.record(msg);
+    }
+
+    /// Token usage as results report it, or summed from the messages for a
+    /// transcript, which has no results.
+    fn usage(&self) -> &Usage {
+        if self.summary.usage != Usage::default() {
+            &self.summary.usage
+        } else {
+            &self.summary.message_usage
+        }
+    }
+}
+
+/// The totals of many sessions.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub sessions: usize,
+    pub cost_usd: f64,
+    pub usage: Usage,
+    pub assistant_messages: usize,
+    /// Sessions per model, by its short name.
+    pub models: BTreeMap<String, usize>,
+    pub tool_counts: BTreeMap<String, usize>,
+    pub file_activity: BTreeMap<String, FileActivity>,
+    /// The length of each session that has one.
+    pub durations: Vec<Duration>,
+}
+
+impl Stats {
+    /// Adds `session`, unless nothing was said in it.
+    pub fn add(&mut self, session: &Session) {
+        let summary = &session.summary;
+        if summary.assistant_messages == 0 {
+            return;
+        }
+        self.sessions += 1;
+        self.cost_usd += summary.running_cost();
+        self.usage.add(session.usage());
+        self.assistant_messages += summary.assistant_messages;
+        let model = session.model.as_deref().filter(|model| !model.starts_with('<')).map_or("unknown", short_model);
+        *self.models.entry(model.to_string()).or_default() += 1;
+        for (tool, count) in &summary.tool_counts {
+            *self.tool_counts.entry(tool.clone()).or_default() += count;
+        }
+        for (path, activity) in &summary.file_activity {
+            let total = self.file_activity.entry(path.clone()).or_default();
+            total.reads += activity.reads;
+            total.edits += activity.edits;
+            total.writes += activity.writes;
+        }
+        self.durations.extend(session.duration);
+    }
+
+    pub fn tool_calls(&self) -> usize {
+        self.tool_counts.values().sum()
+    }
+
+    /// The totals and averages with the models used, then the tools by how
+    /// often they were called and the files edited most.
+    pub fn render(&self, theme: &Theme, labels: &Labels) -> String {
+        let mut lines = vec![theme.heading.paint(&format!("📊 {}", plural(self.sessions, &labels.sessions)))];
+        if self.sessions == 0 {
+            return lines.join("\n");
+        }
+        let average = |total: f64| total / self.sessions as f64;
+        let per_session = format!("  {}", labels.per_session);
+        let mut rows = vec![
+            (labels.cost.clone(), format!("${:.2}", self.cost_usd)),
+            (per_session.clone(), format!("${:.2}", average(self.cost_usd))),
+            (labels.tokens.clone(), format_usage(&self.usage, labels)),
+            (labels.assistant_messages.clone(), self.assistant_messages.to_string()),
+            (per_session, format!("{:.1}", average(self.assistant_messages as f64))),
+            (labels.tool_calls.clone(), self.tool_calls().to_string()),
+        ];
+        if !self.durations.is_empty() {
+            let total: Duration = self.durations.iter().sum();
+            rows.push((labels.session_length.clone(), short(total / self.durations.len() as u32)));
+            rows.push((format!("  {}", labels.longest), short(self.durations.iter().max().copied().unwrap_or_default())));
+        }
+        rows.push((labels.models.clone(), String::new()));
+        let mut models: Vec<(&String, &usize)> = self.models.iter().collect();
+        models.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        rows.extend(models.into_iter().map(|(model, count)| (format!("  {}", model), plural(*count, &labels.sessions))));
+        lines.extend(columns(rows));
+
+        if !self.tool_counts.is_empty() {
+            lines.push(theme.heading.paint(&format!("🔧 {}", labels.tools)));
+            let calls = self.tool_calls();
+            let mut tools: Vec<(&String, &usize)> = self.tool_counts.iter().collect();
+            tools.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let shown = tools.iter().take(TOP).map(|(tool, count)| {
+                let share = **count as f64 * 100.0 / calls as f64;
+                ((*tool).clone(), format!("{:>w$}  {:>3.0}%", count, share, w = calls.to_string().len()))
+            });
+            lines.extend(columns(shown));
+            if tools.len() > TOP {
+                lines.push(theme.comment.paint(&format!("   … {} {}", tools.len() - TOP, labels.more)));
+            }
+        }
+
+        let mut edited: Vec<(&String, &FileActivity)> =
+            self.file_activity.iter().filter(|(_, activity)| activity.edits + activity.writes > 0).collect();
+        if !edited.is_empty() {
+            lines.push(theme.heading.paint(&format!("📁 {}", labels.most_edited)));
+            edited.sort_by(|(a_path, a), (b_path, b)| {
+                (b.edits + b.writes).cmp(&(a.edits + a.writes)).then(b.reads.cmp(&a.reads)).then(a_path.cmp(b_path))
+            });
+            let shown = edited.iter().take(TOP).map(|(path, activity)| ((*path).clone(), activity.describe(labels)));
+            lines.extend(columns(shown));
+            if edited.len() > TOP {
+                lines.push(theme.comment.paint(&format!("   … {} {}", edited.len() - TOP, labels.more)));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// The files `paths` name: each file, and the `.jsonl` files anywhere
+/// under each directory, in order. A directory that can't be read is
+/// passed to `skip`.
+pub fn session_files(paths: &[PathBuf], mut skip: impl FnMut(&Path, io::Error)) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, &mut files, &mut skip);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>, skip: &mut impl FnMut(&Path, io::Error)) {
+    let entries = fs::read_dir(dir).and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect());
+    let mut entries: Vec<PathBuf> = match entries {
+        Ok(entries) => entries,
+        Err(err) => return skip(dir, err),
+    };
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, files, skip);
+        } else if path.extension().is_some_and(|extension| extension == "jsonl") {
+            files.push(path);
+        }
+    }
+}
+
+/// Reads each of `files` into the totals, passing any that can't be read
+/// to `skip` and going on with the rest.
+pub fn read(files: &[PathBuf], mut skip: impl FnMut(&Path, io::Error)) -> Stats {
+    let mut stats = Stats::default();
+    for path in files {
+        match fs::File::open(path).and_then(|file| Session::read(BufReader::new(file))) {
+            Ok(session) => stats.add(&session),
+            Err(err) => skip(path, err),
+        }
+    }
+    stats
+}
+
+/// The directory Claude Code keeps its transcripts in.
+pub fn projects_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(Path::new(&home).join(".claude").join("projects"))
+}
+
+
+/// Names and values in two columns.
+fn columns(rows: impl IntoIterator<Item = (String, String)>) -> Vec<String> {
+    let rows: Vec<(String, String)> = rows.into_iter().collect();
+    let width = rows.iter().map(|(name, _)| display_width(name)).max().unwrap_or(0);
+    rows.iter()
+        .map(|(name, value)| format!("   {}{}  {}", name, " ".repeat(width - display_width(name)), value).trim_end().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = r#"{"type": "summary", "summary": "Fix the tests", "leafUuid": "u0"}
+{"type": "user", "uuid": "u1", "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:00:00.000Z", "message": {"role": "user", "content": "Fix the tests"}}
+{"type": "assistant", "uuid": "u2", "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:00:03.000Z", "message": {"id": "m1", "model": "claude-sonnet-4-20250514", "role": "assistant", "content": [{"type": "tool_use", "id": "t1", "name": "Edit", "input": {"file_path": "/repo/src/lib.rs"}}], "usage": {"input_tokens": 1000, "output_tokens": 100}}}
+{"type": "user", "uuid": "u3", "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:00:04.000Z", "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": "ok"}]}}
+{"type": "assistant", "uuid": "u4", "sessionId": "abc", "cwd": "/repo", "timestamp": "2025-06-01T12:02:00.000Z", "message": {"id": "m2", "model": "claude-sonnet-4-20250514", "role": "assistant", "content": [{"type": "tool_use", "id": "t2", "name": "Bash", "input": {"command": "cargo test"}}], "usage": {"input_tokens": 1000, "output_tokens": 100}}}
+"#;
+
+    const STREAM: &str = r#"{"type": "system", "subtype": "init", "model": "claude-opus-4"}
+{"type": "assistant", "message": {"id": "m1", "content": [{"type": "tool_use", "id": "t1", "name": "Edit", "input": {"file_path": "/repo/src/lib.rs"}}, {"type": "tool_use", "id": "t2", "name": "Read", "input": {"file_path": "/repo/README.md"}}]}}
+{"type": "result", "result": "Done.", "total_cost_usd": 0.5, "duration_ms": 60000, "num_turns": 2}
+"#;
+
+    #[test]
+    fn test_session() {
+        let session = Session::read(TRANSCRIPT.as_bytes()).unwrap();
+        assert_eq!(session.model.as_deref(), Some("claude-sonnet-4-20250514"));
+        assert_eq!(session.summary.assistant_messages, 2);
+        assert_eq!(session.duration, Some(Duration::from_secs(120)));
+        assert_eq!(session.usage().output_tokens, 200);
+        assert!(session.summary.running_cost() > 0.0);
+
+        let session = Session::read(STREAM.as_bytes()).unwrap();
+        assert_eq!(session.duration, Some(Duration::from_secs(60)));
+        assert_eq!(session.summary.running_cost(), 0.5);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::default();
+        stats.add(&Session::read(TRANSCRIPT.as_bytes()).unwrap());
+        stats.add(&Session::read(STREAM.as_bytes()).unwrap());
+        stats.add(&Session::read("not json\n".as_bytes()).unwrap());
+        let corrupt = [b"\xff\xfe\n".as_slice(), STREAM.as_bytes()].concat();
+        assert_eq!(Session::read(corrupt.as_slice()).unwrap().summary.assistant_messages, 1);
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(stats.tool_calls(), 4);
+        assert_eq!(stats.models.get("sonnet-4"), Some(&1));
+        assert_eq!(stats.file_activity["/repo/src/lib.rs"].edits, 2);
+
+        let text = stats.render(&Theme::default(), &Labels::default());
+        assert!(text.starts_with("📊 2 sessions\n   Cost"), "{}", text);
+        assert!(text.contains("   Session length      1m 30s\n     longest           2m\n   Models\n     opus-4            1 session\n"), "{}", text);
+        assert!(text.contains("🔧 Tools\n   Edit  2   50%\n   Bash  1   25%\n   Read  1   25%\n"), "{}", text);
+        assert!(text.ends_with("📁 Most edited\n   /repo/src/lib.rs  2 edits"), "{}", text);
+    }
+
+    #[test]
+    fn test_session_files() {
+        let dir = std::env::temp_dir().join(format!("csf-stats-{}", std::process::id()));
+        fs::create_dir_all(dir.join("-repo")).unwrap();
+        fs::write(dir.join("-repo/b.jsonl"), TRANSCRIPT).unwrap();
+        fs::write(dir.join("-repo/a.jsonl"), STREAM).unwrap();
+        fs::write(dir.join("-repo/notes.txt"), "").unwrap();
+        let files = session_files(std::slice::from_ref(&dir), |path, err| panic!("{}: {}", path.display(), err));
+        assert_eq!(files, [dir.join("-repo/a.jsonl"), dir.join("-repo/b.jsonl")]);
+        let mut skipped = Vec::new();
+        let missing = dir.join("-repo/gone.jsonl");
+        let stats = read(&[files, vec![missing.clone()]].concat(), |path, _| skipped.push(path.to_path_buf()));
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(skipped, [missing]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use clap::ValueEnum;
+use std::io::IsTerminal;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+}
+
+/// An ANSI SGR sequence such as `"1;31"`; empty means unstyled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style(&'static str);
+
+impl Style {
+    pub fn is_plain(self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn paint(self, s: &str) -> String {
+        if self.0.is_empty() || s.is_empty() {
+            s.to_string()
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", self.0, s)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Theme {
+    pub text: Style,
+    pub thinking: Style,
+    pub tool: Style,
+    pub tool_result: Style,
+    pub result: Style,
+    pub error: Style,
+    pub heading: Style,
+    pub bold: Style,
+    pub italic: Style,
+    pub code: Style,
+    pub keyword: Style,
+    pub string: Style,
+    pub comment: Style,
+    pub number: Style,
+    pub added: Style,
+    pub removed: Style,
+    /// Colors for `--by-session` tags, used in turn.
+    pub sessions: &'static [Style],
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme {
+                text: Style("97"),
+                thinking: Style("2;3"),
+                tool: Style("96"),
+                tool_result: Style("90"),
+                result: Style("92"),
+                error: Style("1;91"),
+                heading: Style("1;95"),
+                bold: Style("1"),
+                italic: Style("3"),
+                code: Style("93"),
+                keyword: Style("94"),
+                string: Style("32"),
+                comment: Style("90"),
+                number: Style("33"),
+                added: Style("92"),
+                removed: Style("91"),
+                sessions: &[Style("96"), Style("95"), Style("93"), Style("92"), Style("94"), Style("91")],
+            },
+            ThemeName::Light => Theme {
+                text: Style("30"),
+                thinking: Style("2;3"),
+                tool: Style("34"),
+                tool_result: Style("90"),
+                result: Style("32"),
+                error: Style("1;31"),
+                heading: Style("1;35"),
+                bold: Style("1"),
+                italic: Style("3"),
+                code: Style("35"),
+                keyword: Style("34"),
+                string: Style("32"),
+                comment: Style("90"),
+                number: Style("33"),
+                added: Style("32"),
+                removed: Style("31"),
+                sessions: &[Style("36"), Style("35"), Style("33"), Style("32"), Style("34"), Style("31")],
+            },
+        }
+    }
+
+    /// The theme to use for `name`, or no styling at all if color is off.
+    pub fn resolve(name: ThemeName, color: ColorChoice) -> Self {
+        if color.enabled() {
+            Theme::new(name)
+        } else {
+            Theme::default()
+        }
+    }
+}
+
+/// Removes the SGR sequences added by [`Style::paint`] from `s`, and the
+/// OSC 8 hyperlinks added by [`crate::links::link`].
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('\x1b') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let end = if let Some(sgr) = rest.strip_prefix('[') {
+            sgr.find('m').map(|end| end + 2)
+        } else if let Some(osc) = rest.strip_prefix(']') {
+            // Ended by BEL or ESC \.
+            osc.find(['\x07', '\x1b']).map(|end| end + 1 + if osc[end..].starts_with('\x1b') { 2 } else { 1 })
+        } else {
+            out.push('\x1b');
+            Some(0)
+        };
+        match end {
+            Some(end) => rest = &rest[end..],
+            None => rest = "",
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(Style("31").paint("err"), "\x1b[31merr\x1b[0m");
+        assert_eq!(Style::default().paint("plain"), "plain");
+        assert_eq!(Style("31").paint(""), "");
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        let theme = Theme::new(ThemeName::Dark);
+        let painted = format!("{} and {}", theme.tool.paint("tool"), theme.error.paint("error"));
+        assert_eq!(strip_ansi(&painted), "tool and error");
+        assert_eq!(strip_ansi("plain"), "plain");
+        assert_eq!(strip_ansi("\x1b]8;;file:///a.rs\x1b\\a.rs\x1b]8;;\x1b\\ read"), "a.rs read");
+        assert_eq!(strip_ansi("\x1b]8;;file:///a.rs\x07a.rs\x1b]8;;\x07"), "a.rs");
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(Theme::resolve(ThemeName::Light, ColorChoice::Never), Theme::default());
+        assert_eq!(Theme::resolve(ThemeName::Light, ColorChoice::Always), Theme::new(ThemeName::Light));
+        assert_ne!(Theme::new(ThemeName::Dark), Theme::new(ThemeName::Light));
+    }
+}
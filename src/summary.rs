@@ -0,0 +1,389 @@
+//! Statistics accumulated across a whole stream.
+
+use crate::config::Labels;
+use crate::cost;
+use crate::format::{display_width, format_duration, format_usage, is_agent_tool, plural, truncate};
+use crate::message::{ContentBlock, StreamMessage, Usage};
+use crate::paths::PathDisplay;
+use crate::style::Theme;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Input fields that hold the path of a file a tool works on.
+const PATH_FIELDS: [&str; 2] = ["file_path", "notebook_path"];
+
+/// How often one file was read, edited and written.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FileActivity {
+    pub reads: usize,
+    pub edits: usize,
+    pub writes: usize,
+}
+
+impl FileActivity {
+    /// E.g. `3 reads, 2 edits`.
+    pub fn describe(&self, labels: &Labels) -> String {
+        let counts = [(self.reads, &labels.compact.read), (self.edits, &labels.compact.edit), (self.writes, &labels.compact.write)];
+        counts
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, noun)| plural(*n, noun))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// What one sub-agent used, from the messages naming its Task call as
+/// their `parent_tool_use_id`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AgentUsage {
+    /// The Task's description, e.g. `Find the test files`.
+    pub description: String,
+    /// Assistant messages, each counted once.
+    pub turns: u32,
+    pub tool_calls: usize,
+    pub usage: Usage,
+    /// Estimated from `usage`, as results only report the session's cost.
+    pub cost_usd: f64,
+}
+
+impl AgentUsage {
+    /// E.g. `4 turns, 52k→1.8k tok, $0.18`.
+    pub fn describe(&self, labels: &Labels) -> String {
+        let mut parts = vec![plural(self.turns as usize, &labels.turn)];
+        if self.usage != Usage::default() {
+            parts.push(format_usage(&self.usage, labels));
+        }
+        if self.cost_usd > 0.0 {
+            parts.push(format!("${:.2}", self.cost_usd));
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Calls per tool name.
+    pub tool_counts: BTreeMap<String, usize>,
+    /// Number of assistant messages; a message split over several lines
+    /// counts once.
+    pub assistant_messages: usize,
+    /// Paths of files passed to any tool.
+    pub files: BTreeSet<String>,
+    /// Reads, edits and writes of each file, by path.
+    pub file_activity: BTreeMap<String, FileActivity>,
+    /// For each tool, the time from each call to its result arriving.
+    pub tool_durations: BTreeMap<String, Vec<Duration>>,
+    /// Token usage summed over every result message.
+    pub usage: Usage,
+    /// Token usage summed over assistant messages, each counted once, so
+    /// far in the stream.
+    pub message_usage: Usage,
+    pub cost_usd: f64,
+    pub num_turns: u32,
+    /// Whether any result reported a failed run.
+    pub is_error: bool,
+    /// Each sub-agent's share, in the order they were started. Only the
+    /// messages of the sub-agent itself count, not those of any sub-agents
+    /// it starts.
+    pub agents: Vec<AgentUsage>,
+    /// Index in `agents` for each Task call's id.
+    agent_ids: HashMap<String, usize>,
+    /// Estimated cost of the assistant messages since the last result,
+    /// which reports the real cost.
+    unbilled_usd: f64,
+    message_ids: HashSet<String>,
+    /// Tool name and start time of each call still waiting for its result.
+    pending: HashMap<String, (String, Instant)>,
+    first_seen: Option<Instant>,
+    last_seen: Option<Instant>,
+}
+
+impl Summary {
+    /// The cost so far: what results have reported, plus an estimate for
+    /// the messages since.
+    pub fn running_cost(&self) -> f64 {
+        self.cost_usd + self.unbilled_usd
+    }
+
+    pub fn record(&mut self, msg: &StreamMessage) {
+        self.record_at(msg, Instant::now());
+    }
+
+    /// Records `msg` as having arrived at `now`.
+    pub fn record_at(&mut self, msg: &StreamMessage, now: Instant) {
+        self.first_seen.get_or_insert(now);
+        self.last_seen = Some(now);
+
+        match msg.msg_type.as_str() {
+            "assistant" => self.record_assistant(msg, now),
+            "user" => self.record_results(msg, now),
+            "result" => {
+                if let Some(usage) = &msg.usage {
+                    self.usage.add(usage);
+                }
+                self.cost_usd += msg.total_cost_usd.unwrap_or(0.0);
+                self.unbilled_usd = 0.0;
+                self.num_turns += msg.num_turns.unwrap_or(0);
+                self.is_error |= msg.is_error_result();
+            }
+            _ => {}
+        }
+    }
+
+    fn record_results(&mut self, msg: &StreamMessage, now: Instant) {
+        let Some(message) = &msg.message else {
+            return;
+        };
+        for block in &message.content {
+            if let ContentBlock::ToolResult { tool_use_id, .. } = block {
+                if let Some((name, started)) = self.pending.remove(tool_use_id) {
+                    self.tool_durations.entry(name).or_default().push(now - started);
+                }
+            }
+        }
+    }
+
+    fn record_assistant(&mut self, msg: &StreamMessage, now: Instant) {
+        let Some(message) = &msg.message else {
+            return;
+        };
+
+        let new = match &message.id {
+            Some(id) => self.message_ids.insert(id.clone()),
+            None => true,
+        };
+        // The sub-agent this message came from, if any.
+        let agent = msg.parent_tool_use_id.as_ref().and_then(|id| self.agent_ids.get(id)).copied();
+        if new {
+            self.assistant_messages += 1;
+            // Each line of a split message repeats the message's usage.
+            let estimate = message.usage.as_ref().map(|usage| cost::estimate(message.model.as_deref(), usage));
+            if let Some(usage) = &message.usage {
+                self.message_usage.add(usage);
+                self.unbilled_usd += estimate.unwrap_or(0.0);
+            }
+            if let Some(i) = agent {
+                let agent = &mut self.agents[i];
+                agent.turns += 1;
+                if let Some(usage) = &message.usage {
+                    agent.usage.add(usage);
+                    agent.cost_usd += estimate.unwrap_or(0.0);
+                }
+            }
+        }
+
+        for block in &message.content {
+            if let ContentBlock::ToolUse { id, name, input } = block {
+                *self.tool_counts.entry(name.clone()).or_default() += 1;
+                if let Some(i) = agent {
+                    self.agents[i].tool_calls += 1;
+                }
+                if is_agent_tool(name) && !id.is_empty() {
+                    self.agent_ids.insert(id.clone(), self.agents.len());
+                    self.agents.push(AgentUsage {
+                        description: agent_description(name, input),
+                        ..Default::default()
+                    });
+                }
+                if !id.is_empty() {
+                    self.pending.insert(id.clone(), (name.clone(), now));
+                }
+                let path = PATH_FIELDS.iter().find_map(|f| input.get(f)?.as_str());
+                if let Some(path) = path {
+                    self.files.insert(path.to_string());
+                    let activity = self.file_activity.entry(path.to_string()).or_default();
+                    match name.as_str() {
+                        "Read" => activity.reads += 1,
+                        "Edit" | "MultiEdit" | "NotebookEdit" => activity.edits += 1,
+                        "Write" => activity.writes += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn tool_calls(&self) -> usize {
+        self.tool_counts.values().sum()
+    }
+
+    /// Time between the first and last recorded messages.
+    pub fn time_span(&self) -> Duration {
+        match (self.first_seen, self.last_seen) {
+            (Some(first), Some(last)) => last - first,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Renders the summary as an aligned table.
+    pub fn render(&self, theme: &Theme, labels: &Labels) -> String {
+        let mut rows = vec![
+            (labels.assistant_messages.clone(), self.assistant_messages.to_string()),
+            (labels.tool_calls.clone(), self.tool_calls().to_string()),
+        ];
+        let mut tools: Vec<(&String, &usize)> = self.tool_counts.iter().collect();
+        tools.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        rows.extend(tools.into_iter().map(|(name, count)| (format!("  {}", name), count.to_string())));
+        if !self.agents.is_empty() {
+            rows.push((labels.sub_agents.clone(), self.agents.len().to_string()));
+            let mut agents: Vec<&AgentUsage> = self.agents.iter().collect();
+            agents.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then(b.turns.cmp(&a.turns)));
+            rows.extend(agents.into_iter().map(|agent| (format!("  {}", agent.description), agent.describe(labels))));
+        }
+        rows.push((labels.files_touched.clone(), self.files.len().to_string()));
+        rows.push((
+            labels.time_span.clone(),
+            format_duration(self.time_span().as_millis() as u64),
+        ));
+
+        let label_width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        let value_width = rows.iter().map(|(_, value)| value.chars().count()).max().unwrap_or(0);
+        let mut lines = vec![theme.heading.paint(&format!("📊 {}", labels.summary))];
+        lines.extend(rows.iter().map(|(label, value)| {
+            format!("   {:<lw$}  {:>vw$}", label, value, lw = label_width, vw = value_width)
+        }));
+        lines.join("\n")
+    }
+}
+
+impl Summary {
+    /// The files read, edited or written, changed files first, with paths
+    /// shown as `paths` says: `src/main.rs  3 reads, 2 edits`. `None` if no
+    /// files were touched.
+    pub fn render_files(&self, theme: &Theme, paths: &PathDisplay, labels: &Labels) -> Option<String> {
+        let mut files: Vec<(String, FileActivity)> = self
+            .file_activity
+            .iter()
+            .filter(|(_, activity)| **activity != FileActivity::default())
+            .map(|(path, activity)| (paths.path(path), *activity))
+            .collect();
+        if files.is_empty() {
+            return None;
+        }
+        files.sort_by(|(a_path, a), (b_path, b)| {
+            (b.edits + b.writes).cmp(&(a.edits + a.writes)).then(b.reads.cmp(&a.reads)).then(a_path.cmp(b_path))
+        });
+        let width = files.iter().map(|(path, _)| display_width(path)).max().unwrap_or(0);
+        let mut lines = vec![theme.heading.paint(&format!("📁 {}", labels.files))];
+        lines.extend(files.iter().map(|(path, activity)| {
+            let pad = " ".repeat(width - display_width(path));
+            format!("   {}{}  {}", path, pad, activity.describe(labels))
+        }));
+        Some(lines.join("\n"))
+    }
+}
+
+/// A sub-agent's name in the summary: its Task's description, or failing
+/// that its type or the start of its prompt.
+fn agent_description(name: &str, input: &serde_json::Value) -> String {
+    let field = |key| input.get(key).and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+    let description = field("description")
+        .or_else(|| field("subagent_type"))
+        .or_else(|| field("prompt").and_then(|prompt| prompt.lines().next()))
+        .unwrap_or(name);
+    truncate(description, 40)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(summary: &mut Summary, line: &str) {
+        summary.record(&serde_json::from_str(line).unwrap());
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut summary = Summary::default();
+        record(&mut summary, r#"{"type": "assistant", "message": {"id": "m1", "content": [{"type": "text", "text": "Hi"}]}}"#);
+        record(&mut summary, r#"{"type": "assistant", "message": {"id": "m1", "content": [{"type": "tool_use", "name": "Read", "input": {"file_path": "/a.rs"}}]}}"#);
+        record(&mut summary, r#"{"type": "assistant", "message": {"id": "m2", "content": [{"type": "tool_use", "name": "Edit", "input": {"file_path": "/a.rs"}}, {"type": "tool_use", "name": "Read", "input": {"file_path": "/b.rs"}}]}}"#);
+        record(&mut summary, r#"{"type": "result", "result": "ok"}"#);
+
+        assert_eq!(summary.assistant_messages, 2);
+        assert_eq!(summary.tool_calls(), 3);
+        assert_eq!(summary.tool_counts["Read"], 2);
+        assert_eq!(summary.files.len(), 2);
+    }
+
+    #[test]
+    fn test_durations_and_usage() {
+        let mut summary = Summary::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let parse = |line| serde_json::from_str::<StreamMessage>(line).unwrap();
+
+        summary.record_at(&parse(r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}, {"type": "tool_use", "id": "t2", "name": "Bash", "input": {"command": "pwd"}}]}}"#), at(0));
+        summary.record_at(&parse(r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "a"}]}}"#), at(250));
+        summary.record_at(&parse(r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t2", "content": "b"}]}}"#), at(400));
+        summary.record_at(&parse(r#"{"type": "result", "result": "ok", "num_turns": 2, "total_cost_usd": 0.25, "usage": {"input_tokens": 10, "output_tokens": 5}}"#), at(500));
+
+        assert_eq!(summary.tool_durations["Bash"], vec![Duration::from_millis(250), Duration::from_millis(400)]);
+        assert_eq!(summary.usage.total_input(), 10);
+        assert_eq!(summary.num_turns, 2);
+        assert_eq!(summary.cost_usd, 0.25);
+        assert_eq!(summary.time_span(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_running_cost() {
+        let mut summary = Summary::default();
+        let message = r#"{"type": "assistant", "message": {"id": "m1", "model": "claude-sonnet-4-5", "usage": {"input_tokens": 100000, "output_tokens": 10000}, "content": [{"type": "text", "text": "Hi"}]}}"#;
+        record(&mut summary, message);
+        record(&mut summary, message);
+        assert_eq!(summary.running_cost(), 0.45);
+        // The result's cost replaces the estimate.
+        record(&mut summary, r#"{"type": "result", "result": "ok", "total_cost_usd": 0.5}"#);
+        assert_eq!(summary.running_cost(), 0.5);
+    }
+
+    #[test]
+    fn test_render_files() {
+        let mut summary = Summary::default();
+        record(&mut summary, r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Read", "input": {"file_path": "/repo/src/lib.rs"}}, {"type": "tool_use", "name": "Read", "input": {"file_path": "/repo/src/main.rs"}}, {"type": "tool_use", "name": "Edit", "input": {"file_path": "/repo/src/main.rs"}}]}}"#);
+        record(&mut summary, r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Read", "input": {"file_path": "/repo/src/lib.rs"}}, {"type": "tool_use", "name": "Write", "input": {"file_path": "/repo/NOTES.md"}}, {"type": "tool_use", "name": "Grep", "input": {"path": "/repo"}}]}}"#);
+        let paths = PathDisplay {
+            cwd: Some("/repo".to_string()),
+            max_width: 0,
+        };
+        assert_eq!(
+            summary.render_files(&Theme::default(), &paths, &Labels::default()).unwrap(),
+            "📁 Files\n   src/main.rs  1 read, 1 edit\n   NOTES.md     1 write\n   src/lib.rs   2 reads"
+        );
+        assert_eq!(Summary::default().render_files(&Theme::default(), &paths, &Labels::default()), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut summary = Summary::default();
+        record(&mut summary, r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "name": "Bash", "input": {"command": "ls"}}, {"type": "tool_use", "name": "Read", "input": {"file_path": "/a.rs"}}, {"type": "tool_use", "name": "Read", "input": {"file_path": "/b.rs"}}]}}"#);
+        assert_eq!(
+            summary.render(&Theme::default(), &Labels::default()),
+            "📊 Summary\n   Assistant messages     1\n   Tool calls             3\n     Read                 2\n     Bash                 1\n   Files touched          2\n   Time span           0.0s"
+        );
+    }
+
+    #[test]
+    fn test_sub_agents() {
+        let mut summary = Summary::default();
+        record(&mut summary, r#"{"type": "assistant", "message": {"id": "m1", "content": [{"type": "tool_use", "id": "task1", "name": "Task", "input": {"description": "Find the tests", "prompt": "..."}}, {"type": "tool_use", "id": "task2", "name": "Agent", "input": {"subagent_type": "code-reviewer", "prompt": "Review it"}}]}}"#);
+        let sub = |parent: &str, id: &str, usage: &str, tool: &str| {
+            format!(r#"{{"type": "assistant", "parent_tool_use_id": "{}", "message": {{"id": "{}", "model": "claude-sonnet-4-5", "usage": {}, "content": [{{"type": "tool_use", "name": "{}", "input": {{}}}}]}}}}"#, parent, id, usage, tool)
+        };
+        record(&mut summary, &sub("task1", "s1", r#"{"input_tokens": 10000, "output_tokens": 500}"#, "Glob"));
+        // Repeated lines of one message count once, but each tool call does.
+        record(&mut summary, &sub("task1", "s1", r#"{"input_tokens": 10000, "output_tokens": 500}"#, "Grep"));
+        record(&mut summary, &sub("task2", "s2", r#"{"input_tokens": 100000, "output_tokens": 2000}"#, "Read"));
+        record(&mut summary, &sub("task2", "s3", r#"{"input_tokens": 20000, "output_tokens": 1000}"#, "Read"));
+
+        let reviewer = &summary.agents[1];
+        assert_eq!((summary.agents[0].turns, summary.agents[0].tool_calls), (1, 2));
+        assert_eq!(reviewer.description, "code-reviewer");
+        assert_eq!((reviewer.turns, reviewer.tool_calls, reviewer.usage.total_input()), (2, 2, 120000));
+        assert_eq!(reviewer.describe(&Labels::default()), "2 turns, 120k→3k tok, $0.41");
+        assert!(summary.render(&Theme::default(), &Labels::default()).contains(
+            "   Sub-agents                                    2\n     code-reviewer     2 turns, 120k→3k tok, $0.41\n     Find the tests     1 turn, 10k→500 tok, $0.04\n"
+        ));
+    }
+}
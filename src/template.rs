@@ -0,0 +1,220 @@
+//! Per-tool format templates, set in the `[templates]` config table or with
+//! `--template NAME=TEMPLATE`.
+//!
+//! A template is text with `{...}` placeholders, e.g.
+//! `📖 {input.file_path} ({input.limit|default:all} lines)`. A placeholder
+//! names `name` (the tool name), `input`, or a field of the input such as
+//! `input.file_path` or `input.todos.0.content`, followed by any number of
+//! filters:
+//!
+//! - `default:TEXT` – `TEXT` if the value is missing or empty
+//! - `truncate:N` – cut to `N` columns, ending with `...`
+//! - `upper`, `lower` – change case
+//!
+//! `{{` and `}}` stand for literal braces.
+
+use crate::format::truncate;
+use serde::Deserialize;
+use std::fmt;
+
+/// A parsed template.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Field { path: Vec<String>, filters: Vec<Filter> },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Filter {
+    Default(String),
+    Truncate(usize),
+    Upper,
+    Lower,
+}
+
+/// Why a template could not be parsed.
+#[derive(Debug, PartialEq)]
+pub struct TemplateError(String);
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if !closed {
+                        return Err(TemplateError(format!("unclosed `{{{}`", placeholder)));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_field(&placeholder)?);
+                }
+                '}' => return Err(TemplateError("unmatched `}`; write `}}` for a literal brace".to_string())),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Fills in the template for a call to tool `name` with `input`.
+    pub fn render(&self, name: &str, input: &serde_json::Value) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field { path, filters } => {
+                    let value = lookup(name, input, path);
+                    out.push_str(&filters.iter().fold(value, apply).unwrap_or_default());
+                }
+            }
+        }
+        out
+    }
+}
+
+impl TryFrom<String> for Template {
+    type Error = TemplateError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Template::parse(&source)
+    }
+}
+
+/// Parses the inside of a `{...}` placeholder.
+fn parse_field(placeholder: &str) -> Result<Part, TemplateError> {
+    let mut pieces = placeholder.split('|');
+    let path: Vec<String> = pieces.next().unwrap_or_default().trim().split('.').map(str::to_string).collect();
+    match path.first().map(String::as_str) {
+        Some("name") if path.len() == 1 => {}
+        Some("input") => {}
+        _ => {
+            return Err(TemplateError(format!(
+                "unknown field `{}`; use `name` or `input.<field>`",
+                path.join(".")
+            )))
+        }
+    }
+    let filters = pieces.map(parse_filter).collect::<Result<_, _>>()?;
+    Ok(Part::Field { path, filters })
+}
+
+fn parse_filter(filter: &str) -> Result<Filter, TemplateError> {
+    let (name, arg) = match filter.split_once(':') {
+        Some((name, arg)) => (name.trim(), Some(arg)),
+        None => (filter.trim(), None),
+    };
+    match (name, arg) {
+        ("default", Some(text)) => Ok(Filter::Default(text.to_string())),
+        ("truncate", Some(n)) => n
+            .trim()
+            .parse()
+            .map(Filter::Truncate)
+            .map_err(|_| TemplateError(format!("`truncate` needs a number of columns, not `{}`", n))),
+        ("upper", None) => Ok(Filter::Upper),
+        ("lower", None) => Ok(Filter::Lower),
+        _ => Err(TemplateError(format!("unknown filter `{}`", filter.trim()))),
+    }
+}
+
+/// The value at `path`, as display text; `None` if it is missing or null.
+fn lookup(name: &str, input: &serde_json::Value, path: &[String]) -> Option<String> {
+    if path[0] == "name" {
+        return Some(name.to_string());
+    }
+    let mut value = input;
+    for key in &path[1..] {
+        value = match value {
+            serde_json::Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => value.get(key)?,
+        };
+    }
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn apply(value: Option<String>, filter: &Filter) -> Option<String> {
+    match filter {
+        Filter::Default(text) => value.filter(|v| !v.is_empty()).or_else(|| Some(text.clone())),
+        Filter::Truncate(n) => value.map(|v| truncate(&v, *n)),
+        Filter::Upper => value.map(|v| v.to_uppercase()),
+        Filter::Lower => value.map(|v| v.to_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, input: serde_json::Value) -> String {
+        Template::parse(template).unwrap().render("Read", &input)
+    }
+
+    #[test]
+    fn test_fields_and_defaults() {
+        let input = json!({"file_path": "src/main.rs", "limit": 50});
+        assert_eq!(render("📖 {input.file_path} ({input.limit} lines)", input.clone()), "📖 src/main.rs (50 lines)");
+        assert_eq!(render("{name}: {input.offset|default:start}", input.clone()), "Read: start");
+        assert_eq!(render("[{input.missing}]", input), "[]");
+        assert_eq!(render("{input.todos.1.content}", json!({"todos": [{"content": "a"}, {"content": "b"}]})), "b");
+    }
+
+    #[test]
+    fn test_filters() {
+        let input = json!({"command": "cargo test --workspace"});
+        assert_eq!(render("{input.command|truncate:10}", input.clone()), "cargo t...");
+        assert_eq!(render("{input.command|upper|truncate:8}", input.clone()), "CARGO...");
+        assert_eq!(render("{input.mode|default:Replace|lower}", input), "replace");
+        assert_eq!(render("{{{name}}}", json!({})), "{Read}");
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |template: &str| Template::parse(template).unwrap_err().to_string();
+        assert_eq!(error("{input.path"), "unclosed `{input.path`");
+        assert_eq!(error("a } b"), "unmatched `}`; write `}}` for a literal brace");
+        assert_eq!(error("{file_path}"), "unknown field `file_path`; use `name` or `input.<field>`");
+        assert_eq!(error("{input.x|shout}"), "unknown filter `shout`");
+        assert_eq!(error("{input.x|truncate:many}"), "`truncate` needs a number of columns, not `many`");
+    }
+}
@@ -0,0 +1,222 @@
+//! Wall-clock formatting without a date/time dependency. All times are UTC.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Formats `t` as an RFC 3339 UTC timestamp with milliseconds, e.g.
+/// `2025-01-31T09:05:03.042Z`.
+pub fn rfc3339(t: SystemTime) -> String {
+    let since_epoch = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Parses an RFC 3339 timestamp, such as those in session transcripts,
+/// e.g. `2025-01-31T09:05:03.042Z` or `2025-01-31T10:05:03+01:00`.
+pub fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    let s = s.trim();
+    let (date, time) = s.split_once(['T', 't', ' '])?;
+    // Each part is parsed as a small number, so nothing below overflows.
+    let mut date = date.splitn(3, '-').map(str::parse::<u16>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(i) => time.split_at(i),
+        None => (time, ""),
+    };
+    let offset_secs = match offset {
+        "" | "Z" | "z" => 0,
+        offset => {
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let two_digits = |s: &str| Some(s).filter(|s| s.len() == 2 && s.bytes().all(|b| b.is_ascii_digit()))?.parse::<i64>().ok();
+            let (hours, minutes) = (two_digits(hours)?, two_digits(minutes)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let secs = hours * 3600 + minutes * 60;
+            if offset.starts_with('-') { -secs } else { secs }
+        }
+    };
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<u8>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let millis = match fraction.get(..3.min(fraction.len())) {
+        Some("") | None => 0,
+        Some(digits) => digits.parse::<u64>().ok()? * 10u64.pow(3 - digits.len() as u32),
+    };
+    let secs = days_from_civil(i64::from(year), u32::from(month), u32::from(day)) * 86_400
+        + i64::from(hour) * 3600
+        + i64::from(minute) * 60
+        + i64::from(second)
+        - offset_secs;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?) + Duration::from_millis(millis))
+}
+
+/// Formats the time of day of `t` as `HH:MM:SS`.
+pub fn clock(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() % 86_400;
+    hms(Duration::from_secs(secs))
+}
+
+/// Formats a duration as `HH:MM:SS`.
+pub fn hms(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Formats a duration to the largest whole units, e.g. `45s`, `2m` or
+/// `1h 5m`.
+pub fn short(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 if secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ if secs % 3600 < 60 => format!("{}h", secs / 3600),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Parses a duration such as `90`, `90s`, `2m` or `1h`; a bare number is
+/// seconds. Zero is refused, as nothing waits for no time.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = number.parse().map_err(|_| format!("expected a duration such as 90s or 2m, not `{}`", s))?;
+    let secs = match unit {
+        "" | "s" => Some(n),
+        "m" => n.checked_mul(60),
+        "h" => n.checked_mul(3600),
+        _ => return Err(format!("unknown unit `{}`; use s, m or h", unit)),
+    };
+    match secs {
+        Some(0) => Err("the duration must be at least 1s".to_string()),
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => Err(format!("`{}` is too long", s)),
+    }
+}
+
+/// Parses a rate such as `20/s`, `600/m` or `20` (per second) into the
+/// time between two events.
+pub fn parse_rate(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (count, unit) = s.split_once('/').unwrap_or((s, "s"));
+    let count: u32 = count.trim().parse().map_err(|_| format!("expected a rate such as 20/s, not `{}`", s))?;
+    if count == 0 {
+        return Err("the rate must be at least 1".to_string());
+    }
+    let per = match unit.trim() {
+        "s" | "sec" => Duration::from_secs(1),
+        "m" | "min" => Duration::from_secs(60),
+        unit => return Err(format!("unknown unit `{}`; use s or m", unit)),
+    };
+    Ok(per / count)
+}
+
+/// Converts days since the Unix epoch to a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's algorithm: http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) date to days since the Unix epoch.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // The inverse of `civil_from_days`, from the same source.
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let t = UNIX_EPOCH + Duration::from_millis(1_738_314_303_042);
+        assert_eq!(rfc3339(t), "2025-01-31T09:05:03.042Z");
+        let leap = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(rfc3339(leap), "2000-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let t = UNIX_EPOCH + Duration::from_millis(1_738_314_303_042);
+        assert_eq!(parse_rfc3339("2025-01-31T09:05:03.042Z"), Some(t));
+        assert_eq!(parse_rfc3339("2025-01-31T10:05:03.042+01:00"), Some(t));
+        assert_eq!(parse_rfc3339("2025-01-31T09:05:03.042817Z"), Some(t));
+        assert_eq!(parse_rfc3339("2000-02-29T00:00:00Z"), Some(UNIX_EPOCH + Duration::from_secs(951_782_400)));
+        assert_eq!(parse_rfc3339(&rfc3339(t)), Some(t));
+        assert_eq!(parse_rfc3339("2025-13-01T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("yesterday"), None);
+        // Nothing a stream can hold makes it overflow.
+        assert_eq!(parse_rfc3339("2025-01-31T09:05:03+99999999999999999:00"), None);
+        assert_eq!(parse_rfc3339("2025-01-31T09:05:03+24:00"), None);
+        assert_eq!(parse_rfc3339("99999999999-01-31T09:05:03Z"), None);
+        assert!(parse_rfc3339("2025-01-31T09:05:03.99999999999999999999Z").is_some());
+    }
+
+    #[test]
+    fn test_clock_and_hms() {
+        let t = UNIX_EPOCH + Duration::from_millis(1_738_314_303_042);
+        assert_eq!(clock(t), "09:05:03");
+        assert_eq!(hms(Duration::from_secs(83)), "00:01:23");
+        assert_eq!(hms(Duration::from_secs(90_061)), "25:01:01");
+    }
+
+    #[test]
+    fn test_short_and_parse() {
+        assert_eq!(short(Duration::from_secs(45)), "45s");
+        assert_eq!(short(Duration::from_secs(120)), "2m");
+        assert_eq!(short(Duration::from_secs(150)), "2m 30s");
+        assert_eq!(short(Duration::from_secs(3900)), "1h 5m");
+        assert_eq!(short(Duration::from_secs(7200)), "2h");
+
+        assert_eq!(parse_duration("120s"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("2d"), Err("unknown unit `d`; use s, m or h".to_string()));
+        assert!(parse_duration("soon").is_err());
+        assert_eq!(parse_duration("0"), Err("the duration must be at least 1s".to_string()));
+        assert_eq!(parse_duration("0m"), Err("the duration must be at least 1s".to_string()));
+        assert_eq!(parse_duration("18446744073709551615h"), Err("`18446744073709551615h` is too long".to_string()));
+        assert_eq!(parse_duration("18446744073709551615"), Ok(Duration::from_secs(u64::MAX)));
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("20/s"), Ok(Duration::from_millis(50)));
+        assert_eq!(parse_rate("4"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_rate("120/m"), Ok(Duration::from_millis(500)));
+        assert!(parse_rate("0/s").is_err());
+        assert!(parse_rate("20/h").is_err());
+        assert!(parse_rate("fast").is_err());
+    }
+}
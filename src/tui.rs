@@ -0,0 +1,575 @@
+//! `--tui`: an interactive view of the stream with a scrollable transcript,
+//! collapsible tool output and a status bar.
+//!
+//! Only built with the `tui` feature.
+
+use crate::format::{display_width, format_init, format_stats, format_tokens, format_tool, is_agent_tool, Limits};
+use crate::message::{ContentBlock, StreamMessage};
+use crate::recover::Reassembler;
+use crate::summary::Summary;
+use crate::time;
+use crate::{Kind, Options};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+use std::collections::HashMap;
+use std::io;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// How long to wait for a key press before checking for new input.
+const TICK: Duration = Duration::from_millis(100);
+
+const HINTS: &str = " q quit · ↑↓ move · ⏎ expand · t thinking · r results ";
+
+enum Entry {
+    Header(String),
+    Text(usize, String),
+    Thinking(usize, String),
+    Tool {
+        depth: usize,
+        line: String,
+        result: Option<(String, bool)>,
+        /// Set once the entry has been toggled; until then it follows
+        /// [`App::show_tool_results`].
+        expanded: Option<bool>,
+    },
+    Outcome(String, bool),
+}
+
+/// The state of the interactive view.
+pub struct App {
+    /// What to show, with `--only`, `--hide` and `--redact` applied as
+    /// without `--tui`.
+    options: Options,
+    entries: Vec<Entry>,
+    /// Index in `entries` of the call for each tool_use id.
+    tools: HashMap<String, usize>,
+    /// Nesting depth of the sub-agent spawned by each Task tool_use id.
+    task_depths: HashMap<String, usize>,
+    summary: Summary,
+    last_tool: Option<String>,
+    started: Instant,
+    /// How long the stream ran, once it has ended.
+    ran: Option<Duration>,
+    failed: bool,
+    show_thinking: bool,
+    show_tool_results: bool,
+    selected: usize,
+    /// Keep the last entry selected as new ones arrive.
+    follow: bool,
+    /// The first line shown in the transcript pane.
+    scroll: usize,
+    /// The line range of each entry and the pane height at the last draw.
+    layout: Vec<(usize, usize)>,
+    height: usize,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(options: Options) -> Self {
+        App {
+            show_thinking: options.show_thinking,
+            show_tool_results: options.show_tool_results,
+            options,
+            entries: Vec::new(),
+            tools: HashMap::new(),
+            task_depths: HashMap::new(),
+            summary: Summary::default(),
+            last_tool: None,
+            started: Instant::now(),
+            ran: None,
+            failed: false,
+            selected: 0,
+            follow: true,
+            scroll: 0,
+            layout: Vec::new(),
+            height: 0,
+            quit: false,
+        }
+    }
+
+    /// Whether the stream reported a failed run.
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Adds one line of stream-json input.
+    pub fn push(&mut self, line: &str) {
+        if let Some(msg) = self.options.parse(line) {
+            self.push_message(msg);
+        }
+    }
+
+    /// Adds one message parsed from the input.
+    fn push_message(&mut self, msg: StreamMessage) {
+        self.summary.record(&msg);
+        self.failed |= msg.is_error_result();
+        let depth = match &msg.parent_tool_use_id {
+            Some(id) => self.task_depths.get(id).copied().unwrap_or(1),
+            None => 0,
+        };
+
+        match msg.msg_type.as_str() {
+            "system" if msg.subtype.as_deref() == Some("init") && self.options.selects(Kind::Header) => {
+                self.entries.push(Entry::Header(format_init(&msg, &self.options.config.labels)));
+            }
+            "assistant" => {
+                let Some(message) = &msg.message else {
+                    return;
+                };
+                for block in &message.content {
+                    match block {
+                        ContentBlock::Text { text } if !text.trim().is_empty() && self.options.selects(Kind::Text) => {
+                            self.entries.push(Entry::Text(depth, text.trim().to_string()));
+                        }
+                        ContentBlock::Thinking { thinking } if !thinking.trim().is_empty() && self.options.selects(Kind::Text) => {
+                            self.entries.push(Entry::Thinking(depth, thinking.trim().to_string()));
+                        }
+                        ContentBlock::ToolUse { id, name, input } => {
+                            if is_agent_tool(name) && !id.is_empty() {
+                                self.task_depths.insert(id.clone(), depth + 1);
+                            }
+                            if !self.options.selects(Kind::Tools) {
+                                continue;
+                            }
+                            if !id.is_empty() {
+                                self.tools.insert(id.clone(), self.entries.len());
+                            }
+                            let line = format_tool(name, input, &self.options.config, &Limits::UNLIMITED);
+                            self.last_tool = Some(line.clone());
+                            self.entries.push(Entry::Tool {
+                                depth,
+                                line,
+                                result: None,
+                                expanded: None,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            "user" => {
+                let Some(message) = &msg.message else {
+                    return;
+                };
+                for block in &message.content {
+                    let ContentBlock::ToolResult {
+                        tool_use_id,
+                        content,
+                        is_error,
+                    } = block
+                    else {
+                        continue;
+                    };
+                    if let Some(Entry::Tool { result, .. }) =
+                        self.tools.get(tool_use_id).and_then(|&index| self.entries.get_mut(index))
+                    {
+                        let text = content.as_ref().map(|c| c.text()).unwrap_or_default();
+                        *result = Some((text, *is_error));
+                    }
+                }
+            }
+            "result" => {
+                self.ran.get_or_insert(self.started.elapsed());
+                if !self.options.selects(Kind::Result) {
+                    return;
+                }
+                let label = if msg.is_error_result() { "❌ Error" } else { "✅ Done" };
+                let result = msg.result_text(&self.options.config.labels).unwrap_or_default();
+                let line = match format_stats(&msg, 0, &self.options.config.labels) {
+                    Some(stats) => format!("{} ({}): {}", label, stats, result),
+                    None => format!("{}: {}", label, result),
+                };
+                self.entries.push(Entry::Outcome(line, msg.is_error_result()));
+            }
+            _ => {}
+        }
+        if self.follow {
+            self.selected = self.last_visible().unwrap_or(0);
+        }
+    }
+
+    /// Marks the end of the input.
+    pub fn end(&mut self) {
+        self.ran.get_or_insert(self.started.elapsed());
+    }
+
+    fn visible(&self, index: usize) -> bool {
+        !matches!(self.entries[index], Entry::Thinking(..)) || self.show_thinking
+    }
+
+    fn last_visible(&self) -> Option<usize> {
+        (0..self.entries.len()).rev().find(|&i| self.visible(i))
+    }
+
+    fn expanded(&self, index: usize) -> bool {
+        match &self.entries[index] {
+            Entry::Tool {
+                result: Some(_),
+                expanded,
+                ..
+            } => expanded.unwrap_or(self.show_tool_results),
+            _ => false,
+        }
+    }
+
+    /// Moves the selection to the next visible entry in `indices`.
+    fn select(&mut self, mut indices: impl Iterator<Item = usize>) {
+        if let Some(index) = indices.find(|&i| self.visible(i)) {
+            self.selected = index;
+        }
+        self.follow = self.last_visible().is_none_or(|last| self.selected == last);
+    }
+
+    /// The first line of the entry at `index`, from the last draw.
+    fn start(&self, index: usize) -> usize {
+        self.layout.get(index).map_or(0, |&(start, _)| start)
+    }
+
+    pub fn key(&mut self, key: KeyEvent) {
+        let count = self.entries.len();
+        let page = self.height.max(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected + 1..count),
+            KeyCode::Up | KeyCode::Char('k') => self.select((0..self.selected).rev()),
+            KeyCode::PageDown => {
+                let target = self.start(self.selected) + page;
+                let next = (self.selected + 1..count).find(|&i| self.start(i) >= target);
+                self.select(next.into_iter().chain((0..count).rev()));
+            }
+            KeyCode::PageUp => {
+                let target = self.start(self.selected).saturating_sub(page);
+                let previous = (0..self.selected).rev().find(|&i| self.start(i) <= target);
+                self.select(previous.into_iter().chain(0..count));
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.select(0..count),
+            KeyCode::End | KeyCode::Char('G') => self.select((0..count).rev()),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let open = self.expanded(self.selected);
+                if let Some(Entry::Tool {
+                    result: Some(_),
+                    expanded,
+                    ..
+                }) = self.entries.get_mut(self.selected)
+                {
+                    *expanded = Some(!open);
+                }
+            }
+            KeyCode::Char('t') => {
+                self.show_thinking = !self.show_thinking;
+                if !self.visible(self.selected) {
+                    self.select((0..self.selected).rev().chain(self.selected..count));
+                }
+            }
+            KeyCode::Char('r') => {
+                self.show_tool_results = !self.show_tool_results;
+                for entry in &mut self.entries {
+                    if let Entry::Tool { expanded, .. } = entry {
+                        *expanded = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The transcript as lines `width` columns wide, with the line range of
+    /// each entry.
+    fn lines(&self, width: usize) -> (Vec<Line<'static>>, Vec<(usize, usize)>) {
+        let mut lines = Vec::new();
+        let mut layout = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let start = lines.len();
+            if self.visible(index) {
+                self.entry_lines(index, entry, width.saturating_sub(2), &mut lines);
+            }
+            // A gutter marks the selected entry.
+            let gutter = if index == self.selected { "▌ " } else { "  " };
+            for line in &mut lines[start..] {
+                line.spans.insert(0, Span::styled(gutter, Style::new().fg(Color::Yellow)));
+            }
+            layout.push((start, lines.len()));
+        }
+        (lines, layout)
+    }
+
+    fn entry_lines(&self, index: usize, entry: &Entry, width: usize, lines: &mut Vec<Line<'static>>) {
+        let mut push = |depth: usize, marker: &str, text: &str, style: Style| {
+            let indent = "  ".repeat(depth);
+            let rest = " ".repeat(display_width(marker));
+            let wrapped = wrap(text, width.saturating_sub(indent.len() + display_width(marker)).max(1));
+            for (i, piece) in wrapped.into_iter().enumerate() {
+                let marker = if i == 0 { marker.to_string() } else { rest.clone() };
+                lines.push(Line::from(vec![Span::raw(format!("{}{}", indent, marker)), Span::styled(piece, style)]));
+            }
+        };
+        match entry {
+            Entry::Header(header) => push(0, "", header, Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+            Entry::Text(depth, text) => {
+                for line in text.lines() {
+                    push(*depth, "", line, Style::new());
+                }
+            }
+            Entry::Thinking(depth, text) => {
+                let style = Style::new().add_modifier(Modifier::DIM | Modifier::ITALIC);
+                for (i, line) in text.lines().enumerate() {
+                    push(*depth, if i == 0 { "🧠 " } else { "   " }, line, style);
+                }
+            }
+            Entry::Tool { depth, line, result, .. } => {
+                let failed = matches!(result, Some((_, true)));
+                let marker = match result {
+                    None => "… ",
+                    Some(_) if self.expanded(index) => "▾ ",
+                    Some(_) => "▸ ",
+                };
+                let style = Style::new().fg(if failed { Color::Red } else { Color::Cyan });
+                push(*depth, marker, line, style);
+                if let Some((output, is_error)) = result.as_ref().filter(|_| self.expanded(index)) {
+                    let style = Style::new().fg(if *is_error { Color::Red } else { Color::DarkGray });
+                    for line in output.lines() {
+                        push(depth + 1, "│ ", line, style);
+                    }
+                }
+            }
+            Entry::Outcome(line, is_error) => {
+                let color = if *is_error { Color::Red } else { Color::Green };
+                push(0, "", line, Style::new().fg(color).add_modifier(Modifier::BOLD));
+            }
+        }
+    }
+
+    /// The status bar: state, elapsed time, tool calls, tokens and cost.
+    fn status(&self) -> String {
+        let elapsed = self.ran.unwrap_or_else(|| self.started.elapsed());
+        let state = match self.ran {
+            None => "⏳ running",
+            Some(_) if self.failed => "❌ failed",
+            Some(_) => "✅ done",
+        };
+        let mut parts = vec![
+            format!(" {}", state),
+            time::hms(elapsed),
+            match self.summary.tool_calls() {
+                1 => "1 tool call".to_string(),
+                calls => format!("{} tool calls", calls),
+            },
+        ];
+        let usage = &self.summary.usage;
+        if usage.total_input() + usage.output_tokens > 0 {
+            parts.push(format!(
+                "{} in / {} out",
+                format_tokens(usage.total_input()),
+                format_tokens(usage.output_tokens)
+            ));
+        }
+        if self.summary.cost_usd > 0.0 {
+            parts.push(format!("${:.4}", self.summary.cost_usd));
+        }
+        if let Some(tool) = self.last_tool.as_ref().filter(|_| self.ran.is_none()) {
+            parts.push(tool.clone());
+        }
+        parts.join(" │ ")
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let [body, bar] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let (lines, layout) = self.lines(usize::from(body.width));
+        self.height = usize::from(body.height);
+        self.layout = layout;
+
+        let (start, end) = self.layout.get(self.selected).copied().unwrap_or((0, 0));
+        if self.follow {
+            self.scroll = lines.len().saturating_sub(self.height);
+        } else if start < self.scroll {
+            self.scroll = start;
+        } else if end > self.scroll + self.height {
+            self.scroll = start.min(end - self.height);
+        }
+        let shown: Vec<Line> = lines.into_iter().skip(self.scroll).take(self.height).collect();
+        frame.render_widget(Paragraph::new(shown), body);
+
+        let bar_style = Style::new().bg(Color::DarkGray).fg(Color::White);
+        let [status, hints] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(display_width(HINTS) as u16)]).areas(bar);
+        frame.render_widget(Paragraph::new(self.status()).style(bar_style), status);
+        frame.render_widget(Paragraph::new(HINTS).style(bar_style.fg(Color::Gray)), hints);
+    }
+}
+
+/// Greedily wraps `text` at spaces to lines of at most `width` columns,
+/// breaking words that are longer than a line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_inclusive(' ') {
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() && display_width(line) + display_width(word.trim_end()) > width {
+            lines.push(String::new());
+        }
+        for c in word.chars() {
+            let line = lines.last_mut().unwrap();
+            if !line.is_empty() && c != ' ' && display_width(line) + display_width(&c.to_string()) > width {
+                lines.push(String::new());
+            }
+            lines.last_mut().unwrap().push(c);
+        }
+    }
+    lines.into_iter().map(|line| line.trim_end().to_string()).collect()
+}
+
+/// Runs the interactive view until the user quits, reading stream-json
+/// lines from `lines`.
+pub fn run(lines: Receiver<io::Result<String>>, app: &mut App) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let mut reassembler = Reassembler::default();
+    let mut ended = false;
+    let result = loop {
+        while !ended {
+            match lines.try_recv() {
+                Ok(Ok(line)) => {
+                    // Only a line that doesn't parse on its own is split
+                    // up or joined by the reassembler.
+                    match reassembler.is_idle().then(|| app.options.parse(&line)).flatten() {
+                        Some(msg) => app.push_message(msg),
+                        None => {
+                            for doc in reassembler.push(&line) {
+                                app.push(&doc);
+                            }
+                        }
+                    }
+                }
+                Ok(Err(_)) => {}
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if let Some(rest) = reassembler.finish() {
+                        app.push(&rest);
+                    }
+                    app.end();
+                    ended = true;
+                }
+            }
+        }
+        if let Err(err) = terminal.draw(|frame| app.draw(frame)) {
+            break Err(err);
+        }
+        match event::poll(TICK).and_then(|ready| if ready { event::read().map(Some) } else { Ok(None) }) {
+            Ok(Some(Event::Key(key))) if key.kind == KeyEventKind::Press => app.key(key),
+            Ok(_) => {}
+            Err(err) => break Err(err),
+        }
+        if app.quit {
+            break Ok(());
+        }
+    };
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(lines: &[&str]) -> App {
+        let mut app = App::new(Options::default());
+        for line in lines {
+            app.push(line);
+        }
+        app
+    }
+
+    fn text(app: &App, width: usize) -> Vec<String> {
+        let (lines, _) = app.lines(width);
+        lines.iter().map(|line| line.to_string().trim_end().to_string()).collect()
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    const STREAM: [&str; 4] = [
+        r#"{"type": "assistant", "message": {"content": [{"type": "thinking", "thinking": "Hmm."}, {"type": "text", "text": "Running the tests now."}]}}"#,
+        r#"{"type": "assistant", "message": {"content": [{"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "cargo test"}}]}}"#,
+        r#"{"type": "user", "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "ok\nall passed"}]}}"#,
+        r#"{"type": "result", "result": "Done."}"#,
+    ];
+
+    #[test]
+    fn test_collapsible_results() {
+        let mut app = app(&STREAM);
+        assert_eq!(
+            text(&app, 80),
+            vec!["  Running the tests now.", "  ▸ 💻 Bash: cargo test", "▌ ✅ Done: Done."]
+        );
+
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            text(&app, 80),
+            vec![
+                "  Running the tests now.",
+                "▌ ▾ 💻 Bash: cargo test",
+                "▌   │ ok",
+                "▌   │ all passed",
+                "  ✅ Done: Done.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_toggles() {
+        let mut app = app(&STREAM);
+        press(&mut app, KeyCode::Char('t'));
+        press(&mut app, KeyCode::Char('r'));
+        assert_eq!(text(&app, 80)[0], "  🧠 Hmm.");
+        assert_eq!(text(&app, 80).len(), 6);
+        // Selection stays on the last entry while following.
+        press(&mut app, KeyCode::Char('g'));
+        assert!(!app.follow);
+        press(&mut app, KeyCode::Char('G'));
+        assert!(app.follow);
+    }
+
+    #[test]
+    fn test_only_and_hide() {
+        let mut app = App::new(Options {
+            only: vec![Kind::Tools, Kind::Result],
+            ..Default::default()
+        });
+        for line in STREAM {
+            app.push(line);
+        }
+        assert_eq!(text(&app, 80), vec!["  ▸ 💻 Bash: cargo test", "▌ ✅ Done: Done."]);
+
+        let mut app = App::new(Options {
+            hide: vec![Kind::Result],
+            ..Default::default()
+        });
+        for line in STREAM {
+            app.push(line);
+        }
+        assert_eq!(text(&app, 80), vec!["  Running the tests now.", "▌ ▸ 💻 Bash: cargo test"]);
+        assert!(app.status().starts_with(" ✅ done │ "));
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("one two three", 8), vec!["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 4), vec![""]);
+    }
+
+    #[test]
+    fn test_status() {
+        let running = app(&STREAM[..2]);
+        assert!(running.status().starts_with(" ⏳ running │ "));
+        assert!(running.status().ends_with(" │ 1 tool call │ 💻 Bash: cargo test"));
+        let done = app(&STREAM);
+        assert!(done.status().starts_with(" ✅ done │ "));
+    }
+}
@@ -0,0 +1,113 @@
+//! `--highlight`: a watchlist of tool calls to make stand out, such as
+//! `rm` commands or writes to `.env` files.
+
+use std::str::FromStr;
+
+/// Marks a highlighted tool line.
+pub const MARK: &str = "⚠️ ";
+
+/// A `TOOL:PATTERN` rule. Both parts are matched with `*` and `?`
+/// wildcards; a pattern with neither matches any part of a value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    tool: String,
+    pattern: String,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tool, pattern) = s.split_once(':').ok_or("expected TOOL:PATTERN")?;
+        if tool.is_empty() || pattern.is_empty() {
+            return Err("expected TOOL:PATTERN".to_string());
+        }
+        Ok(Rule {
+            tool: tool.to_string(),
+            pattern: pattern.to_string(),
+        })
+    }
+}
+
+impl Rule {
+    /// Whether a call to `name` with `input` matches: the tool name, and
+    /// any of the input's string values.
+    pub fn matches(&self, name: &str, input: &serde_json::Value) -> bool {
+        if !wildcard(&self.tool, name) {
+            return false;
+        }
+        let Some(fields) = input.as_object() else {
+            return false;
+        };
+        let wild = self.pattern.contains(['*', '?']);
+        fields.values().filter_map(|value| value.as_str()).any(|value| {
+            if wild {
+                wildcard(&self.pattern, value)
+            } else {
+                value.contains(&self.pattern)
+            }
+        })
+    }
+}
+
+/// Whether all of `text` matches `pattern`, where `*` matches any run of
+/// characters and `?` any one.
+pub fn wildcard(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text it has matched up to.
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wildcard() {
+        assert!(wildcard("*.env", "/repo/.env"));
+        assert!(wildcard("*.env", "config/prod.env"));
+        assert!(!wildcard("*.env", "/repo/.envrc"));
+        assert!(wildcard("mcp__github__*", "mcp__github__create_issue"));
+        assert!(wildcard("Bas?", "Bash"));
+        assert!(!wildcard("Bash", "BashOutput"));
+    }
+
+    #[test]
+    fn test_rule() {
+        let rm: Rule = "Bash:rm ".parse().unwrap();
+        assert!(rm.matches("Bash", &json!({"command": "cd /tmp && rm -rf build"})));
+        assert!(!rm.matches("Bash", &json!({"command": "cargo fmt"})));
+        assert!(!rm.matches("Read", &json!({"file_path": "rm notes.txt"})));
+
+        let env: Rule = "Write:*.env".parse().unwrap();
+        assert!(env.matches("Write", &json!({"file_path": "/repo/.env", "content": "KEY=1"})));
+        assert!(!env.matches("Write", &json!({"file_path": "/repo/env.rs", "content": ""})));
+
+        assert!("Bash".parse::<Rule>().is_err());
+        assert!(":rm".parse::<Rule>().is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! `--webhook`: a summary of the run posted to a URL when it finishes, for
+//! Slack incoming webhooks and the like. The request is made with `curl`
+//! so no HTTP client has to be linked in.
+
+use crate::config::Labels;
+use crate::format::truncate;
+use crate::formatter::RunResult;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// How much of the final result text the payload carries.
+const SNIPPET_WIDTH: usize = 300;
+
+/// The JSON posted for `result`. `text` is a one-line summary, which is
+/// all a Slack incoming webhook shows; the other fields are for hooks that
+/// want to build their own message.
+pub fn payload(result: &RunResult, labels: &Labels) -> Value {
+    let snippet = truncate(result.text.trim(), SNIPPET_WIDTH);
+    let mut text = result.title(labels);
+    if let Some(cost) = result.cost_usd {
+        text.push_str(&format!(" (${:.2})", cost));
+    }
+    if !snippet.is_empty() {
+        text.push_str(&format!(": {}", snippet));
+    }
+    json!({
+        "text": text,
+        "session_id": result.session_id,
+        "status": if result.is_error { "error" } else { "success" },
+        "duration_ms": result.duration_ms,
+        "cost_usd": result.cost_usd,
+        "num_turns": result.num_turns,
+        "result": snippet,
+    })
+}
+
+/// Posts `payload` to `url` as JSON, waiting at most ten seconds.
+pub fn post(url: &str, payload: &Value) -> io::Result<()> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "10"])
+        .args(["--header", "Content-Type: application/json", "--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.to_string().as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("curl exited with {}", status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let result = RunResult {
+            is_error: false,
+            text: "All 42 tests pass.\n".to_string(),
+            duration_ms: Some(12_400),
+            cost_usd: Some(0.0512),
+            num_turns: Some(3),
+            session_id: Some("abc".to_string()),
+        };
+        assert_eq!(
+            payload(&result, &Labels::default()),
+            json!({
+                "text": "✅ Claude finished in 12.4s ($0.05): All 42 tests pass.",
+                "session_id": "abc",
+                "status": "success",
+                "duration_ms": 12400,
+                "cost_usd": 0.0512,
+                "num_turns": 3,
+                "result": "All 42 tests pass.",
+            })
+        );
+
+        let failed = RunResult { is_error: true, text: String::new(), duration_ms: None, cost_usd: None, num_turns: None, session_id: None };
+        let failed = payload(&failed, &Labels::default());
+        assert_eq!(failed["text"], "❌ Claude failed");
+        assert_eq!(failed["status"], "error");
+    }
+}
@@ -0,0 +1,306 @@
+//! The output writer: a thread of its own that writes what is formatted,
+//! so a slow terminal or pager doesn't hold up reading the stream, and
+//! flushes it as `--flush-policy` says.
+
+use clap::ValueEnum;
+use std::io::{self, BufWriter, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The longest output is held back, batched, before it is flushed.
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+const OUTPUT_BUFFER: usize = 64 * 1024;
+
+/// Bytes written before the writer waits for the thread to catch up, as
+/// when the pager is left scrolled back.
+const BACKLOG: usize = 1024 * 1024;
+
+/// When output is flushed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum FlushPolicy {
+    /// Results and errors at once, anything else within 50 ms
+    #[default]
+    Batched,
+    /// Every line as it is written
+    Immediate,
+    /// Only when the buffer fills, for writing to a file
+    Full,
+}
+
+/// What the writer and its thread share.
+struct Shared {
+    policy: FlushPolicy,
+    state: Mutex<State>,
+    /// Signalled when there is something for the thread to do.
+    work: Condvar,
+    /// Signalled when the thread has taken what was written, or flushed.
+    progress: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// Written and not yet taken by the thread.
+    pending: Vec<u8>,
+    /// What is written so far should go out now.
+    urgent: bool,
+    /// Flushes asked for, whatever the policy, and done, counted.
+    flushes: u64,
+    flushed: u64,
+    /// How the last flush went.
+    error: Option<io::ErrorKind>,
+    /// Whatever reads the output has gone, e.g. the pager quit.
+    closed: bool,
+    /// The writer is gone, so the thread should finish.
+    done: bool,
+    /// The thread is waiting for a write to wake it. Otherwise writes
+    /// leave it be, so they cost no more than copying into `pending`.
+    idle: bool,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Whether the thread has anything to take from `state` yet.
+    fn ready(&self, state: &State, due: Option<Instant>) -> bool {
+        if state.urgent || state.flushes > state.flushed || state.done {
+            return true;
+        }
+        match self.policy {
+            FlushPolicy::Batched => due.map_or(!state.pending.is_empty(), |due| Instant::now() >= due),
+            FlushPolicy::Immediate => !state.pending.is_empty(),
+            FlushPolicy::Full => state.pending.len() >= OUTPUT_BUFFER,
+        }
+    }
+}
+
+/// Hands writes to the thread. Flushing waits for it to catch up, so
+/// anything written to stderr afterwards comes after the output.
+pub struct Writer {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Writer {
+    /// Starts a thread writing to `out`, holding output back at most
+    /// `interval` with the batched policy.
+    pub fn spawn(out: Box<dyn Write + Send>, policy: FlushPolicy, interval: Duration) -> Writer {
+        let shared = Arc::new(Shared {
+            policy,
+            state: Mutex::default(),
+            work: Condvar::new(),
+            progress: Condvar::new(),
+        });
+        let thread = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || run(&shared, out, interval))
+        };
+        Writer { shared, thread: Some(thread) }
+    }
+
+    /// Flushes what has been written, unless the policy is to wait for a
+    /// full buffer.
+    pub fn urgent(&mut self) {
+        if self.shared.policy != FlushPolicy::Full {
+            self.shared.state().urgent = true;
+            self.shared.work.notify_one();
+        }
+    }
+
+    /// Whether whatever reads the output has gone.
+    pub fn closed(&self) -> bool {
+        self.shared.state().closed
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.shared.state();
+        while state.pending.len() >= BACKLOG && !state.closed {
+            self.shared.work.notify_one();
+            state = self.shared.progress.wait(state).unwrap_or_else(|err| err.into_inner());
+        }
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        state.pending.extend_from_slice(buf);
+        if state.idle && self.shared.ready(&state, None) {
+            state.idle = false;
+            drop(state);
+            self.shared.work.notify_one();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.shared.state();
+        state.flushes += 1;
+        let flush = state.flushes;
+        self.shared.work.notify_one();
+        while state.flushed < flush && !state.closed {
+            state = self.shared.progress.wait(state).unwrap_or_else(|err| err.into_inner());
+        }
+        match (state.closed, state.error) {
+            (true, _) => Err(io::ErrorKind::BrokenPipe.into()),
+            (false, Some(kind)) => Err(kind.into()),
+            (false, None) => Ok(()),
+        }
+    }
+}
+
+impl Drop for Writer {
+    /// Waits for everything written to go out.
+    fn drop(&mut self) {
+        self.shared.state().done = true;
+        self.shared.work.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(shared: &Shared, out: Box<dyn Write + Send>, interval: Duration) {
+    let mut out = BufWriter::with_capacity(OUTPUT_BUFFER, out);
+    let mut bytes = Vec::new();
+    // When what is written has to go out by, under the batched policy.
+    let mut due: Option<Instant> = None;
+    loop {
+        let mut state = shared.state();
+        while !shared.ready(&state, due) {
+            state.idle = due.is_none();
+            state = match due {
+                Some(due) => {
+                    let wait = due.saturating_duration_since(Instant::now());
+                    shared.work.wait_timeout(state, wait).unwrap_or_else(|err| err.into_inner()).0
+                }
+                None => shared.work.wait(state).unwrap_or_else(|err| err.into_inner()),
+            };
+            state.idle = false;
+        }
+        let asked = state.urgent || state.flushes > state.flushed || state.done;
+        if shared.policy == FlushPolicy::Batched && due.is_none() && !asked {
+            // The first write since the last flush starts the clock.
+            due = Some(Instant::now() + interval);
+            continue;
+        }
+        bytes.clear();
+        std::mem::swap(&mut bytes, &mut state.pending);
+        state.urgent = false;
+        let (flushes, done) = (state.flushes, state.done);
+        let flush = shared.policy != FlushPolicy::Full || flushes > state.flushed || done;
+        drop(state);
+        shared.progress.notify_all();
+
+        let written = out.write_all(&bytes);
+        if flush || written.is_err() {
+            let flushed = if flush { written.and_then(|_| out.flush()) } else { written };
+            due = None;
+            let mut state = shared.state();
+            state.flushed = flushes;
+            state.error = flushed.as_ref().err().map(io::Error::kind);
+            state.closed |= state.error == Some(io::ErrorKind::BrokenPipe);
+            drop(state);
+            shared.progress.notify_all();
+        }
+        if done {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Output shared with the test, as it has been flushed.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+
+        /// Waits a while for `text` to be written.
+        fn wait_for(&self, text: &str) -> bool {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(5) {
+                if self.text() == text {
+                    return true;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            false
+        }
+    }
+
+    #[test]
+    fn test_batched() {
+        let out = Shared::default();
+        let mut writer = Writer::spawn(Box::new(out.clone()), FlushPolicy::Batched, Duration::from_millis(200));
+        writeln!(writer, "one").unwrap();
+        assert_eq!(out.text(), "");
+        assert!(out.wait_for("one\n"));
+
+        let mut writer = Writer::spawn(Box::new(out.clone()), FlushPolicy::Batched, Duration::from_secs(3600));
+        writeln!(writer, "two").unwrap();
+        writer.urgent();
+        assert!(out.wait_for("one\ntwo\n"));
+    }
+
+    #[test]
+    fn test_immediate() {
+        let out = Shared::default();
+        let mut writer = Writer::spawn(Box::new(out.clone()), FlushPolicy::Immediate, Duration::from_secs(3600));
+        write!(writer, "one").unwrap();
+        assert!(out.wait_for("one"));
+    }
+
+    #[test]
+    fn test_full() {
+        let out = Shared::default();
+        let mut writer = Writer::spawn(Box::new(out.clone()), FlushPolicy::Full, Duration::ZERO);
+        writeln!(writer, "one").unwrap();
+        writer.urgent();
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(out.text(), "");
+        writer.flush().unwrap();
+        assert_eq!(out.text(), "one\n");
+        writeln!(writer, "two").unwrap();
+        drop(writer);
+        assert_eq!(out.text(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_closed() {
+        struct Gone;
+        impl Write for Gone {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut writer = Writer::spawn(Box::new(Gone), FlushPolicy::Immediate, Duration::ZERO);
+        let _ = writeln!(writer, "one");
+        assert!(writer.flush().is_err());
+        assert!(writer.closed());
+        assert!(writeln!(writer, "two").is_err());
+    }
+}